@@ -0,0 +1,111 @@
+use super::ai_channel::TerminalConfig;
+
+// Defaults for a general purpose IEPE accelerometer
+const SENSITIVITY: f64 = 100.0;			// [mV/g]
+const EXCITATION_CURRENT: f64 = 0.004;	// [A]
+const ACCEL_SPAN: f64 = 50.0;			// [g]
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccelUnits {
+	G,
+	MetersPerSecondSquared,
+}
+
+impl AccelUnits {
+	pub(crate) fn as_daqmx(self) -> i32 {
+		match self {
+			AccelUnits::G => nidaqmx_sys::DAQmx_Val_g as i32,
+			AccelUnits::MetersPerSecondSquared => {
+				nidaqmx_sys::DAQmx_Val_MetersPerSecondSquared as i32
+			}
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coupling {
+	// Removes the sensor's DC bias, the usual choice for IEPE sensors
+	Ac,
+	Dc,
+}
+
+impl Coupling {
+	pub(crate) fn as_daqmx(self) -> i32 {
+		match self {
+			Coupling::Ac => nidaqmx_sys::DAQmx_Val_AC as i32,
+			Coupling::Dc => nidaqmx_sys::DAQmx_Val_DC as i32,
+		}
+	}
+}
+
+// An IEPE accelerometer on one (or a range of) physical channels, read in acceleration units
+#[derive(Debug, Clone)]
+pub struct AccelChannel {
+	// Relative to the device, e.g. "ai0" or "ai0:2"
+	pub physical_channel: String,
+	// Attributes like the coupling are set through this name when given
+	pub name: Option<String>,
+	pub min_val: f64,
+	pub max_val: f64,
+	pub units: AccelUnits,
+	pub terminal_config: TerminalConfig,
+	pub sensitivity: f64,					// [mV/g]
+	pub excitation_current: Option<f64>,	// [A], None for externally powered sensors
+	pub coupling: Coupling,
+}
+
+impl AccelChannel {
+	pub fn new<S: Into<String>>(physical_channel: S) -> Self {
+		AccelChannel {
+			physical_channel: physical_channel.into(),
+			name: None,
+			min_val: -ACCEL_SPAN,
+			max_val: ACCEL_SPAN,
+			units: AccelUnits::G,
+			terminal_config: TerminalConfig::Default,
+			sensitivity: SENSITIVITY,
+			excitation_current: Some(EXCITATION_CURRENT),
+			coupling: Coupling::Ac,
+		}
+	}
+
+	pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+		self.name = Some(name.into());
+		self
+	}
+
+	// Expected acceleration range in the channel's units
+	pub fn range(mut self, min_val: f64, max_val: f64) -> Self {
+		self.min_val = min_val;
+		self.max_val = max_val;
+		self
+	}
+
+	// g by default
+	pub fn units(mut self, units: AccelUnits) -> Self {
+		self.units = units;
+		self
+	}
+
+	pub fn terminal_config(mut self, terminal_config: TerminalConfig) -> Self {
+		self.terminal_config = terminal_config;
+		self
+	}
+
+	// From the sensor's calibration sheet [mV/g]
+	pub fn sensitivity(mut self, sensitivity: f64) -> Self {
+		self.sensitivity = sensitivity;
+		self
+	}
+
+	// Internal 4 mA by default, None turns the current source off
+	pub fn excitation_current(mut self, amps: Option<f64>) -> Self {
+		self.excitation_current = amps;
+		self
+	}
+
+	pub fn coupling(mut self, coupling: Coupling) -> Self {
+		self.coupling = coupling;
+		self
+	}
+}
@@ -1,226 +1,519 @@
 use super::{
+	ai_accel_channel::AccelChannel,
+	ai_force_channel::ForceBridgeChannel,
+	async_channel::{AsyncChannel, StreamChannel, StreamRead, StreamSetup},
+	backend::{self, Backend},
+	blocking::BlockingRead,
+	error::DaqError,
+	physical_chan_desc,
+	task_control::TaskControl,
 	task_handle::{RawTaskHandle, TaskHandle},
-	DAQ_CALLBACK_FREQ, SAMPLE_TIMEOUT_SECS, SCAN_WARNING,
-	// get_steady_time_nanoseconds, CALLBACK_PERIOD,
+	terminal_desc,
+	timing::{SampleMode, TimeAxis},
+	trigger::{AnalogTrigger, Arm, ReferenceTrigger, StartTrigger},
+	get_steady_time_nanoseconds, DAQ_CALLBACK_FREQ, SAMPLE_TIMEOUT_SECS,
 };
 
-use std::{fmt, ptr};
+use std::{fmt, time::Duration};
 
-use futures::{
-	stream::Stream,
-	sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
-	Poll,
-};
-
-const NUM_CHANNELS: usize = 2;
 const VOLTAGE_SPAN: f64 = 10.0;
 
-type RawScanData = [f64; NUM_CHANNELS];
-
-static mut AI_CALLBACK_INIT_TIME: Option<u64> = None;		// Initial timestamp [ns]
-static mut AI_CALLBACKS: u64 = 0;				// # of analog callbacks for this stream
+type RawScanData = Box<[f64]>;
 
+// Scans are stored interleaved, one row of num_channels samples after another
 struct BatchedScan {
-	data: Box<[RawScanData]>,
-	timestamp: u64,
+	data: Box<[f64]>,
+	num_channels: usize,
+	last_sample: u64,	// Number of the batch's last scan
 }
 
 impl BatchedScan {
-	unsafe fn new_uninit(batch_size: usize) -> Self {
-		let boxed: Box<[RawScanData]> =
-			(0..batch_size).map(|_| std::mem::uninitialized()).collect();
-
+	fn new(batch_size: usize, num_channels: usize) -> Self {
 		Self {
-			data: boxed,
-			timestamp: 0,
+			data: vec![0.0; batch_size * num_channels].into_boxed_slice(),
+			num_channels,
+			last_sample: 0,
 		}
 	}
 
-	fn as_scan_iter<'a>(&'a self, _sample_rate: usize) -> impl Iterator<Item = ScanData> + 'a {
-		// const TO_NANOSEC: u64 = 1e9 as u64;
-
-		let base_ts = self.timestamp;
-		let data_len = self.data.len() as u32;
-
-		let tstamp =
-			// (0..data_len).map(move |ind| base_ts - ind as u64 * TO_NANOSEC / _sample_rate as u64);
-			(0..data_len).map(move |ind| base_ts - ind as u64);
+	// Host timestamps taken in the callbacks jitter by milliseconds, so scans are timed by their
+	// sample number on the sample clock instead
+	fn as_scan_iter(&self, time_axis: TimeAxis) -> impl Iterator<Item = ScanData> + '_ {
+		let n_scans = (self.data.len() / self.num_channels) as u64;
 
 		self.data
-			.iter()
-			.rev()
-			.zip(tstamp)
-			.map(|(data, ts)| ScanData::new(*data, ts))
-			.rev()
+			.chunks(self.num_channels)
+			.zip(self.last_sample + 1 - n_scans..)
+			.map(move |(data, sample)| ScanData::new(data.into(), sample, time_axis.seconds(sample)))
 	}
 }
 
 #[derive(Debug)]
 pub struct ScanData {
 	pub data: RawScanData,
-	pub timestamp: u64,
+	// Counts from 1 at the start of the acquisition
+	pub sample: u64,
+	// Since the first sample, from the coerced sample clock rate [s]
+	pub time: f64,
 }
 
 impl ScanData {
-	fn new(data: [f64; NUM_CHANNELS], timestamp: u64) -> Self {
-		ScanData { data, timestamp }
+	fn new(data: RawScanData, sample: u64, time: f64) -> Self {
+		ScanData { data, sample, time }
 	}
 }
 
 impl fmt::Display for ScanData {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(f, "{},{},{}", self.timestamp, self.data[0], self.data[1])
+		write!(f, "{},{}", self.sample, self.time)?;
+
+		for val in self.data.iter() {
+			write!(f, ",{}", val)?;
+		}
+
+		Ok(())
+	}
+}
+
+// Pre- and post-trigger samples of a reference triggered acquisition, numbered from 1 like
+// the other reads
+#[derive(Debug)]
+pub struct TriggeredCapture {
+	pub scans: Vec<ScanData>,
+	// Index in `scans` of the sample the trigger occured on, the ones before it are pretrigger
+	pub trigger_index: usize,
+}
+
+impl TriggeredCapture {
+	pub fn pretrigger(&self) -> &[ScanData] {
+		&self.scans[..self.trigger_index]
+	}
+
+	// Starts with the trigger sample
+	pub fn posttrigger(&self) -> &[ScanData] {
+		&self.scans[self.trigger_index..]
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TerminalConfig {
+	// Let DAQmx pick the device default
+	Default,
+	Rse,
+	Nrse,
+	Differential,
+	PseudoDifferential,
+}
+
+impl TerminalConfig {
+	pub(crate) fn as_daqmx(self) -> i32 {
+		match self {
+			TerminalConfig::Default => nidaqmx_sys::DAQmx_Val_Cfg_Default,
+			TerminalConfig::Rse => nidaqmx_sys::DAQmx_Val_RSE as i32,
+			TerminalConfig::Nrse => nidaqmx_sys::DAQmx_Val_NRSE as i32,
+			TerminalConfig::Differential => nidaqmx_sys::DAQmx_Val_Diff as i32,
+			TerminalConfig::PseudoDifferential => nidaqmx_sys::DAQmx_Val_PseudoDiff as i32,
+		}
+	}
+}
+
+// A voltage input on one (or a range of) physical channels, e.g. "Dev1/ai0" or "Dev1/ai0:3"
+#[derive(Debug, Clone)]
+pub struct AiVoltageChannel {
+	// Relative to the device, e.g. "ai0" or "ai0:3"
+	pub physical_channel: String,
+	pub name: Option<String>,
+	pub min_val: f64,
+	pub max_val: f64,
+	pub terminal_config: TerminalConfig,
+}
+
+impl AiVoltageChannel {
+	pub fn new<S: Into<String>>(physical_channel: S) -> Self {
+		AiVoltageChannel {
+			physical_channel: physical_channel.into(),
+			name: None,
+			min_val: -VOLTAGE_SPAN,
+			max_val: VOLTAGE_SPAN,
+			terminal_config: TerminalConfig::Default,
+		}
+	}
+
+	pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+		self.name = Some(name.into());
+		self
+	}
+
+	// Expected input range [V], DAQmx picks the smallest gain that covers it
+	pub fn range(mut self, min_val: f64, max_val: f64) -> Self {
+		self.min_val = min_val;
+		self.max_val = max_val;
+		self
+	}
+
+	pub fn terminal_config(mut self, terminal_config: TerminalConfig) -> Self {
+		self.terminal_config = terminal_config;
+		self
+	}
+}
+
+// Any of the input channel types an AiChannel can be built from. The samples come back in the
+// channel's own units, e.g. volts, newtons or g.
+#[derive(Debug, Clone)]
+pub enum AiChannelConfig {
+	Voltage(AiVoltageChannel),
+	ForceBridge(ForceBridgeChannel),
+	Accel(AccelChannel),
+}
+
+impl From<AiVoltageChannel> for AiChannelConfig {
+	fn from(chan: AiVoltageChannel) -> Self {
+		AiChannelConfig::Voltage(chan)
+	}
+}
+
+impl From<ForceBridgeChannel> for AiChannelConfig {
+	fn from(chan: ForceBridgeChannel) -> Self {
+		AiChannelConfig::ForceBridge(chan)
+	}
+}
+
+impl From<AccelChannel> for AiChannelConfig {
+	fn from(chan: AccelChannel) -> Self {
+		AiChannelConfig::Accel(chan)
+	}
+}
+
+impl AiChannelConfig {
+	fn create(&self, task_handle: &mut TaskHandle, device: &str) -> Result<(), DaqError> {
+		match self {
+			AiChannelConfig::Voltage(chan) => task_handle.create_ai_volt_chan(
+				&physical_chan_desc(device, &chan.physical_channel),
+				chan.name.as_ref().map_or("", String::as_str),
+				chan.terminal_config.as_daqmx(),
+				chan.min_val,
+				chan.max_val,
+			),
+			AiChannelConfig::ForceBridge(chan) => task_handle.create_ai_force_bridge_chan(
+				&physical_chan_desc(device, &chan.physical_channel),
+				chan,
+			),
+			AiChannelConfig::Accel(chan) => task_handle.create_ai_accel_chan(
+				&physical_chan_desc(device, &chan.physical_channel),
+				chan,
+			),
+		}
+	}
+}
+
+// A task has at most one start trigger
+#[derive(Debug, Clone)]
+enum AiStartTrigger {
+	Digital(StartTrigger),
+	Analog(AnalogTrigger),
+}
+
+pub struct AiChannelBuilder {
+	backend: Backend,
+	device: String,
+	clk_src: String,
+	sample_rate: usize,
+	channels: Vec<AiChannelConfig>,
+	start_trigger: Option<AiStartTrigger>,
+	reference_trigger: Option<ReferenceTrigger>,
+	sample_mode: SampleMode,
+}
+
+impl AiChannelBuilder {
+	pub fn backend(mut self, backend: Backend) -> Self {
+		self.backend = backend;
+		self
+	}
+
+	// Channels are read in the order they are added
+	pub fn channel<C: Into<AiChannelConfig>>(mut self, channel: C) -> Self {
+		self.channels.push(channel.into());
+		self
+	}
+
+	// Acquisition waits for this edge after the task is launched
+	pub fn start_trigger(mut self, trigger: StartTrigger) -> Self {
+		self.start_trigger = Some(AiStartTrigger::Digital(trigger));
+		self
+	}
+
+	// Acquisition waits for the signal to meet the condition, replaces a digital start trigger
+	pub fn analog_start_trigger(mut self, trigger: AnalogTrigger) -> Self {
+		self.start_trigger = Some(AiStartTrigger::Analog(trigger));
+		self
+	}
+
+	// Makes the acquisition finite, with the pre- and post-trigger samples as its length.
+	// Read it with `capture()`.
+	pub fn reference_trigger(mut self, trigger: ReferenceTrigger) -> Self {
+		self.reference_trigger = Some(trigger);
+		self
+	}
+
+	// Continuous by default, a finite acquisition stops after the given number of samples
+	pub fn sample_mode(mut self, sample_mode: SampleMode) -> Self {
+		self.sample_mode = sample_mode;
+		self
+	}
+
+	pub fn build(self) -> Result<AiChannel, DaqError> {
+		let task_handle = TaskHandle::with_backend(self.backend)?;
+
+		let sample_mode = match &self.reference_trigger {
+			Some(trigger) => {
+				SampleMode::Finite(trigger.pretrigger_samples + trigger.posttrigger_samples)
+			}
+			None => self.sample_mode,
+		};
+
+		let mut ai_channel = AiChannel {
+			task_handle,
+			sample_rate: self.sample_rate,
+			time_axis: TimeAxis::new(self.sample_rate as f64),
+			batch_size: self.sample_rate / DAQ_CALLBACK_FREQ,
+			num_channels: 0,
+			samples_read: 0,
+			sample_mode,
+			pretrigger_samples: None,
+		};
+
+		ai_channel.init(&self.device, &self.clk_src, &self.channels)?;
+
+		match &self.start_trigger {
+			Some(AiStartTrigger::Digital(trigger)) => ai_channel.task_handle.configure_start_trigger(
+				&trigger.source.terminal_desc(&self.device),
+				trigger.edge.as_daqmx(),
+			)?,
+			Some(AiStartTrigger::Analog(trigger)) => {
+				ai_channel.task_handle.configure_analog_start_trigger(trigger)?
+			}
+			None => (),
+		}
+
+		if let Some(trigger) = &self.reference_trigger {
+			ai_channel
+				.task_handle
+				.configure_reference_trigger(&trigger.trigger, trigger.pretrigger_samples as u32)?;
+			ai_channel.pretrigger_samples = Some(trigger.pretrigger_samples);
+		}
+
+		Ok(ai_channel)
 	}
 }
 
 pub struct AiChannel {
 	task_handle: TaskHandle,
 	sample_rate: usize,
+	time_axis: TimeAxis,
 	batch_size: usize,
+	num_channels: usize,
+	samples_read: u64,	// # of samples per channel read with BlockingRead
+	sample_mode: SampleMode,
+	pretrigger_samples: Option<u64>,	// Set when there's a reference trigger
 }
 
 impl AiChannel {
-	pub fn new<S1: AsRef<str>, S2: AsRef<str>>(clk_src: S1, dev: S2, sample_rate: usize) -> Self {
-		let task_handle = TaskHandle::new();
-
-		let mut ai_channel = AiChannel {
-			task_handle,
+	// `clk_src` is a terminal of the device like "PFI13", or empty for the onboard clock
+	pub fn builder<S1: Into<String>, S2: Into<String>>(
+		device: S1,
+		clk_src: S2,
+		sample_rate: usize,
+	) -> AiChannelBuilder {
+		AiChannelBuilder {
+			backend: backend::ni_daqmx(),
+			device: device.into(),
+			clk_src: clk_src.into(),
 			sample_rate,
-			batch_size: sample_rate / DAQ_CALLBACK_FREQ,
-		};
+			channels: Vec::new(),
+			start_trigger: None,
+			reference_trigger: None,
+			sample_mode: SampleMode::Continuous,
+		}
+	}
 
-		ai_channel.init(clk_src.as_ref(), dev.as_ref());
+	pub fn new<S1: AsRef<str>, S2: AsRef<str>, S3: AsRef<str>>(
+		device: S1,
+		clk_src: S2,
+		physical_channels: S3,
+		sample_rate: usize,
+	) -> Result<Self, DaqError> {
+		Self::with_backend(backend::ni_daqmx(), device, clk_src, physical_channels, sample_rate)
+	}
 
-		ai_channel
+	pub fn with_backend<S1: AsRef<str>, S2: AsRef<str>, S3: AsRef<str>>(
+		backend: Backend,
+		device: S1,
+		clk_src: S2,
+		physical_channels: S3,
+		sample_rate: usize,
+	) -> Result<Self, DaqError> {
+		Self::builder(device.as_ref(), clk_src.as_ref(), sample_rate)
+			.backend(backend)
+			.channel(AiVoltageChannel::new(physical_channels.as_ref()))
+			.build()
 	}
 
-	fn init(&mut self, clk_src: &str, dev: &str) {
+	fn init(
+		&mut self,
+		device: &str,
+		clk_src: &str,
+		channels: &[AiChannelConfig],
+	) -> Result<(), DaqError> {
 		let internal_buf_size = 10 * self.sample_rate as u64;
 
-		self.task_handle
-			.create_ai_volt_chan(dev, VOLTAGE_SPAN);
+		for chan in channels {
+			chan.create(&mut self.task_handle, device)?;
+		}
+
+		// Let DAQmx expand ranges like "Dev1/ai0:3" for us
+		self.num_channels = self.task_handle.num_channels()?;
 
+		let (sample_mode, samps_per_chan) = self.sample_mode.as_daqmx(internal_buf_size);
 		self.task_handle.configure_sample_clock(
-			clk_src,
+			&terminal_desc(device, clk_src),
 			self.sample_rate as f64,
-			internal_buf_size,
-		);
+			sample_mode,
+			samps_per_chan,
+		)?;
+
+		self.time_axis = TimeAxis::new(self.task_handle.sample_clock_rate()?);
+
+		Ok(())
 	}
 
-	pub fn make_async(mut self) -> AsyncAiChannel {
-		let (snd, recv) = mpsc::unbounded();
+	pub fn num_channels(&self) -> usize {
+		self.num_channels
+	}
 
-		let internal = AsyncAiChanInternal {
-			sender: snd,
-			sample_rate: self.sample_rate,
-		};
+	// Coerced sample clock rate, and when the task was last launched
+	pub fn time_axis(&self) -> TimeAxis {
+		self.time_axis
+	}
 
-		unsafe
-		{
-			// Save the initial time, and initialize the "callback counter"
-			AI_CALLBACK_INIT_TIME = None;
-			AI_CALLBACKS = 0;
-
-			self.task_handle.register_read_callback(
-				self.batch_size as u32,
-				async_read_callback_impl,
-				internal,
-			);
-			// We dont care about the done callback
-			self.task_handle.register_done_callback(|_| (), ());
-		}
+	// Needed before reading with BlockingRead, streams launch through their own handle. A
+	// relaunched task counts its samples from 1 again.
+	pub fn launch_task(&mut self) -> Result<(), DaqError> {
+		self.task_handle.start()?;
+		self.time_axis.start = Some(get_steady_time_nanoseconds());
+		self.samples_read = 0;
 
-		AsyncAiChannel
-		{
-			_ai_chan: self,
-			recv,
-		}
+		Ok(())
 	}
-}
 
-struct AsyncAiChanInternal {
-	sender: UnboundedSender<ScanData>,
-	sample_rate: usize,
-}
+	// Launches a finite acquisition and waits for all of it
+	pub fn acquire(&mut self) -> Result<Vec<ScanData>, DaqError> {
+		let n_samps = self.sample_mode.finite_samps()?;
+		let timeout = n_samps as f64 / self.sample_rate as f64 + SAMPLE_TIMEOUT_SECS;
 
-pub struct AsyncAiChannel {
-	_ai_chan: AiChannel,
-	recv: UnboundedReceiver<ScanData>,
-}
+		self.launch_task()?;
+		self.read_to_end(Duration::from_secs_f64(timeout))
+	}
 
-impl AsyncAiChannel
-{
-	pub fn launch_task(&mut self) -> ()
-	{
-		self._ai_chan.task_handle.launch();
+	// Launches a reference triggered acquisition and waits up to `timeout` for the trigger and
+	// the samples after it
+	pub fn capture(&mut self, timeout: Duration) -> Result<TriggeredCapture, DaqError> {
+		let pretrigger_samples = self.pretrigger_samples.ok_or_else(|| {
+			DaqError::new(
+				nidaqmx_sys::DAQmxErrorNoRefTrigConfigured,
+				"Only reference triggered acquisitions can be captured.",
+				"capture",
+			)
+		})?;
+
+		self.launch_task()?;
+		let scans = self.read_to_end(timeout)?;
+
+		Ok(TriggeredCapture {
+			scans,
+			trigger_index: pretrigger_samples as usize,
+		})
 	}
 }
 
-impl Stream for AsyncAiChannel {
-	type Item = ScanData;
-	type Error = ();
+impl Arm for AiChannel {
+	fn arm(&mut self) -> Result<(), DaqError> {
+		self.launch_task()
+	}
+}
 
-	#[inline]
-	fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-		self.recv.poll()
+impl TaskControl for AiChannel {
+	fn task_handle(&mut self) -> &mut TaskHandle {
+		&mut self.task_handle
 	}
 }
 
-unsafe fn read_analog_f64(
-	task_handle: &mut RawTaskHandle,
-	n_samps: u32,
-) -> Result<BatchedScan, i32> {
-	let mut samps_read = 0i32;
-	let samps_read_ptr = &mut samps_read as *mut _;
+impl BlockingRead for AiChannel {
+	type Sample = ScanData;
 
-	let mut scan = BatchedScan::new_uninit(n_samps as usize);
+	fn read(&mut self, n_samps: usize, timeout: Duration) -> Result<Vec<ScanData>, DaqError> {
+		let mut batch = BatchedScan::new(n_samps, self.num_channels);
 
-	AI_CALLBACKS += 1;
-	// if AI_CALLBACK_INIT_TIME == None
-	// {
-	// 	AI_CALLBACK_INIT_TIME = Some(get_steady_time_nanoseconds());
-	// };
+		let n_read =
+			self.task_handle.read_analog_f64(n_samps, timeout.as_secs_f64(), &mut batch.data)?;
+		if n_read < n_samps {
+			batch.data = batch.data[..n_read * self.num_channels].into();
+		}
 
-	// scan.timestamp = AI_CALLBACK_INIT_TIME.unwrap() + ENCODER_CALLBACKS * CALLBACK_PERIOD;
-	// scan.timestamp = get_steady_time_nanoseconds();	// Actual timestamp (jittery)
-	scan.timestamp = AI_CALLBACKS * n_samps as u64;	// Sample count
+		self.samples_read += n_read as u64;
+		batch.last_sample = self.samples_read;	// Sample count, same as the streams
 
-	let buf_len = (scan.data.len() * NUM_CHANNELS) as u32;
-	let buf_ptr = scan.data.as_mut_ptr() as *mut _ as *mut f64;
+		Ok(batch.as_scan_iter(self.time_axis).collect())
+	}
 
-	let err_code = nidaqmx_sys::DAQmxReadAnalogF64(
-		task_handle.get().as_ptr(),
-		n_samps as i32,
-		SAMPLE_TIMEOUT_SECS,
-		nidaqmx_sys::DAQmx_Val_GroupByScanNumber,
-		buf_ptr,
-		buf_len,
-		samps_read_ptr,
-		ptr::null_mut(),
-	);
+	fn sample_mode(&self) -> SampleMode {
+		self.sample_mode
+	}
 
-	match err_code {
-		0 if samps_read == n_samps as i32 => Ok(scan),
-		0 => Err(SCAN_WARNING),
-		_ => Err(err_code),
+	fn samples_read(&self) -> u64 {
+		self.samples_read
 	}
 }
 
-fn async_read_callback_impl(
-	scan_chan: &mut AsyncAiChanInternal,
-	task_handle: &mut RawTaskHandle,
-	n_samps: u32,
-) -> Result<(), ()> {
-	let send_channel = &scan_chan.sender;
-	let sample_rate = scan_chan.sample_rate;
+// Scans of a stream, timed like the BlockingRead ones
+pub struct AiReader {
+	num_channels: usize,
+	time_axis: TimeAxis,	// Only the rate is used, so it needn't be launched yet
+}
+
+impl StreamRead for AiReader {
+	type Sample = ScanData;
+	const READ_CALL: &'static str = "DAQmxReadAnalogF64";
 
-	let batch = unsafe { read_analog_f64(task_handle, n_samps) }
-		.map_err(|err_code| task_handle.chk_err_code(err_code))?;
+	fn read(
+		&mut self,
+		task_handle: &mut RawTaskHandle,
+		n_samps: u32,
+		first: u64,
+	) -> Result<Vec<ScanData>, DaqError> {
+		let mut batch = BatchedScan::new(n_samps as usize, self.num_channels);
 
-	batch
-		.as_scan_iter(sample_rate)
-		.try_for_each(|scan| send_channel.unbounded_send(scan))
-		.map_err(|_| ())?;
+		let n_read =
+			task_handle.read_analog_f64(n_samps as usize, SAMPLE_TIMEOUT_SECS, &mut batch.data)?;
+		batch.data = batch.data[..n_read * self.num_channels].into();
+		batch.last_sample = first + n_read as u64 - 1;	// Sample count
 
-	Ok(())
+		Ok(batch.as_scan_iter(self.time_axis).collect())
+	}
 }
+
+impl StreamChannel for AiChannel {
+	type Reader = AiReader;
+
+	fn stream_setup(&self) -> Result<StreamSetup<AiReader>, DaqError> {
+		Ok(StreamSetup {
+			reader: AiReader {
+				num_channels: self.num_channels,
+				time_axis: self.time_axis,
+			},
+			batch_size: self.batch_size as u32,
+			sample_mode: self.sample_mode,
+		})
+	}
+}
+
+pub type AsyncAiChannel = AsyncChannel<AiChannel>;
@@ -0,0 +1,169 @@
+// Defaults for a typical 350 ohm full-bridge load cell
+const NOMINAL_BRIDGE_RESISTANCE: f64 = 350.0;	// [ohm]
+const EXCITATION_VOLTAGE: f64 = 2.5;			// [V]
+const FORCE_SPAN: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForceUnits {
+	Newtons,
+	KilogramForce,
+	Pounds,
+}
+
+impl ForceUnits {
+	pub(crate) fn as_daqmx(self) -> i32 {
+		match self {
+			ForceUnits::Newtons => nidaqmx_sys::DAQmx_Val_Newtons as i32,
+			ForceUnits::KilogramForce => nidaqmx_sys::DAQmx_Val_KilogramForce as i32,
+			ForceUnits::Pounds => nidaqmx_sys::DAQmx_Val_Pounds as i32,
+		}
+	}
+}
+
+// How many of the bridge's arms are active gauges
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BridgeConfig {
+	Full,
+	Half,
+	Quarter,
+}
+
+impl BridgeConfig {
+	pub(crate) fn as_daqmx(self) -> i32 {
+		match self {
+			BridgeConfig::Full => nidaqmx_sys::DAQmx_Val_FullBridge as i32,
+			BridgeConfig::Half => nidaqmx_sys::DAQmx_Val_HalfBridge as i32,
+			BridgeConfig::Quarter => nidaqmx_sys::DAQmx_Val_QuarterBridge as i32,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExcitationSource {
+	Internal,
+	External,
+}
+
+impl ExcitationSource {
+	pub(crate) fn as_daqmx(self) -> i32 {
+		match self {
+			ExcitationSource::Internal => nidaqmx_sys::DAQmx_Val_Internal as i32,
+			ExcitationSource::External => nidaqmx_sys::DAQmx_Val_External as i32,
+		}
+	}
+}
+
+// Calibration from the bridge output [mV/V] to force in the channel's units, usually straight
+// off the load cell's calibration sheet
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForceBridgeScale {
+	// Two (bridge output, force) points on a straight line
+	TwoPointLinear {
+		first: (f64, f64),
+		second: (f64, f64),
+	},
+	// Force from the bridge output, and the reverse, as coefficients from the constant term up
+	Polynomial {
+		forward: Vec<f64>,
+		reverse: Vec<f64>,
+	},
+	// Interpolated linearly between the points
+	Table {
+		electrical: Vec<f64>,
+		physical: Vec<f64>,
+	},
+}
+
+// A strain-gauge load cell on one (or a range of) physical channels, read in force units
+#[derive(Debug, Clone)]
+pub struct ForceBridgeChannel {
+	// Relative to the device, e.g. "ai0" or "ai0:1"
+	pub physical_channel: String,
+	pub name: Option<String>,
+	pub min_val: f64,
+	pub max_val: f64,
+	pub units: ForceUnits,
+	pub bridge_config: BridgeConfig,
+	pub excitation_source: ExcitationSource,
+	pub excitation_val: f64,			// [V]
+	pub nominal_resistance: f64,		// [ohm]
+	pub scale: ForceBridgeScale,
+}
+
+impl ForceBridgeChannel {
+	pub fn new<S: Into<String>>(physical_channel: S, scale: ForceBridgeScale) -> Self {
+		ForceBridgeChannel {
+			physical_channel: physical_channel.into(),
+			name: None,
+			min_val: -FORCE_SPAN,
+			max_val: FORCE_SPAN,
+			units: ForceUnits::Newtons,
+			bridge_config: BridgeConfig::Full,
+			excitation_source: ExcitationSource::Internal,
+			excitation_val: EXCITATION_VOLTAGE,
+			nominal_resistance: NOMINAL_BRIDGE_RESISTANCE,
+			scale,
+		}
+	}
+
+	// `first` and `second` are (bridge output [mV/V], force) pairs
+	pub fn two_point_lin<S: Into<String>>(
+		physical_channel: S,
+		first: (f64, f64),
+		second: (f64, f64),
+	) -> Self {
+		Self::new(physical_channel, ForceBridgeScale::TwoPointLinear { first, second })
+	}
+
+	pub fn polynomial<S: Into<String>>(
+		physical_channel: S,
+		forward: Vec<f64>,
+		reverse: Vec<f64>,
+	) -> Self {
+		Self::new(physical_channel, ForceBridgeScale::Polynomial { forward, reverse })
+	}
+
+	// `electrical` holds the bridge outputs [mV/V] for the forces in `physical`
+	pub fn table<S: Into<String>>(
+		physical_channel: S,
+		electrical: Vec<f64>,
+		physical: Vec<f64>,
+	) -> Self {
+		Self::new(physical_channel, ForceBridgeScale::Table { electrical, physical })
+	}
+
+	pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+		self.name = Some(name.into());
+		self
+	}
+
+	// Expected force range in the channel's units
+	pub fn range(mut self, min_val: f64, max_val: f64) -> Self {
+		self.min_val = min_val;
+		self.max_val = max_val;
+		self
+	}
+
+	// Units of the samples and of the calibration's force values, newtons by default
+	pub fn units(mut self, units: ForceUnits) -> Self {
+		self.units = units;
+		self
+	}
+
+	pub fn bridge_config(mut self, bridge_config: BridgeConfig) -> Self {
+		self.bridge_config = bridge_config;
+		self
+	}
+
+	// Internal 2.5 V by default
+	pub fn excitation(mut self, source: ExcitationSource, volts: f64) -> Self {
+		self.excitation_source = source;
+		self.excitation_val = volts;
+		self
+	}
+
+	pub fn nominal_resistance(mut self, ohms: f64) -> Self {
+		self.nominal_resistance = ohms;
+		self
+	}
+}
@@ -0,0 +1,378 @@
+use super::{
+	backend::{self, Backend},
+	error::DaqError,
+	physical_chan_desc,
+	task_handle::TaskHandle,
+	terminal_desc,
+	SAMPLE_TIMEOUT_SECS,
+};
+
+use std::{f64::consts::PI, time::Duration};
+
+const VOLTAGE_SPAN: f64 = 10.0;
+// Most samples, over all channels, regenerated from the output buffer (128 MiB)
+const MAX_REGEN_SAMPLES: usize = 1 << 24;
+
+// Signal for an analog output [V]. Periodic waveforms are rounded to a whole number of samples
+// per period, so a single period can be regenerated without a seam.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AoWaveform {
+	Sine {
+		amplitude: f64,
+		frequency: f64,
+		offset: f64,
+	},
+	// At offset + amplitude for `duty_cycle` of every period, offset - amplitude otherwise
+	Square {
+		amplitude: f64,
+		frequency: f64,
+		offset: f64,
+		duty_cycle: f64,
+	},
+	// Played back as is, one sample per sample clock tick
+	Arbitrary(Vec<f64>),
+}
+
+impl AoWaveform {
+	// Samples in one period at `sample_rate`, periodic waveforms need a finite, positive frequency
+	pub fn period_len(&self, sample_rate: usize) -> Result<usize, DaqError> {
+		match self {
+			AoWaveform::Sine { frequency, .. } | AoWaveform::Square { frequency, .. }
+				if !(frequency.is_finite() && *frequency > 0.0) =>
+			{
+				Err(DaqError::new(
+					nidaqmx_sys::DAQmxErrorInvalidAttributeValue,
+					format!("A waveform frequency of {} Hz can't be generated.", frequency),
+					"DAQmxWriteAnalogF64",
+				))
+			}
+			_ => Ok(self.samples_per_period(sample_rate)),
+		}
+	}
+
+	// Sample number `ind` counted from the start of the generation
+	pub fn sample(&self, ind: usize, sample_rate: usize) -> f64 {
+		let period_len = self.samples_per_period(sample_rate);
+		let phase = (ind % period_len) as f64 / period_len as f64;
+
+		match self {
+			AoWaveform::Sine {
+				amplitude, offset, ..
+			} => offset + amplitude * (2.0 * PI * phase).sin(),
+			AoWaveform::Square {
+				amplitude,
+				offset,
+				duty_cycle,
+				..
+			} => {
+				if phase < *duty_cycle {
+					offset + amplitude
+				} else {
+					offset - amplitude
+				}
+			}
+			AoWaveform::Arbitrary(samples) => samples.get(ind % period_len).cloned().unwrap_or(0.0),
+		}
+	}
+
+	// Saturates instead of failing, see `period_len`
+	fn samples_per_period(&self, sample_rate: usize) -> usize {
+		match self {
+			AoWaveform::Sine { frequency, .. } | AoWaveform::Square { frequency, .. } => {
+				((sample_rate as f64 / frequency).round() as usize).max(2)
+			}
+			AoWaveform::Arbitrary(samples) => samples.len().max(1),
+		}
+	}
+}
+
+// A voltage output on one (or a range of) physical channels, e.g. "Dev1/ao0" or "Dev1/ao0:1"
+#[derive(Debug, Clone)]
+pub struct AoVoltageChannel {
+	// Relative to the device, e.g. "ao0" or "ao0:1"
+	pub physical_channel: String,
+	pub name: Option<String>,
+	pub min_val: f64,
+	pub max_val: f64,
+}
+
+impl AoVoltageChannel {
+	pub fn new<S: Into<String>>(physical_channel: S) -> Self {
+		AoVoltageChannel {
+			physical_channel: physical_channel.into(),
+			name: None,
+			min_val: -VOLTAGE_SPAN,
+			max_val: VOLTAGE_SPAN,
+		}
+	}
+
+	pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+		self.name = Some(name.into());
+		self
+	}
+
+	// Output range [V], writing anything outside of it fails
+	pub fn range(mut self, min_val: f64, max_val: f64) -> Self {
+		self.min_val = min_val;
+		self.max_val = max_val;
+		self
+	}
+}
+
+pub struct AoChannelBuilder {
+	backend: Backend,
+	device: String,
+	channels: Vec<AoVoltageChannel>,
+	sample_clock: Option<(String, usize)>,
+}
+
+impl AoChannelBuilder {
+	pub fn backend(mut self, backend: Backend) -> Self {
+		self.backend = backend;
+		self
+	}
+
+	// Each scan holds one sample per channel, in the order the channels are added
+	pub fn channel(mut self, channel: AoVoltageChannel) -> Self {
+		self.channels.push(channel);
+		self
+	}
+
+	// Needed for waveforms, without a sample clock every write is output right away.
+	// `clk_src` is a terminal of the device like "PFI13", or empty for the onboard clock.
+	pub fn sample_clock<S: Into<String>>(mut self, clk_src: S, sample_rate: usize) -> Self {
+		self.sample_clock = Some((clk_src.into(), sample_rate));
+		self
+	}
+
+	pub fn build(self) -> Result<AoChannel, DaqError> {
+		let task_handle = TaskHandle::with_backend(self.backend)?;
+
+		let mut ao_channel = AoChannel {
+			task_handle,
+			num_channels: 0,
+			sample_rate: None,
+			scans_written: 0,
+			launched: false,
+		};
+
+		ao_channel.init(&self.device, &self.channels, self.sample_clock)?;
+
+		Ok(ao_channel)
+	}
+}
+
+pub struct AoChannel {
+	task_handle: TaskHandle,
+	num_channels: usize,
+	sample_rate: Option<usize>,
+	scans_written: u64,	// # of scans written by `write`, so waveforms keep their phase
+	launched: bool,
+}
+
+impl AoChannel {
+	pub fn builder<S: Into<String>>(device: S) -> AoChannelBuilder {
+		AoChannelBuilder {
+			backend: backend::ni_daqmx(),
+			device: device.into(),
+			channels: Vec::new(),
+			sample_clock: None,
+		}
+	}
+
+	// Static outputs on the given channels, see `write_static`
+	pub fn new<S1: AsRef<str>, S2: AsRef<str>>(
+		device: S1,
+		physical_channels: S2,
+	) -> Result<Self, DaqError> {
+		Self::with_backend(backend::ni_daqmx(), device, physical_channels)
+	}
+
+	pub fn with_backend<S1: AsRef<str>, S2: AsRef<str>>(
+		backend: Backend,
+		device: S1,
+		physical_channels: S2,
+	) -> Result<Self, DaqError> {
+		Self::builder(device.as_ref())
+			.backend(backend)
+			.channel(AoVoltageChannel::new(physical_channels.as_ref()))
+			.build()
+	}
+
+	fn init(
+		&mut self,
+		device: &str,
+		channels: &[AoVoltageChannel],
+		sample_clock: Option<(String, usize)>,
+	) -> Result<(), DaqError> {
+		for chan in channels {
+			self.task_handle.create_ao_volt_chan(
+				&physical_chan_desc(device, &chan.physical_channel),
+				chan.name.as_ref().map_or("", String::as_str),
+				chan.min_val,
+				chan.max_val,
+			)?;
+		}
+
+		// Let DAQmx expand ranges like "Dev1/ao0:1" for us
+		self.num_channels = self.task_handle.num_channels()?;
+
+		match sample_clock {
+			Some((clk_src, sample_rate)) => {
+				self.sample_rate = Some(sample_rate);
+
+				let internal_buf_size = 10 * sample_rate as u64;
+				self.task_handle.configure_sample_clock(
+					&terminal_desc(device, &clk_src),
+					sample_rate as f64,
+					nidaqmx_sys::DAQmx_Val_ContSamps as i32,
+					internal_buf_size,
+				)
+			}
+			None => Ok(()),
+		}
+	}
+
+	pub fn num_channels(&self) -> usize {
+		self.num_channels
+	}
+
+	// Sets every channel to its voltage right away, only for channels without a sample clock
+	pub fn write_static(&mut self, voltages: &[f64]) -> Result<(), DaqError> {
+		if voltages.len() != self.num_channels {
+			return Err(num_chans_mismatch());
+		}
+
+		self.task_handle.write_analog_f64(1, true, SAMPLE_TIMEOUT_SECS, voltages)?;
+
+		Ok(())
+	}
+
+	// Outputs one waveform per channel until the channel is dropped. The device regenerates
+	// a buffer holding a whole number of periods of every waveform, so nothing has to be
+	// written after this returns.
+	pub fn generate(&mut self, waveforms: &[AoWaveform]) -> Result<(), DaqError> {
+		let sample_rate = self.clocked_sample_rate()?;
+		if waveforms.len() != self.num_channels {
+			return Err(num_chans_mismatch());
+		}
+
+		let n_scans = waveforms.iter().try_fold(1, |n_scans, waveform| {
+			lcm(n_scans, waveform.period_len(sample_rate)?)
+				.filter(|n_scans| {
+					n_scans
+						.checked_mul(self.num_channels)
+						.is_some_and(|len| len <= MAX_REGEN_SAMPLES)
+				})
+				.ok_or_else(regen_buffer_too_large)
+		})?;
+		let buf = interleave(waveforms, sample_rate, 0, n_scans);
+
+		self.task_handle.set_regeneration(true)?;
+		self.task_handle.write_analog_f64(n_scans, false, SAMPLE_TIMEOUT_SECS, &buf)?;
+		self.launch()
+	}
+
+	// Streams interleaved scans, waiting up to `timeout` for room in the output buffer. The
+	// task starts with the first write and fails if it runs out of samples, so keep writing.
+	pub fn write(&mut self, scans: &[f64], timeout: Duration) -> Result<usize, DaqError> {
+		self.clocked_sample_rate()?;
+		if self.num_channels == 0 || !scans.len().is_multiple_of(self.num_channels) {
+			return Err(num_chans_mismatch());
+		}
+
+		if !self.launched {
+			self.task_handle.set_regeneration(false)?;
+		}
+
+		let n_scans = scans.len() / self.num_channels;
+		let n_written =
+			self.task_handle.write_analog_f64(n_scans, false, timeout.as_secs_f64(), scans)?;
+		self.scans_written += n_written as u64;
+
+		if !self.launched {
+			self.launch()?;
+		}
+
+		Ok(n_written)
+	}
+
+	// Streams the next `n_scans` scans of one waveform per channel, continuing where the last
+	// write left off
+	pub fn write_waveforms(
+		&mut self,
+		waveforms: &[AoWaveform],
+		n_scans: usize,
+		timeout: Duration,
+	) -> Result<usize, DaqError> {
+		let sample_rate = self.clocked_sample_rate()?;
+		if waveforms.len() != self.num_channels {
+			return Err(num_chans_mismatch());
+		}
+		for waveform in waveforms {
+			waveform.period_len(sample_rate)?;
+		}
+
+		let buf = interleave(waveforms, sample_rate, self.scans_written as usize, n_scans);
+		self.write(&buf, timeout)
+	}
+
+	fn launch(&mut self) -> Result<(), DaqError> {
+		self.task_handle.start()?;
+		self.launched = true;
+
+		Ok(())
+	}
+
+	fn clocked_sample_rate(&self) -> Result<usize, DaqError> {
+		self.sample_rate.ok_or_else(|| {
+			DaqError::new(
+				nidaqmx_sys::DAQmxErrorInvalidTimingType,
+				"Waveforms need a channel with a sample clock.",
+				"DAQmxWriteAnalogF64",
+			)
+		})
+	}
+}
+
+// `n_scans` scans of the waveforms interleaved by channel, starting at scan `start`
+fn interleave(
+	waveforms: &[AoWaveform],
+	sample_rate: usize,
+	start: usize,
+	n_scans: usize,
+) -> Vec<f64> {
+	(start..start + n_scans)
+		.flat_map(|ind| waveforms.iter().map(move |waveform| waveform.sample(ind, sample_rate)))
+		.collect()
+}
+
+fn num_chans_mismatch() -> DaqError {
+	DaqError::new(
+		nidaqmx_sys::DAQmxErrorWriteNumChansMismatch,
+		"Number of samples doesn't match the number of channels in the task.",
+		"DAQmxWriteAnalogF64",
+	)
+}
+
+// Whole periods of every waveform would take more memory than makes sense to regenerate
+fn regen_buffer_too_large() -> DaqError {
+	DaqError::new(
+		nidaqmx_sys::DAQmxErrorInvalidAttributeValue,
+		"The waveforms' periods have no common multiple small enough to regenerate.",
+		"DAQmxWriteAnalogF64",
+	)
+}
+
+// None on overflow
+fn lcm(a: usize, b: usize) -> Option<usize> {
+	fn gcd(a: usize, b: usize) -> usize {
+		if b == 0 {
+			a
+		} else {
+			gcd(b, a % b)
+		}
+	}
+
+	(a / gcd(a, b)).checked_mul(b)
+}
@@ -0,0 +1,289 @@
+use super::{
+	callback_utils::{catch_panic, READ_EVENT},
+	error::{self, DaqError},
+	sample_queue::{sample_queue, OverflowPolicy, QueueReceiver, QueueSender, QueueStats},
+	task_control::TaskControl,
+	task_handle::{RawTaskHandle, TaskHandle},
+	timing::SampleMode,
+	trigger::Arm,
+};
+
+use std::{
+	pin::Pin,
+	sync::{Arc, Mutex, MutexGuard},
+	task::{Context, Poll},
+};
+
+use futures::stream::Stream;
+
+// Reads the batches of a stream in the DAQ callbacks, all a kind of channel has to provide
+pub trait StreamRead: Send + 'static {
+	type Sample: Send + 'static;
+	// Reported when a read comes back short
+	const READ_CALL: &'static str;
+
+	// Up to `n_samps` samples per channel, `first` is the number of the batch's first sample
+	// counting from 1 at the start of the acquisition
+	fn read(
+		&mut self,
+		task_handle: &mut RawTaskHandle,
+		n_samps: u32,
+		first: u64,
+	) -> Result<Vec<Self::Sample>, DaqError>;
+}
+
+// What a channel hands over to its stream
+pub struct StreamSetup<R> {
+	pub reader: R,
+	// Samples per channel read every callback
+	pub batch_size: u32,
+	pub sample_mode: SampleMode,
+}
+
+// An input channel that can be turned into a stream fed from its DAQ callbacks. Streams are
+// launched through Arm, so channels sharing a start trigger can be launched together.
+pub trait StreamChannel: TaskControl + Arm + Sized {
+	type Reader: StreamRead;
+
+	fn stream_setup(&self) -> Result<StreamSetup<Self::Reader>, DaqError>;
+
+	// Samples queue up without limit until the stream is polled
+	fn make_async(self) -> Result<AsyncChannel<Self>, DaqError> {
+		AsyncChannel::new(self, None, OverflowPolicy::DropNewest)
+	}
+
+	// At most `capacity` samples queue up, `policy` decides what happens to the ones that don't fit
+	fn make_async_bounded(
+		self,
+		capacity: usize,
+		policy: OverflowPolicy,
+	) -> Result<AsyncChannel<Self>, DaqError> {
+		AsyncChannel::new(self, Some(capacity), policy)
+	}
+}
+
+type Sample<C> = <<C as StreamChannel>::Reader as StreamRead>::Sample;
+
+// Shared by the read and done callbacks
+struct StreamInternal<R: StreamRead> {
+	sender: Option<QueueSender<R::Sample>>,	// Dropped when the task is done, ending the stream
+	reader: R,
+	samples_read: u64,		// # of samples per channel read by this stream
+	sample_mode: SampleMode,
+}
+
+impl<R: StreamRead> StreamInternal<R> {
+	fn read_batch(&mut self, task_handle: &mut RawTaskHandle, n_samps: u32) -> Result<(), ()> {
+		let send_channel = self.sender.as_ref().ok_or(())?;
+
+		let samples = match self.reader.read(task_handle, n_samps, self.samples_read + 1) {
+			Ok(samples) if samples.len() == n_samps as usize => samples,
+			Ok(samples) => {
+				self.fail(error::incomplete_read(samples.len() as i32, n_samps, R::READ_CALL));
+				return Err(());
+			}
+			Err(err) => {
+				self.fail(err);
+				return Err(());
+			}
+		};
+
+		self.samples_read += n_samps as u64;
+
+		// Failing to query the buffer only costs the backlog metric, not the batch
+		if let Ok(available) = task_handle.available_samples() {
+			send_channel.set_buffered(available as usize);
+		}
+
+		send_channel.send_all(samples)
+	}
+
+	// Feeds a renewed queue, counting the samples of a relaunched task from 1 again
+	fn restart(&mut self, sender: QueueSender<R::Sample>) {
+		self.sender = Some(sender);
+		self.samples_read = 0;
+	}
+
+	// Ends the stream with `err` once the samples queued before it are drained
+	fn fail(&mut self, err: DaqError) {
+		if let Some(sender) = self.sender.take() {
+			sender.fail(err);
+		}
+	}
+}
+
+type SharedStreamInternal<R> = Arc<Mutex<StreamInternal<R>>>;
+
+// The stream of any input channel, see StreamChannel
+pub struct AsyncChannel<C: StreamChannel> {
+	chan: C,
+	recv: QueueReceiver<Sample<C>>,
+	internal: SharedStreamInternal<C::Reader>,
+	launched: bool,
+}
+
+impl<C: StreamChannel> AsyncChannel<C> {
+	fn new(mut chan: C, capacity: Option<usize>, policy: OverflowPolicy) -> Result<Self, DaqError> {
+		let StreamSetup {
+			reader,
+			batch_size,
+			sample_mode,
+		} = chan.stream_setup()?;
+
+		let (snd, recv) = sample_queue(capacity, policy);
+
+		let internal = Arc::new(Mutex::new(StreamInternal {
+			sender: Some(snd),
+			reader,
+			samples_read: 0,
+			sample_mode,
+		}));
+
+		unsafe
+		{
+			chan.task_handle().register_read_callback(
+				batch_size,
+				async_read_callback_impl::<C::Reader>,
+				Arc::clone(&internal),
+			)?;
+
+			// The stream ends once the task is done
+			chan.task_handle().register_done_callback(
+				async_done_callback_impl::<C::Reader>,
+				Arc::clone(&internal),
+			)?;
+		}
+
+		Ok(AsyncChannel
+		{
+			chan,
+			recv,
+			internal,
+			launched: false,
+		})
+	}
+
+	// The channel the stream reads, e.g. for its time axis
+	pub fn channel(&self) -> &C
+	{
+		&self.chan
+	}
+
+	// A relaunched stream starts over with an empty queue, the samples it still had are gone
+	pub fn launch_task(&mut self) -> Result<(), DaqError>
+	{
+		self.arm()?;
+		self.fire()
+	}
+
+	// Dropped samples and queue high-water mark, the handle stays valid after the stream is moved
+	pub fn stats(&self) -> Arc<QueueStats>
+	{
+		self.recv.stats()
+	}
+
+	// Samples the stream lags behind the acquisition, see QueueStats::backlog
+	pub fn backlog(&self) -> usize
+	{
+		self.recv.stats().backlog()
+	}
+
+	fn internal(&self) -> MutexGuard<'_, StreamInternal<C::Reader>>
+	{
+		self.internal.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl<C: StreamChannel> Arm for AsyncChannel<C> {
+	fn arm(&mut self) -> Result<(), DaqError> {
+		if self.launched {
+			// A finite task has to be stopped before it starts again, even once it's done
+			self.chan.stop_task()?;
+
+			let sender = self.recv.renew();
+			self.internal().restart(sender);
+		}
+
+		self.chan.arm()?;
+		self.launched = true;
+
+		Ok(())
+	}
+
+	fn fire(&mut self) -> Result<(), DaqError> {
+		self.chan.fire()
+	}
+}
+
+impl<C: StreamChannel> TaskControl for AsyncChannel<C> {
+	fn task_handle(&mut self) -> &mut TaskHandle {
+		self.chan.task_handle()
+	}
+
+	// The stream ends after the samples it already has, launch again for another run
+	fn stop_task(&mut self) -> Result<(), DaqError> {
+		self.chan.stop_task()?;
+
+		// Stopping doesn't fire the done event
+		self.internal().sender = None;
+
+		Ok(())
+	}
+
+	fn verify_task(&mut self) -> Result<(), DaqError> {
+		self.chan.verify_task()
+	}
+
+	fn commit_task(&mut self) -> Result<(), DaqError> {
+		self.chan.commit_task()
+	}
+}
+
+// Ends with an error item if the task stops early or a read fails
+impl<C: StreamChannel + Unpin> Stream for AsyncChannel<C> {
+	type Item = Result<Sample<C>, DaqError>;
+
+	#[inline]
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		Pin::new(&mut self.recv).poll_next(cx)
+	}
+}
+
+fn async_read_callback_impl<R: StreamRead>(
+	scan_chan: &mut SharedStreamInternal<R>,
+	task_handle: &mut RawTaskHandle,
+	n_samps: u32,
+) -> Result<(), ()> {
+	let mut scan_chan = scan_chan.lock().unwrap_or_else(|e| e.into_inner());
+
+	let result = catch_panic(READ_EVENT, || scan_chan.read_batch(task_handle, n_samps));
+
+	result.unwrap_or_else(|err| {
+		scan_chan.fail(err);
+		Err(())
+	})
+}
+
+fn async_done_callback_impl<R: StreamRead>(
+	scan_chan: &mut SharedStreamInternal<R>,
+	task_handle: &mut RawTaskHandle,
+	status: Result<(), DaqError>,
+) {
+	let mut scan_chan = scan_chan.lock().unwrap_or_else(|e| e.into_inner());
+
+	// Stopped early, e.g. because the device was removed
+	if let Err(err) = status {
+		scan_chan.fail(err);
+	}
+
+	// A finite acquisition can end on a partial batch the read callback never fired for
+	if let SampleMode::Finite(n_samps) = scan_chan.sample_mode {
+		let remaining = n_samps.saturating_sub(scan_chan.samples_read);
+		if remaining > 0 {
+			let _ = scan_chan.read_batch(task_handle, remaining as u32);
+		}
+	}
+
+	// The stream ends once the queued samples are drained
+	scan_chan.sender = None;
+}
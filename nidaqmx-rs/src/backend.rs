@@ -0,0 +1,1296 @@
+use super::callback_utils::{RawDoneCallback, RawReadCallback};
+
+use std::{
+	ffi::CStr,
+	os::raw::{c_char, c_void},
+	ptr,
+	sync::Arc,
+};
+
+pub type TaskPtr = *mut c_void;
+pub type Backend = Arc<dyn DaqBackend>;
+
+// The subset of the DAQmx C-API used by this crate. Every call mirrors its DAQmx counterpart,
+// returning the raw DAQmx status code (negative on failure, positive for warnings).
+//
+// Functions taking a `TaskPtr` are unsafe, the caller must ensure it was created by
+// `create_task` on the same backend and hasn't been cleared yet. It's invalid after `clear_task`.
+#[allow(clippy::missing_safety_doc)]
+pub trait DaqBackend: Send + Sync {
+	fn create_task(&self, task_name: &CStr, task: &mut TaskPtr) -> i32;
+
+	unsafe fn start_task(&self, task: TaskPtr) -> i32;
+
+	unsafe fn stop_task(&self, task: TaskPtr) -> i32;
+
+	unsafe fn clear_task(&self, task: TaskPtr) -> i32;
+
+	unsafe fn task_control(&self, task: TaskPtr, action: i32) -> i32;
+
+	unsafe fn is_task_done(&self, task: TaskPtr, is_done: &mut bool) -> i32;
+
+	unsafe fn get_task_num_chans(&self, task: TaskPtr, num_chans: &mut u32) -> i32;
+
+	unsafe fn create_ai_voltage_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		terminal_config: i32,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+	) -> i32;
+
+	unsafe fn create_ai_force_bridge_two_point_lin_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		bridge_config: i32,
+		excit_source: i32,
+		excit_val: f64,
+		nominal_bridge_resistance: f64,
+		first: (f64, f64),
+		second: (f64, f64),
+		electrical_units: i32,
+		physical_units: i32,
+	) -> i32;
+
+	unsafe fn create_ai_force_bridge_polynomial_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		bridge_config: i32,
+		excit_source: i32,
+		excit_val: f64,
+		nominal_bridge_resistance: f64,
+		forward_coeffs: &[f64],
+		reverse_coeffs: &[f64],
+		electrical_units: i32,
+		physical_units: i32,
+	) -> i32;
+
+	unsafe fn create_ai_force_bridge_table_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		bridge_config: i32,
+		excit_source: i32,
+		excit_val: f64,
+		nominal_bridge_resistance: f64,
+		electrical_vals: &[f64],
+		physical_vals: &[f64],
+		electrical_units: i32,
+		physical_units: i32,
+	) -> i32;
+
+	unsafe fn create_ai_accel_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		terminal_config: i32,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		sensitivity: f64,
+		sensitivity_units: i32,
+		current_excit_source: i32,
+		current_excit_val: f64,
+	) -> i32;
+
+	unsafe fn create_co_pulse_chan_freq(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		units: i32,
+		idle_state: i32,
+		initial_delay: f64,
+		freq: f64,
+		duty_cycle: f64,
+	) -> i32;
+
+	unsafe fn create_ci_ang_encoder_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		decoding_type: i32,
+		z_idx_enable: bool,
+		z_idx_val: f64,
+		z_idx_phase: i32,
+		units: i32,
+		pulses_per_rev: u32,
+		initial_angle: f64,
+	) -> i32;
+
+	unsafe fn create_ci_lin_encoder_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		decoding_type: i32,
+		z_idx_enable: bool,
+		z_idx_val: f64,
+		z_idx_phase: i32,
+		units: i32,
+		dist_per_pulse: f64,
+		initial_pos: f64,
+	) -> i32;
+
+	unsafe fn create_ci_ang_velocity_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		decoding_type: i32,
+		units: i32,
+		pulses_per_rev: u32,
+	) -> i32;
+
+	unsafe fn create_ci_count_edges_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		edge: i32,
+		initial_count: u32,
+		count_direction: i32,
+	) -> i32;
+
+	unsafe fn create_ci_freq_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		edge: i32,
+		meas_method: i32,
+		meas_time: f64,
+		divisor: u32,
+	) -> i32;
+
+	unsafe fn create_ci_period_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		edge: i32,
+		meas_method: i32,
+		meas_time: f64,
+		divisor: u32,
+	) -> i32;
+
+	unsafe fn create_ao_voltage_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+	) -> i32;
+
+	unsafe fn create_di_chan(
+		&self,
+		task: TaskPtr,
+		lines: &CStr,
+		name_to_assign: &CStr,
+		line_grouping: i32,
+	) -> i32;
+
+	unsafe fn create_do_chan(
+		&self,
+		task: TaskPtr,
+		lines: &CStr,
+		name_to_assign: &CStr,
+		line_grouping: i32,
+	) -> i32;
+
+	unsafe fn cfg_samp_clk_timing(
+		&self,
+		task: TaskPtr,
+		source: &CStr,
+		rate: f64,
+		active_edge: i32,
+		sample_mode: i32,
+		samps_per_chan: u64,
+	) -> i32;
+
+	unsafe fn cfg_implicit_timing(
+		&self,
+		task: TaskPtr,
+		sample_mode: i32,
+		samps_per_chan: u64,
+	) -> i32;
+
+	// Sample clock rate after DAQmx coerced it to what the timebase can generate [Hz]
+	unsafe fn get_samp_clk_rate(&self, task: TaskPtr, rate: &mut f64) -> i32;
+
+	// Pulse frequency after DAQmx coerced it to what the counter's timebase can generate [Hz]
+	unsafe fn get_co_pulse_freq(&self, task: TaskPtr, channel: &CStr, freq: &mut f64) -> i32;
+
+	unsafe fn cfg_dig_edge_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_edge: i32,
+	) -> i32;
+
+	unsafe fn cfg_anlg_edge_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_slope: i32,
+		trigger_level: f64,
+	) -> i32;
+
+	unsafe fn cfg_anlg_window_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_when: i32,
+		window_top: f64,
+		window_bottom: f64,
+	) -> i32;
+
+	// Reference triggers need a finite task, its samples per channel include the pretrigger ones
+	unsafe fn cfg_anlg_edge_ref_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_slope: i32,
+		trigger_level: f64,
+		pretrigger_samples: u32,
+	) -> i32;
+
+	unsafe fn cfg_anlg_window_ref_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_when: i32,
+		window_top: f64,
+		window_bottom: f64,
+		pretrigger_samples: u32,
+	) -> i32;
+
+	unsafe fn register_every_n_samples_event(
+		&self,
+		task: TaskPtr,
+		event_type: i32,
+		n_samps: u32,
+		options: u32,
+		callback: RawReadCallback,
+		callback_data: *mut c_void,
+	) -> i32;
+
+	unsafe fn register_done_event(
+		&self,
+		task: TaskPtr,
+		options: u32,
+		callback: RawDoneCallback,
+		callback_data: *mut c_void,
+	) -> i32;
+
+	unsafe fn read_analog_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		fill_mode: u32,
+		buf: &mut [f64],
+		samps_read: &mut i32,
+	) -> i32;
+
+	unsafe fn read_counter_u32(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [u32],
+		samps_read: &mut i32,
+	) -> i32;
+
+	unsafe fn read_counter_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [f64],
+		samps_read: &mut i32,
+	) -> i32;
+
+	// One byte per line and sample, 0 or 1
+	unsafe fn read_digital_lines(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		fill_mode: u32,
+		buf: &mut [u8],
+		samps_read: &mut i32,
+		bytes_per_samp: &mut i32,
+	) -> i32;
+
+	unsafe fn write_analog_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		auto_start: bool,
+		timeout: f64,
+		data_layout: u32,
+		buf: &[f64],
+		samps_written: &mut i32,
+	) -> i32;
+
+	unsafe fn write_digital_lines(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		auto_start: bool,
+		timeout: f64,
+		data_layout: u32,
+		buf: &[u8],
+		samps_written: &mut i32,
+	) -> i32;
+
+	unsafe fn set_write_regen_mode(&self, task: TaskPtr, regen_mode: i32) -> i32;
+
+	// Input buffer size in samples per channel, replaces the one DAQmx picked from the timing
+	unsafe fn cfg_input_buffer(&self, task: TaskPtr, num_samps_per_chan: u32) -> i32;
+
+	unsafe fn get_buf_input_buf_size(&self, task: TaskPtr, buf_size: &mut u32) -> i32;
+
+	unsafe fn set_read_over_write(&self, task: TaskPtr, over_write: i32) -> i32;
+
+	unsafe fn set_read_relative_to(&self, task: TaskPtr, relative_to: i32) -> i32;
+
+	unsafe fn set_read_offset(&self, task: TaskPtr, offset: i32) -> i32;
+
+	// Samples per channel acquired but not read yet
+	unsafe fn get_read_avail_samp_per_chan(&self, task: TaskPtr, avail: &mut u32) -> i32;
+
+	unsafe fn get_read_total_samp_per_chan_acquired(&self, task: TaskPtr, total: &mut u64) -> i32;
+
+	// `channel` is the name of a virtual channel in the task, or a list of them
+	unsafe fn set_ai_coupling(&self, task: TaskPtr, channel: &CStr, coupling: i32) -> i32;
+
+	// String queries follow the DAQmx convention: an empty buffer returns the required size
+	// (including the null terminator), otherwise the string is copied and truncated to fit.
+	fn get_sys_dev_names(&self, buf: &mut [u8]) -> i32;
+
+	fn get_dev_product_type(&self, device: &CStr, buf: &mut [u8]) -> i32;
+
+	fn get_dev_serial_num(&self, device: &CStr, serial_num: &mut u32) -> i32;
+
+	fn get_dev_ai_physical_chans(&self, device: &CStr, buf: &mut [u8]) -> i32;
+
+	fn get_dev_ci_physical_chans(&self, device: &CStr, buf: &mut [u8]) -> i32;
+
+	fn get_dev_terminals(&self, device: &CStr, buf: &mut [u8]) -> i32;
+
+	// Aborts the device's tasks and returns it to its power-on state
+	fn reset_device(&self, device: &CStr) -> i32;
+
+	fn self_test_device(&self, device: &CStr) -> i32;
+
+	// Local date and time of the device's last calibration
+	fn get_self_cal_last_date_and_time(
+		&self,
+		device: &CStr,
+		year: &mut u32,
+		month: &mut u32,
+		day: &mut u32,
+		hour: &mut u32,
+		minute: &mut u32,
+	) -> i32;
+
+	fn get_ext_cal_last_date_and_time(
+		&self,
+		device: &CStr,
+		year: &mut u32,
+		month: &mut u32,
+		day: &mut u32,
+		hour: &mut u32,
+		minute: &mut u32,
+	) -> i32;
+
+	// Device temperature during the last calibration, and right now [°C]
+	fn get_self_cal_last_temp(&self, device: &CStr, temp: &mut f64) -> i32;
+
+	fn get_ext_cal_last_temp(&self, device: &CStr, temp: &mut f64) -> i32;
+
+	fn get_cal_dev_temp(&self, device: &CStr, temp: &mut f64) -> i32;
+
+	// Extended description of the last error that occured
+	fn extended_error_info(&self) -> String;
+}
+
+// Backend that talks to the installed NI-DAQmx driver
+pub struct NiDaqmx;
+
+pub fn ni_daqmx() -> Backend {
+	Arc::new(NiDaqmx)
+}
+
+impl DaqBackend for NiDaqmx {
+	fn create_task(&self, task_name: &CStr, task: &mut TaskPtr) -> i32 {
+		// This is safe as long as NI isn't dumb
+		unsafe { nidaqmx_sys::DAQmxCreateTask(task_name.as_ptr(), task as *mut _) }
+	}
+
+	unsafe fn start_task(&self, task: TaskPtr) -> i32 {
+		nidaqmx_sys::DAQmxStartTask(task)
+	}
+
+	unsafe fn stop_task(&self, task: TaskPtr) -> i32 {
+		nidaqmx_sys::DAQmxStopTask(task)
+	}
+
+	unsafe fn clear_task(&self, task: TaskPtr) -> i32 {
+		nidaqmx_sys::DAQmxClearTask(task)
+	}
+
+	unsafe fn task_control(&self, task: TaskPtr, action: i32) -> i32 {
+		nidaqmx_sys::DAQmxTaskControl(task, action)
+	}
+
+	unsafe fn is_task_done(&self, task: TaskPtr, is_done: &mut bool) -> i32 {
+		let mut done: nidaqmx_sys::bool32 = 0;
+		let err_code = nidaqmx_sys::DAQmxIsTaskDone(task, &mut done);
+		*is_done = done != 0;
+
+		err_code
+	}
+
+	unsafe fn get_task_num_chans(&self, task: TaskPtr, num_chans: &mut u32) -> i32 {
+		nidaqmx_sys::DAQmxGetTaskNumChans(task, num_chans as *mut _)
+	}
+
+	unsafe fn create_ai_voltage_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		terminal_config: i32,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateAIVoltageChan(
+			task,
+			physical_channel.as_ptr(),
+			name_to_assign.as_ptr(),
+			terminal_config,
+			min_val,
+			max_val,
+			units,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ai_force_bridge_two_point_lin_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		bridge_config: i32,
+		excit_source: i32,
+		excit_val: f64,
+		nominal_bridge_resistance: f64,
+		first: (f64, f64),
+		second: (f64, f64),
+		electrical_units: i32,
+		physical_units: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateAIForceBridgeTwoPointLinChan(
+			task,
+			physical_channel.as_ptr(),
+			name_to_assign.as_ptr(),
+			min_val,
+			max_val,
+			units,
+			bridge_config,
+			excit_source,
+			excit_val,
+			nominal_bridge_resistance,
+			first.0,
+			second.0,
+			electrical_units,
+			first.1,
+			second.1,
+			physical_units,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ai_force_bridge_polynomial_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		bridge_config: i32,
+		excit_source: i32,
+		excit_val: f64,
+		nominal_bridge_resistance: f64,
+		forward_coeffs: &[f64],
+		reverse_coeffs: &[f64],
+		electrical_units: i32,
+		physical_units: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateAIForceBridgePolynomialChan(
+			task,
+			physical_channel.as_ptr(),
+			name_to_assign.as_ptr(),
+			min_val,
+			max_val,
+			units,
+			bridge_config,
+			excit_source,
+			excit_val,
+			nominal_bridge_resistance,
+			forward_coeffs.as_ptr(),
+			forward_coeffs.len() as u32,
+			reverse_coeffs.as_ptr(),
+			reverse_coeffs.len() as u32,
+			electrical_units,
+			physical_units,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ai_force_bridge_table_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		bridge_config: i32,
+		excit_source: i32,
+		excit_val: f64,
+		nominal_bridge_resistance: f64,
+		electrical_vals: &[f64],
+		physical_vals: &[f64],
+		electrical_units: i32,
+		physical_units: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateAIForceBridgeTableChan(
+			task,
+			physical_channel.as_ptr(),
+			name_to_assign.as_ptr(),
+			min_val,
+			max_val,
+			units,
+			bridge_config,
+			excit_source,
+			excit_val,
+			nominal_bridge_resistance,
+			electrical_vals.as_ptr(),
+			electrical_vals.len() as u32,
+			electrical_units,
+			physical_vals.as_ptr(),
+			physical_vals.len() as u32,
+			physical_units,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ai_accel_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		terminal_config: i32,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		sensitivity: f64,
+		sensitivity_units: i32,
+		current_excit_source: i32,
+		current_excit_val: f64,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateAIAccelChan(
+			task,
+			physical_channel.as_ptr(),
+			name_to_assign.as_ptr(),
+			terminal_config,
+			min_val,
+			max_val,
+			units,
+			sensitivity,
+			sensitivity_units,
+			current_excit_source,
+			current_excit_val,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_co_pulse_chan_freq(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		units: i32,
+		idle_state: i32,
+		initial_delay: f64,
+		freq: f64,
+		duty_cycle: f64,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateCOPulseChanFreq(
+			task,
+			counter.as_ptr(),
+			name_to_assign.as_ptr(),
+			units,
+			idle_state,
+			initial_delay,
+			freq,
+			duty_cycle,
+		)
+	}
+
+	unsafe fn create_ci_ang_encoder_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		decoding_type: i32,
+		z_idx_enable: bool,
+		z_idx_val: f64,
+		z_idx_phase: i32,
+		units: i32,
+		pulses_per_rev: u32,
+		initial_angle: f64,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateCIAngEncoderChan(
+			task,
+			counter.as_ptr(),
+			name_to_assign.as_ptr(),
+			decoding_type,
+			z_idx_enable as u32,
+			z_idx_val,
+			z_idx_phase,
+			units,
+			pulses_per_rev,
+			initial_angle,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ci_lin_encoder_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		decoding_type: i32,
+		z_idx_enable: bool,
+		z_idx_val: f64,
+		z_idx_phase: i32,
+		units: i32,
+		dist_per_pulse: f64,
+		initial_pos: f64,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateCILinEncoderChan(
+			task,
+			counter.as_ptr(),
+			name_to_assign.as_ptr(),
+			decoding_type,
+			z_idx_enable as u32,
+			z_idx_val,
+			z_idx_phase,
+			units,
+			dist_per_pulse,
+			initial_pos,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ci_ang_velocity_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		decoding_type: i32,
+		units: i32,
+		pulses_per_rev: u32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateCIAngVelocityChan(
+			task,
+			counter.as_ptr(),
+			name_to_assign.as_ptr(),
+			min_val,
+			max_val,
+			decoding_type,
+			units,
+			pulses_per_rev,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ci_count_edges_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		edge: i32,
+		initial_count: u32,
+		count_direction: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateCICountEdgesChan(
+			task,
+			counter.as_ptr(),
+			name_to_assign.as_ptr(),
+			edge,
+			initial_count,
+			count_direction,
+		)
+	}
+
+	unsafe fn create_ci_freq_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		edge: i32,
+		meas_method: i32,
+		meas_time: f64,
+		divisor: u32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateCIFreqChan(
+			task,
+			counter.as_ptr(),
+			name_to_assign.as_ptr(),
+			min_val,
+			max_val,
+			units,
+			edge,
+			meas_method,
+			meas_time,
+			divisor,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ci_period_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		edge: i32,
+		meas_method: i32,
+		meas_time: f64,
+		divisor: u32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateCIPeriodChan(
+			task,
+			counter.as_ptr(),
+			name_to_assign.as_ptr(),
+			min_val,
+			max_val,
+			units,
+			edge,
+			meas_method,
+			meas_time,
+			divisor,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_ao_voltage_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateAOVoltageChan(
+			task,
+			physical_channel.as_ptr(),
+			name_to_assign.as_ptr(),
+			min_val,
+			max_val,
+			units,
+			ptr::null(),
+		)
+	}
+
+	unsafe fn create_di_chan(
+		&self,
+		task: TaskPtr,
+		lines: &CStr,
+		name_to_assign: &CStr,
+		line_grouping: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateDIChan(task, lines.as_ptr(), name_to_assign.as_ptr(), line_grouping)
+	}
+
+	unsafe fn create_do_chan(
+		&self,
+		task: TaskPtr,
+		lines: &CStr,
+		name_to_assign: &CStr,
+		line_grouping: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCreateDOChan(task, lines.as_ptr(), name_to_assign.as_ptr(), line_grouping)
+	}
+
+	unsafe fn cfg_samp_clk_timing(
+		&self,
+		task: TaskPtr,
+		source: &CStr,
+		rate: f64,
+		active_edge: i32,
+		sample_mode: i32,
+		samps_per_chan: u64,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCfgSampClkTiming(
+			task,
+			source.as_ptr(),
+			rate,
+			active_edge,
+			sample_mode,
+			samps_per_chan,
+		)
+	}
+
+	unsafe fn cfg_implicit_timing(
+		&self,
+		task: TaskPtr,
+		sample_mode: i32,
+		samps_per_chan: u64,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCfgImplicitTiming(task, sample_mode, samps_per_chan)
+	}
+
+	unsafe fn get_samp_clk_rate(&self, task: TaskPtr, rate: &mut f64) -> i32 {
+		nidaqmx_sys::DAQmxGetSampClkRate(task, rate as *mut _)
+	}
+
+	unsafe fn get_co_pulse_freq(&self, task: TaskPtr, channel: &CStr, freq: &mut f64) -> i32 {
+		nidaqmx_sys::DAQmxGetCOPulseFreq(task, channel.as_ptr(), freq as *mut _)
+	}
+
+	unsafe fn cfg_dig_edge_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_edge: i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCfgDigEdgeStartTrig(task, trigger_source.as_ptr(), trigger_edge)
+	}
+
+	unsafe fn cfg_anlg_edge_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_slope: i32,
+		trigger_level: f64,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCfgAnlgEdgeStartTrig(
+			task,
+			trigger_source.as_ptr(),
+			trigger_slope,
+			trigger_level,
+		)
+	}
+
+	unsafe fn cfg_anlg_window_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_when: i32,
+		window_top: f64,
+		window_bottom: f64,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCfgAnlgWindowStartTrig(
+			task,
+			trigger_source.as_ptr(),
+			trigger_when,
+			window_top,
+			window_bottom,
+		)
+	}
+
+	unsafe fn cfg_anlg_edge_ref_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_slope: i32,
+		trigger_level: f64,
+		pretrigger_samples: u32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCfgAnlgEdgeRefTrig(
+			task,
+			trigger_source.as_ptr(),
+			trigger_slope,
+			trigger_level,
+			pretrigger_samples,
+		)
+	}
+
+	unsafe fn cfg_anlg_window_ref_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_when: i32,
+		window_top: f64,
+		window_bottom: f64,
+		pretrigger_samples: u32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxCfgAnlgWindowRefTrig(
+			task,
+			trigger_source.as_ptr(),
+			trigger_when,
+			window_top,
+			window_bottom,
+			pretrigger_samples,
+		)
+	}
+
+	unsafe fn register_every_n_samples_event(
+		&self,
+		task: TaskPtr,
+		event_type: i32,
+		n_samps: u32,
+		options: u32,
+		callback: RawReadCallback,
+		callback_data: *mut c_void,
+	) -> i32 {
+		nidaqmx_sys::DAQmxRegisterEveryNSamplesEvent(
+			task,
+			event_type,
+			n_samps,
+			options,
+			callback,
+			callback_data,
+		)
+	}
+
+	unsafe fn register_done_event(
+		&self,
+		task: TaskPtr,
+		options: u32,
+		callback: RawDoneCallback,
+		callback_data: *mut c_void,
+	) -> i32 {
+		nidaqmx_sys::DAQmxRegisterDoneEvent(task, options, callback, callback_data)
+	}
+
+	unsafe fn read_analog_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		fill_mode: u32,
+		buf: &mut [f64],
+		samps_read: &mut i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxReadAnalogF64(
+			task,
+			n_samps,
+			timeout,
+			fill_mode,
+			buf.as_mut_ptr(),
+			buf.len() as u32,
+			samps_read as *mut _,
+			ptr::null_mut(),
+		)
+	}
+
+	unsafe fn read_counter_u32(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [u32],
+		samps_read: &mut i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxReadCounterU32(
+			task,
+			n_samps,
+			timeout,
+			buf.as_mut_ptr(),
+			buf.len() as u32,
+			samps_read as *mut _,
+			ptr::null_mut(),
+		)
+	}
+
+	unsafe fn read_counter_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [f64],
+		samps_read: &mut i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxReadCounterF64(
+			task,
+			n_samps,
+			timeout,
+			buf.as_mut_ptr(),
+			buf.len() as u32,
+			samps_read as *mut _,
+			ptr::null_mut(),
+		)
+	}
+
+	unsafe fn read_digital_lines(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		fill_mode: u32,
+		buf: &mut [u8],
+		samps_read: &mut i32,
+		bytes_per_samp: &mut i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxReadDigitalLines(
+			task,
+			n_samps,
+			timeout,
+			fill_mode,
+			buf.as_mut_ptr(),
+			buf.len() as u32,
+			samps_read as *mut _,
+			bytes_per_samp as *mut _,
+			ptr::null_mut(),
+		)
+	}
+
+	unsafe fn write_analog_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		auto_start: bool,
+		timeout: f64,
+		data_layout: u32,
+		buf: &[f64],
+		samps_written: &mut i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxWriteAnalogF64(
+			task,
+			n_samps,
+			auto_start as u32,
+			timeout,
+			data_layout,
+			buf.as_ptr(),
+			samps_written as *mut _,
+			ptr::null_mut(),
+		)
+	}
+
+	unsafe fn write_digital_lines(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		auto_start: bool,
+		timeout: f64,
+		data_layout: u32,
+		buf: &[u8],
+		samps_written: &mut i32,
+	) -> i32 {
+		nidaqmx_sys::DAQmxWriteDigitalLines(
+			task,
+			n_samps,
+			auto_start as u32,
+			timeout,
+			data_layout,
+			buf.as_ptr(),
+			samps_written as *mut _,
+			ptr::null_mut(),
+		)
+	}
+
+	unsafe fn set_write_regen_mode(&self, task: TaskPtr, regen_mode: i32) -> i32 {
+		nidaqmx_sys::DAQmxSetWriteRegenMode(task, regen_mode)
+	}
+
+	unsafe fn cfg_input_buffer(&self, task: TaskPtr, num_samps_per_chan: u32) -> i32 {
+		nidaqmx_sys::DAQmxCfgInputBuffer(task, num_samps_per_chan)
+	}
+
+	unsafe fn get_buf_input_buf_size(&self, task: TaskPtr, buf_size: &mut u32) -> i32 {
+		nidaqmx_sys::DAQmxGetBufInputBufSize(task, buf_size as *mut _)
+	}
+
+	unsafe fn set_read_over_write(&self, task: TaskPtr, over_write: i32) -> i32 {
+		nidaqmx_sys::DAQmxSetReadOverWrite(task, over_write)
+	}
+
+	unsafe fn set_read_relative_to(&self, task: TaskPtr, relative_to: i32) -> i32 {
+		nidaqmx_sys::DAQmxSetReadRelativeTo(task, relative_to)
+	}
+
+	unsafe fn set_read_offset(&self, task: TaskPtr, offset: i32) -> i32 {
+		nidaqmx_sys::DAQmxSetReadOffset(task, offset)
+	}
+
+	unsafe fn get_read_avail_samp_per_chan(&self, task: TaskPtr, avail: &mut u32) -> i32 {
+		nidaqmx_sys::DAQmxGetReadAvailSampPerChan(task, avail as *mut _)
+	}
+
+	unsafe fn get_read_total_samp_per_chan_acquired(&self, task: TaskPtr, total: &mut u64) -> i32 {
+		nidaqmx_sys::DAQmxGetReadTotalSampPerChanAcquired(task, total as *mut _)
+	}
+
+	unsafe fn set_ai_coupling(&self, task: TaskPtr, channel: &CStr, coupling: i32) -> i32 {
+		nidaqmx_sys::DAQmxSetAICoupling(task, channel.as_ptr(), coupling)
+	}
+
+	fn get_sys_dev_names(&self, buf: &mut [u8]) -> i32 {
+		unsafe { nidaqmx_sys::DAQmxGetSysDevNames(buf_ptr(buf), buf.len() as u32) }
+	}
+
+	fn get_dev_product_type(&self, device: &CStr, buf: &mut [u8]) -> i32 {
+		unsafe {
+			nidaqmx_sys::DAQmxGetDevProductType(device.as_ptr(), buf_ptr(buf), buf.len() as u32)
+		}
+	}
+
+	fn get_dev_serial_num(&self, device: &CStr, serial_num: &mut u32) -> i32 {
+		unsafe { nidaqmx_sys::DAQmxGetDevSerialNum(device.as_ptr(), serial_num as *mut _) }
+	}
+
+	fn get_dev_ai_physical_chans(&self, device: &CStr, buf: &mut [u8]) -> i32 {
+		unsafe {
+			nidaqmx_sys::DAQmxGetDevAIPhysicalChans(device.as_ptr(), buf_ptr(buf), buf.len() as u32)
+		}
+	}
+
+	fn get_dev_ci_physical_chans(&self, device: &CStr, buf: &mut [u8]) -> i32 {
+		unsafe {
+			nidaqmx_sys::DAQmxGetDevCIPhysicalChans(device.as_ptr(), buf_ptr(buf), buf.len() as u32)
+		}
+	}
+
+	fn get_dev_terminals(&self, device: &CStr, buf: &mut [u8]) -> i32 {
+		unsafe { nidaqmx_sys::DAQmxGetDevTerminals(device.as_ptr(), buf_ptr(buf), buf.len() as u32) }
+	}
+
+	fn reset_device(&self, device: &CStr) -> i32 {
+		unsafe { nidaqmx_sys::DAQmxResetDevice(device.as_ptr()) }
+	}
+
+	fn self_test_device(&self, device: &CStr) -> i32 {
+		unsafe { nidaqmx_sys::DAQmxSelfTestDevice(device.as_ptr()) }
+	}
+
+	fn get_self_cal_last_date_and_time(
+		&self,
+		device: &CStr,
+		year: &mut u32,
+		month: &mut u32,
+		day: &mut u32,
+		hour: &mut u32,
+		minute: &mut u32,
+	) -> i32 {
+		unsafe {
+			nidaqmx_sys::DAQmxGetSelfCalLastDateAndTime(
+				device.as_ptr(),
+				year as *mut _,
+				month as *mut _,
+				day as *mut _,
+				hour as *mut _,
+				minute as *mut _,
+			)
+		}
+	}
+
+	fn get_ext_cal_last_date_and_time(
+		&self,
+		device: &CStr,
+		year: &mut u32,
+		month: &mut u32,
+		day: &mut u32,
+		hour: &mut u32,
+		minute: &mut u32,
+	) -> i32 {
+		unsafe {
+			nidaqmx_sys::DAQmxGetExtCalLastDateAndTime(
+				device.as_ptr(),
+				year as *mut _,
+				month as *mut _,
+				day as *mut _,
+				hour as *mut _,
+				minute as *mut _,
+			)
+		}
+	}
+
+	fn get_self_cal_last_temp(&self, device: &CStr, temp: &mut f64) -> i32 {
+		unsafe { nidaqmx_sys::DAQmxGetSelfCalLastTemp(device.as_ptr(), temp as *mut _) }
+	}
+
+	fn get_ext_cal_last_temp(&self, device: &CStr, temp: &mut f64) -> i32 {
+		unsafe { nidaqmx_sys::DAQmxGetExtCalLastTemp(device.as_ptr(), temp as *mut _) }
+	}
+
+	fn get_cal_dev_temp(&self, device: &CStr, temp: &mut f64) -> i32 {
+		unsafe { nidaqmx_sys::DAQmxGetCalDevTemp(device.as_ptr(), temp as *mut _) }
+	}
+
+	fn extended_error_info(&self) -> String {
+		const ERROR_CODE_MAX_LEN: usize = 2048;
+
+		let mut buf = [0u8; ERROR_CODE_MAX_LEN];
+		let buf_ptr = buf.as_mut_ptr() as *mut i8;
+
+		// This is safe as long as nidaqmx respects the length of this buffer
+		unsafe { nidaqmx_sys::DAQmxGetExtendedErrorInfo(buf_ptr, buf.len() as u32) };
+
+		let last_elem = buf[..].last_mut().unwrap();
+		*last_elem = b'\0'; // Write out null terminator
+
+		// This is safe since there's a null terminator at the end of our buffer
+		let err_msg = unsafe { CStr::from_ptr(buf.as_ptr() as *const _) };
+
+		err_msg.to_string_lossy().into_owned()
+	}
+}
+
+// DAQmx wants a null pointer alongside a zero buffer size
+fn buf_ptr(buf: &mut [u8]) -> *mut c_char {
+	if buf.is_empty() {
+		ptr::null_mut()
+	} else {
+		buf.as_mut_ptr() as *mut c_char
+	}
+}
@@ -0,0 +1,60 @@
+use super::{error::DaqError, timing::SampleMode};
+
+use std::time::Duration;
+
+// Reads straight from the DAQmx buffer on the calling thread, for scripts and tests that don't
+// need a stream. The task has to be launched first.
+pub trait BlockingRead {
+	type Sample;
+
+	// Waits until `n_samps` samples per channel were acquired, or fails once `timeout` expires
+	fn read(&mut self, n_samps: usize, timeout: Duration) -> Result<Vec<Self::Sample>, DaqError>;
+
+	fn sample_mode(&self) -> SampleMode;
+
+	// Samples per channel read so far
+	fn samples_read(&self) -> u64;
+
+	// Waits for the rest of a finite acquisition and returns it as one buffer
+	fn read_to_end(&mut self, timeout: Duration) -> Result<Vec<Self::Sample>, DaqError> {
+		let remaining = self.sample_mode().finite_samps()?.saturating_sub(self.samples_read());
+
+		self.read(remaining as usize, timeout)
+	}
+
+	// Batches of `n_samps` samples for as long as the acquisition runs
+	fn batches(&mut self, n_samps: usize, timeout: Duration) -> Batches<'_, Self>
+	where
+		Self: Sized,
+	{
+		Batches {
+			chan: self,
+			n_samps,
+			timeout,
+			failed: false,
+		}
+	}
+}
+
+// Ends after yielding the first error
+pub struct Batches<'a, C> {
+	chan: &'a mut C,
+	n_samps: usize,
+	timeout: Duration,
+	failed: bool,
+}
+
+impl<'a, C: BlockingRead> Iterator for Batches<'a, C> {
+	type Item = Result<Vec<C::Sample>, DaqError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None;
+		}
+
+		let batch = self.chan.read(self.n_samps, self.timeout);
+		self.failed = batch.is_err();
+
+		Some(batch)
+	}
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+
+// What DAQmx does once a continuous acquisition fills its buffer before the samples were read
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverwriteMode {
+	// The task stops with an error, nothing acquired is ever lost silently
+	DoNotOverwrite,
+	// The oldest unread samples are replaced, for monitors that only care about recent data
+	Overwrite,
+}
+
+impl OverwriteMode {
+	pub fn as_daqmx(self) -> i32 {
+		match self {
+			OverwriteMode::DoNotOverwrite => nidaqmx_sys::DAQmx_Val_DoNotOverwriteUnreadSamps as i32,
+			OverwriteMode::Overwrite => nidaqmx_sys::DAQmx_Val_OverwriteUnreadSamps as i32,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadRelativeTo {
+	FirstSample,
+	// Right after the last sample read, the default
+	CurrentReadPosition,
+	ReferenceTrigger,
+	FirstPretriggerSample,
+	// Right after the newest sample, so an offset of -n reads the last n samples
+	MostRecentSample,
+}
+
+impl ReadRelativeTo {
+	pub fn as_daqmx(self) -> i32 {
+		match self {
+			ReadRelativeTo::FirstSample => nidaqmx_sys::DAQmx_Val_FirstSample as i32,
+			ReadRelativeTo::CurrentReadPosition => nidaqmx_sys::DAQmx_Val_CurrReadPos as i32,
+			ReadRelativeTo::ReferenceTrigger => nidaqmx_sys::DAQmx_Val_RefTrig as i32,
+			ReadRelativeTo::FirstPretriggerSample => nidaqmx_sys::DAQmx_Val_FirstPretrigSamp as i32,
+			ReadRelativeTo::MostRecentSample => nidaqmx_sys::DAQmx_Val_MostRecentSamp as i32,
+		}
+	}
+}
+
+// Where the next read starts, `offset` is in samples per channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReadPosition {
+	pub relative_to: ReadRelativeTo,
+	pub offset: i32,
+}
+
+impl ReadPosition {
+	// The last `n_samps` samples per channel, e.g. for a live display of an overwriting buffer
+	pub fn most_recent(n_samps: u32) -> Self {
+		ReadPosition {
+			relative_to: ReadRelativeTo::MostRecentSample,
+			offset: -(n_samps as i32),
+		}
+	}
+}
+
+impl Default for ReadPosition {
+	fn default() -> Self {
+		ReadPosition {
+			relative_to: ReadRelativeTo::CurrentReadPosition,
+			offset: 0,
+		}
+	}
+}
+
+// Snapshot of a task's input buffer, all counts are samples per channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BufferStatus {
+	// Acquired but not read yet
+	pub available: u32,
+	// Acquired since the task was launched, read or not
+	pub total_acquired: u64,
+	pub size: u32,
+}
+
+impl BufferStatus {
+	// How full the buffer is, 1.0 means the next sample overflows it
+	pub fn fill(&self) -> f64 {
+		if self.size == 0 {
+			0.0
+		} else {
+			self.available as f64 / self.size as f64
+		}
+	}
+}
+
+impl fmt::Display for BufferStatus {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{}/{} samples buffered ({:.0}%), {} acquired",
+			self.available,
+			self.size,
+			self.fill() * 100.0,
+			self.total_acquired
+		)
+	}
+}
@@ -1,22 +1,33 @@
-use super::task_handle::RawTaskHandle;
+use super::{backend::Backend, error::DaqError, task_handle::RawTaskHandle};
 
-use std::os::raw::c_void;
+use std::{
+	os::raw::c_void,
+	panic::{self, AssertUnwindSafe},
+	sync::Arc,
+};
 
 pub const CALLBACK_OPTIONS: u32 = 0;
+// Reported as the failed call when a read callback panics
+pub const READ_EVENT: &str = "DAQmxEveryNSamplesEvent";
 
-// type RawReadCallback = Option<unsafe extern "C" fn(*mut c_void, i32, u32, *mut c_void) -> i32>;
+pub type RawReadCallback = Option<unsafe extern "C" fn(*mut c_void, i32, u32, *mut c_void) -> i32>;
 
-type ReadCallback<T> = fn(&mut T, &mut RawTaskHandle, u32) -> Result<(), ()>;
-type ReadCallbackWrapper<T> = CallbackWrapper<T, ReadCallback<T>>;
+pub type ReadCallback<T> = fn(&mut T, &mut RawTaskHandle, u32) -> Result<(), ()>;
+pub type ReadCallbackWrapper<T> = CallbackWrapper<T, ReadCallback<T>>;
 
 pub struct CallbackWrapper<T, F> {
 	pub data: T,
 	pub func: F,
+	pub backend: Backend,
 }
 
 impl<T, F> CallbackWrapper<T, F> {
-	pub fn new(data: T, func: F) -> Box<Self> {
-		Box::new(CallbackWrapper { data, func })
+	pub fn new(data: T, func: F, backend: Backend) -> Box<Self> {
+		Box::new(CallbackWrapper {
+			data,
+			func,
+			backend,
+		})
 	}
 
 	pub fn into_raw(self: Box<Self>) -> *mut c_void {
@@ -26,9 +37,15 @@ impl<T, F> CallbackWrapper<T, F> {
 	pub unsafe fn from_raw(ptr: *mut c_void) -> Box<Self> {
 		Box::from_raw(ptr as *mut _)
 	}
+
+	// Frees a wrapper given to the backend, once the task it was registered with is cleared
+	pub unsafe fn free_raw(ptr: *mut c_void) {
+		std::mem::drop(Self::from_raw(ptr));
+	}
 }
 
-pub type DoneCallback<T> = fn(&mut T) -> ();
+// Gets the status the task stopped with, an error if it stopped early
+pub type DoneCallback<T> = fn(&mut T, &mut RawTaskHandle, Result<(), DaqError>);
 pub type DoneCallbackWrapper<T> = CallbackWrapper<T, DoneCallback<T>>;
 
 pub type RawDoneCallback = Option<unsafe extern "C" fn(*mut c_void, i32, *mut c_void) -> i32>;
@@ -40,16 +57,18 @@ pub unsafe extern "C" fn raw_done_callback_impl<T>(
 ) -> i32 {
 	// Need to catch panics at ffi boundary
 	let _ = std::panic::catch_unwind(|| {
+		// The task handle owns the wrapper and keeps it alive until the task is cleared, the done
+		// event fires again every time a relaunched task finishes
+		let callback_wrapper = &mut *(callback as *mut DoneCallbackWrapper<T>);
+
+		// We assume we were given a valid task handle
+		let backend = Arc::clone(&callback_wrapper.backend);
+		let mut task_handle = RawTaskHandle::from_raw(task_handle, backend).unwrap();
+
+		let status = task_handle.chk_err_code(err_code, "DAQmxDoneEvent");
+
 		// Run user defined callback
-		// Should be safe as long as no-one else touched that ptr
-		let mut callback_wrapper = DoneCallbackWrapper::<T>::from_raw(callback);
-		(callback_wrapper.func)(&mut callback_wrapper.data);
-
-		// Check for errors if task handle is still alive
-		let task_handle = RawTaskHandle::from_raw(task_handle);
-		if let Some(mut task_handle) = task_handle {
-			task_handle.chk_err_code(err_code);
-		}
+		(callback_wrapper.func)(&mut callback_wrapper.data, &mut task_handle, status);
 	});
 
 	0
@@ -63,23 +82,35 @@ pub unsafe extern "C" fn raw_read_callback_impl<T>(
 ) -> i32 {
 	// Need to catch panics at ffi boundary
 	let _ = std::panic::catch_unwind(|| {
+		// Owned by the task handle, like the done callback's wrapper
+		let callback_wrapper = &mut *(callback as *mut ReadCallbackWrapper<T>);
+
 		// We assume we were given a valid task handle
-		let mut task_handle = RawTaskHandle::from_raw(task_handle).unwrap();
+		let backend = Arc::clone(&callback_wrapper.backend);
+		let mut task_handle = RawTaskHandle::from_raw(task_handle, backend).unwrap();
 
 		// Run user defined callback
-		// Should be safe as long as no one else touched the callback ptr
-		let mut callback_wrapper = ReadCallbackWrapper::<T>::from_raw(callback);
 		let callback_result =
 			(callback_wrapper.func)(&mut callback_wrapper.data, &mut task_handle, n_samps);
 
-		// Don't free the boxed callback if it succeeded
-		if callback_result.is_ok() {
-			Box::into_raw(callback_wrapper);
-		} else {
-			task_handle.clear_task();
-			std::mem::drop(callback_wrapper);
+		// Nobody wants the samples anymore, stop acquiring them. The task stays valid, so it can
+		// be launched again.
+		if callback_result.is_err() {
+			task_handle.stop_task();
 		}
 	});
 
 	0
 }
+
+// Runs part of a callback, turning a panic into an error that can end its stream instead of
+// vanishing at the ffi boundary
+pub fn catch_panic<R, F: FnOnce() -> R>(call: &'static str, f: F) -> Result<R, DaqError> {
+	panic::catch_unwind(AssertUnwindSafe(f)).map_err(|_| {
+		DaqError::new(
+			nidaqmx_sys::DAQmxErrorPALSoftwareFault,
+			"The callback panicked.",
+			call,
+		)
+	})
+}
@@ -0,0 +1,324 @@
+use super::{
+	async_channel::{AsyncChannel, StreamChannel, StreamRead, StreamSetup},
+	backend::{self, Backend, DaqBackend, TaskPtr},
+	blocking::BlockingRead,
+	co_channel::*,
+	counter_generate_chan_desc,
+	error::DaqError,
+	terminal_desc,
+	timing::{SampleMode, TimeAxis},
+	trigger::{Arm, StartTrigger},
+	task_control::TaskControl,
+	task_handle::{RawTaskHandle, TaskHandle},
+	get_steady_time_nanoseconds, DAQ_CALLBACK_FREQ, SAMPLE_TIMEOUT_SECS,
+};
+
+use std::{marker::PhantomData, time::Duration};
+
+const DUTY_CYCLE: f64 = 0.5;
+
+// What a counter input is read as: raw ticks with DAQmxReadCounterU32, or values scaled to the
+// channel's units with DAQmxReadCounterF64. `task` must come from `create_task` on `backend`
+// and not be cleared yet.
+#[allow(clippy::missing_safety_doc)]
+pub trait CounterSample: Copy + Default + Send + 'static {
+	const READ_CALL: &'static str;
+
+	unsafe fn read_counter(
+		backend: &dyn DaqBackend,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [Self],
+		samps_read: &mut i32,
+	) -> i32;
+}
+
+impl CounterSample for u32 {
+	const READ_CALL: &'static str = "DAQmxReadCounterU32";
+
+	unsafe fn read_counter(
+		backend: &dyn DaqBackend,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [u32],
+		samps_read: &mut i32,
+	) -> i32 {
+		backend.read_counter_u32(task, n_samps, timeout, buf, samps_read)
+	}
+}
+
+impl CounterSample for f64 {
+	const READ_CALL: &'static str = "DAQmxReadCounterF64";
+
+	unsafe fn read_counter(
+		backend: &dyn DaqBackend,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [f64],
+		samps_read: &mut i32,
+	) -> i32 {
+		backend.read_counter_f64(task, n_samps, timeout, buf, samps_read)
+	}
+}
+
+// Counters and clocking shared by every counter input. Counter inputs have no sample clock of
+// their own, so a second counter generates one and routes it out on a PFI line where other
+// tasks can share it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CiSetup {
+	// Counter doing the measurement
+	pub counter_id: u8,
+	// Counter generating the sample clock, and the PFI line it's routed out on
+	pub clk_src_counter_id: u8,
+	pub clk_src_output_pfi_id: u8,
+	// Acquisition waits for this edge after the task is launched
+	pub start_trigger: Option<StartTrigger>,
+	pub sample_mode: SampleMode,
+}
+
+impl Default for CiSetup {
+	fn default() -> Self {
+		CiSetup {
+			counter_id: 0,
+			clk_src_counter_id: 1,
+			clk_src_output_pfi_id: 13,
+			start_trigger: None,
+			sample_mode: SampleMode::Continuous,
+		}
+	}
+}
+
+// A kind of counter input measurement, e.g. an encoder. It creates the DAQmx channel and turns
+// the values read into readings, CiChannel takes care of the rest.
+pub trait CiMeasurement: Send + Unpin + 'static {
+	type Raw: CounterSample;
+	type Reading: Send + 'static;
+
+	fn setup(&self) -> &CiSetup;
+
+	fn create_channel(&self, task_handle: &mut TaskHandle, chan_desc: &str) -> Result<(), DaqError>;
+
+	// `sample` counts from 1 at the start of the acquisition, `time` is the time since the first
+	// sample from the coerced sample clock rate [s]
+	fn reading(sample: u64, time: f64, raw: Self::Raw) -> Self::Reading;
+}
+
+pub struct CiChannel<M: CiMeasurement> {
+	task_handle: TaskHandle,
+	co_channel: CoFreqChannel,
+	device: String,
+	config: M,
+	sample_rate: usize,
+	time_axis: TimeAxis,
+	batch_size: usize,
+	samples_read: u64,	// # of samples read with BlockingRead
+}
+
+impl<M: CiMeasurement> CiChannel<M> {
+	pub fn new(device: &str, sample_rate: usize, config: M) -> Result<Self, DaqError> {
+		Self::with_backend(backend::ni_daqmx(), device, sample_rate, config)
+	}
+
+	pub fn with_backend(
+		backend: Backend,
+		device: &str,
+		sample_rate: usize,
+		config: M,
+	) -> Result<Self, DaqError> {
+		let task_handle = TaskHandle::with_backend(Backend::clone(&backend))?;
+		let co_channel = CoFreqChannel::with_backend(
+			backend,
+			device,
+			config.setup().clk_src_counter_id,
+			sample_rate as f64,
+			DUTY_CYCLE,
+		)?;
+		let batch_size = sample_rate / DAQ_CALLBACK_FREQ;
+
+		let mut ci_channel = CiChannel {
+			task_handle,
+			co_channel,
+			device: device.to_string(),
+			config,
+			sample_rate,
+			time_axis: TimeAxis::new(sample_rate as f64),
+			batch_size,
+			samples_read: 0,
+		};
+
+		ci_channel.setup()?;
+
+		Ok(ci_channel)
+	}
+
+	pub fn config(&self) -> &M {
+		&self.config
+	}
+
+	// Coerced sample clock rate, and when the sample clock was last launched
+	pub fn time_axis(&self) -> TimeAxis {
+		self.time_axis
+	}
+
+	// Starts the counter task, then the sample clock driving it. A relaunched task counts its
+	// samples from 1 again.
+	pub fn launch_task(&mut self) -> Result<(), DaqError> {
+		self.arm()?;
+		self.fire()
+	}
+
+	// Launches a finite acquisition and waits for all of it
+	pub fn acquire(&mut self) -> Result<Vec<M::Reading>, DaqError> {
+		let n_samps = self.config.setup().sample_mode.finite_samps()?;
+		let timeout = n_samps as f64 / self.sample_rate as f64 + SAMPLE_TIMEOUT_SECS;
+
+		self.launch_task()?;
+		self.read_to_end(Duration::from_secs_f64(timeout))
+	}
+
+	fn setup(&mut self) -> Result<(), DaqError> {
+		let internal_daqmx_buf_size = 10 * self.sample_rate as u64;
+		let setup = *self.config.setup();
+
+		let chan_desc = counter_generate_chan_desc(&self.device, setup.counter_id);
+		self.config.create_channel(&mut self.task_handle, &chan_desc)?;
+
+		let clk_src = generate_clock_src_desc(&self.device, setup.clk_src_output_pfi_id);
+		let (sample_mode, samps_per_chan) = setup.sample_mode.as_daqmx(internal_daqmx_buf_size);
+		self.task_handle.configure_sample_clock(
+			&clk_src,
+			self.sample_rate as f64,
+			sample_mode,
+			samps_per_chan,
+		)?;
+		// The clock comes in on a PFI line, DAQmx only knows the rate it was given for it. The
+		// counter generating it runs at its coerced frequency.
+		self.time_axis = TimeAxis::new(self.co_channel.freq()?);
+
+		match setup.start_trigger {
+			Some(trigger) => self.task_handle.configure_start_trigger(
+				&trigger.source.terminal_desc(&self.device),
+				trigger.edge.as_daqmx(),
+			),
+			None => Ok(()),
+		}
+	}
+}
+
+impl<M: CiMeasurement> Arm for CiChannel<M> {
+	fn arm(&mut self) -> Result<(), DaqError> {
+		self.task_handle.start()?;
+		self.samples_read = 0;
+
+		Ok(())
+	}
+
+	// The sample clock counter doubles as a trigger source for tasks sharing the clock
+	fn fire(&mut self) -> Result<(), DaqError> {
+		self.co_channel.launch()?;
+		self.time_axis.start = Some(get_steady_time_nanoseconds());
+
+		Ok(())
+	}
+}
+
+// The sample clock task follows the counter task through its lifecycle
+impl<M: CiMeasurement> TaskControl for CiChannel<M> {
+	fn task_handle(&mut self) -> &mut TaskHandle {
+		&mut self.task_handle
+	}
+
+	// Stops the sample clock, then the counter task, both can be launched again
+	fn stop_task(&mut self) -> Result<(), DaqError> {
+		self.co_channel.stop()?;
+		self.task_handle.stop()
+	}
+
+	fn verify_task(&mut self) -> Result<(), DaqError> {
+		self.task_handle.verify()?;
+		self.co_channel.verify()
+	}
+
+	fn commit_task(&mut self) -> Result<(), DaqError> {
+		self.task_handle.commit()?;
+		self.co_channel.commit()
+	}
+}
+
+impl<M: CiMeasurement> BlockingRead for CiChannel<M> {
+	type Sample = M::Reading;
+
+	fn read(&mut self, n_samps: usize, timeout: Duration) -> Result<Vec<M::Reading>, DaqError> {
+		let mut buf = vec![M::Raw::default(); n_samps];
+
+		let n_read = self.task_handle.read_counter(n_samps, timeout.as_secs_f64(), &mut buf)?;
+
+		let first = self.samples_read + 1;	// Sample count, same as the streams
+		self.samples_read += n_read as u64;
+
+		Ok(to_readings::<M>(&buf[..n_read], first, self.time_axis))
+	}
+
+	fn sample_mode(&self) -> SampleMode {
+		self.config.setup().sample_mode
+	}
+
+	fn samples_read(&self) -> u64 {
+		self.samples_read
+	}
+}
+
+fn to_readings<M: CiMeasurement>(raw: &[M::Raw], first: u64, time_axis: TimeAxis) -> Vec<M::Reading> {
+	raw.iter()
+		.zip(first..)
+		.map(|(&raw, sample)| M::reading(sample, time_axis.seconds(sample), raw))
+		.collect()
+}
+
+// Readings of a stream, timed like the BlockingRead ones
+pub struct CiReader<M> {
+	time_axis: TimeAxis,	// Only the rate is used, so it needn't be launched yet
+	measurement: PhantomData<M>,
+}
+
+impl<M: CiMeasurement> StreamRead for CiReader<M> {
+	type Sample = M::Reading;
+	const READ_CALL: &'static str = <M::Raw as CounterSample>::READ_CALL;
+
+	fn read(
+		&mut self,
+		task_handle: &mut RawTaskHandle,
+		n_samps: u32,
+		first: u64,
+	) -> Result<Vec<M::Reading>, DaqError> {
+		let mut buf = vec![M::Raw::default(); n_samps as usize];
+
+		let n_read = task_handle.read_counter(n_samps as usize, SAMPLE_TIMEOUT_SECS, &mut buf)?;
+
+		Ok(to_readings::<M>(&buf[..n_read], first, self.time_axis))
+	}
+}
+
+impl<M: CiMeasurement> StreamChannel for CiChannel<M> {
+	type Reader = CiReader<M>;
+
+	fn stream_setup(&self) -> Result<StreamSetup<CiReader<M>>, DaqError> {
+		Ok(StreamSetup {
+			reader: CiReader {
+				time_axis: self.time_axis,
+				measurement: PhantomData,
+			},
+			batch_size: self.batch_size as u32,
+			sample_mode: self.config.setup().sample_mode,
+		})
+	}
+}
+
+pub type AsyncCiChannel<M> = AsyncChannel<CiChannel<M>>;
+
+fn generate_clock_src_desc(device: &str, pfi_id: u8) -> String {
+	terminal_desc(device, &format!("PFI{}", pfi_id))
+}
@@ -1,271 +1,350 @@
 use super::{
-	co_channel::*,
-	counter_generate_chan_desc,
-	task_handle::{RawTaskHandle, TaskHandle},
-	DAQ_CALLBACK_FREQ, EMPTY_CSTRING, SAMPLE_TIMEOUT_SECS, SCAN_WARNING,
-	// get_steady_time_nanoseconds, CALLBACK_PERIOD,
+	ci_channel::{AsyncCiChannel, CiChannel, CiMeasurement, CiSetup},
+	error::DaqError,
+	task_handle::TaskHandle,
 };
 
-use std::{ffi::CString, fmt, ptr};
-
-use futures::{
-	stream::Stream,
-	sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
-	Poll,
-};
-
-const CLK_SRC_OUTPUT_PFI_ID: u8 = 13;
-const CLK_SRC_COUNTER_ID: u8 = 1;
-const ENCODER_COUNTER_ID: u8 = 0;
-
-const DUTY_CYCLE: f64 = 0.5;
-
-static mut ENC_CALLBACK_INIT_TIME: Option<u64> = None;		// Initial timestamp [ns]
-static mut ENCODER_CALLBACKS: u64 = 0;			// # of encoder callbacks for this stream
+use std::fmt;
 
 pub type EncoderTick = i32;
-type RawScanData = Box<[EncoderTick]>;
 
 pub struct EncoderReading {
-	pub timestamp: u64,
+	pub sample: u64,
+	pub time: f64,	// [s]
 	pub pos: EncoderTick,
 }
 
 impl EncoderReading {
-	fn new(timestamp: u64, pos: EncoderTick) -> Self {
-		Self { timestamp, pos }
+	fn new(sample: u64, time: f64, pos: EncoderTick) -> Self {
+		Self { sample, time, pos }
 	}
 }
 
 impl fmt::Display for EncoderReading {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(f, "{},{}", self.timestamp, self.pos)
+		write!(f, "{},{},{}", self.sample, self.time, self.pos)
 	}
 }
 
-struct BatchedScan {
-	data: RawScanData,
-	timestamp: u64,
+// Position in the linear encoder's units
+pub struct LinEncoderReading {
+	pub sample: u64,
+	pub time: f64,	// [s]
+	pub pos: f64,
 }
 
-impl BatchedScan {
-	unsafe fn new_uninit(batch_size: usize) -> Self {
-		use std::mem::uninitialized;
+impl fmt::Display for LinEncoderReading {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{},{},{}", self.sample, self.time, self.pos)
+	}
+}
+
+// Velocity in the channel's units, computed by the counter hardware
+pub struct AngVelocityReading {
+	pub sample: u64,
+	pub time: f64,	// [s]
+	pub velocity: f64,
+}
+
+impl fmt::Display for AngVelocityReading {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{},{},{}", self.sample, self.time, self.velocity)
+	}
+}
 
-		let boxed: RawScanData = (0..batch_size).map(|_| uninitialized()).collect();
+pub type CiEncoderChannel = CiChannel<EncoderConfig>;
+pub type AsyncEncoderChannel = AsyncCiChannel<EncoderConfig>;
+pub type CiLinEncoderChannel = CiChannel<LinEncoderConfig>;
+pub type AsyncLinEncoderChannel = AsyncCiChannel<LinEncoderConfig>;
+pub type CiAngVelocityChannel = CiChannel<AngVelocityConfig>;
+pub type AsyncAngVelocityChannel = AsyncCiChannel<AngVelocityConfig>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodingType {
+	X1,
+	X2,
+	X4,
+	TwoPulseCounting,
+}
 
-		Self {
-			data: boxed,
-			timestamp: 0,
+impl DecodingType {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			DecodingType::X1 => nidaqmx_sys::DAQmx_Val_X1 as i32,
+			DecodingType::X2 => nidaqmx_sys::DAQmx_Val_X2 as i32,
+			DecodingType::X4 => nidaqmx_sys::DAQmx_Val_X4 as i32,
+			DecodingType::TwoPulseCounting => nidaqmx_sys::DAQmx_Val_TwoPulseCounting as i32,
 		}
 	}
 
-	fn as_encoder_reading_iter<'a>(
-		&'a self,
-		_sample_rate: usize,
-	) -> impl Iterator<Item = EncoderReading> + 'a {
-		// const TO_NANOSEC: u64 = 1e9 as u64;
-
-		let base_ts = self.timestamp;
-		let data_len = self.data.len() as u32;
-
-		let tstamp =
-			// (0..data_len).map(move |ind| base_ts - ind as u64 * TO_NANOSEC / _sample_rate as u64);
-			(0..data_len).map(move |ind| base_ts - ind as u64);
-
-		self.data
-			.iter()
-			.rev()
-			.zip(tstamp)
-			.map(|(pos, ts)| EncoderReading::new(ts, *pos))
-			.rev()
+	// Counts per encoder pulse
+	pub fn factor(self) -> u32 {
+		match self {
+			DecodingType::X2 => 2,
+			DecodingType::X4 => 4,
+			_ => 1,
+		}
 	}
 }
 
-pub struct CiEncoderChannel {
-	task_handle: TaskHandle,
-	_co_channel: CoFreqChannel,
-	sample_rate: usize,
-	batch_size: usize,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AngleUnits {
+	Ticks,
+	Degrees,
+	Radians,
 }
 
-impl CiEncoderChannel {
-	pub fn new(sample_rate: usize) -> Self {
-		let task_handle = TaskHandle::new();
-		let _co_channel = CoFreqChannel::new(CLK_SRC_COUNTER_ID, sample_rate as f64, DUTY_CYCLE);
-		let batch_size = sample_rate / DAQ_CALLBACK_FREQ;
-
-		let mut ci_encoder_channel = CiEncoderChannel {
-			task_handle,
-			_co_channel,
-			sample_rate,
-			batch_size,
-		};
+impl AngleUnits {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			AngleUnits::Ticks => nidaqmx_sys::DAQmx_Val_Ticks as i32,
+			AngleUnits::Degrees => nidaqmx_sys::DAQmx_Val_Degrees as i32,
+			AngleUnits::Radians => nidaqmx_sys::DAQmx_Val_Radians as i32,
+		}
+	}
+}
 
-		ci_encoder_channel.setup();
+// State of the A and B signals at which the z index pulse resets the count
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZIndexPhase {
+	AHighBHigh,
+	AHighBLow,
+	ALowBHigh,
+	ALowBLow,
+}
 
-		ci_encoder_channel
+impl ZIndexPhase {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			ZIndexPhase::AHighBHigh => nidaqmx_sys::DAQmx_Val_AHighBHigh as i32,
+			ZIndexPhase::AHighBLow => nidaqmx_sys::DAQmx_Val_AHighBLow as i32,
+			ZIndexPhase::ALowBHigh => nidaqmx_sys::DAQmx_Val_ALowBHigh as i32,
+			ZIndexPhase::ALowBLow => nidaqmx_sys::DAQmx_Val_ALowBLow as i32,
+		}
 	}
+}
 
-	pub fn make_async(mut self) -> AsyncEncoderChannel {
-		let (snd, recv) = mpsc::unbounded();
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZIndex {
+	// Position the count is reset to, in the encoder units
+	pub value: f64,
+	pub phase: ZIndexPhase,
+}
 
-		let internal = AsyncEncoderChanInternal {
-			sender: snd,
-			sample_rate: self.sample_rate,
-		};
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LengthUnits {
+	Ticks,
+	Meters,
+	Inches,
+}
 
-		unsafe
-		{
-			// Save the initial time, and initialize the "callback counter"
-			ENC_CALLBACK_INIT_TIME = None;
-			ENCODER_CALLBACKS = 0;
+impl LengthUnits {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			LengthUnits::Ticks => nidaqmx_sys::DAQmx_Val_Ticks as i32,
+			LengthUnits::Meters => nidaqmx_sys::DAQmx_Val_Meters as i32,
+			LengthUnits::Inches => nidaqmx_sys::DAQmx_Val_Inches as i32,
+		}
+	}
+}
 
-			self.task_handle.register_read_callback(
-				self.batch_size as u32,
-				async_read_callback_impl,
-				internal,
-			);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AngVelocityUnits {
+	Rpm,
+	RadiansPerSecond,
+	DegreesPerSecond,
+}
 
-			// Don't care about the done callback
-			self.task_handle.register_done_callback(|_| (), ());
+impl AngVelocityUnits {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			AngVelocityUnits::Rpm => nidaqmx_sys::DAQmx_Val_RPM as i32,
+			AngVelocityUnits::RadiansPerSecond => nidaqmx_sys::DAQmx_Val_RadiansPerSecond as i32,
+			AngVelocityUnits::DegreesPerSecond => nidaqmx_sys::DAQmx_Val_DegreesPerSecond as i32,
 		}
+	}
+}
+
+// DAQmx still wants a phase when the z index is disabled
+fn z_index_args(z_index: Option<ZIndex>) -> (bool, ZIndex) {
+	match z_index {
+		Some(z_idx) => (true, z_idx),
+		None => (
+			false,
+			ZIndex {
+				value: 0.0,
+				phase: ZIndexPhase::ALowBLow,
+			},
+		),
+	}
+}
+
+// Readings are always raw ticks (DAQmxReadCounterU32), `units` sets the scale DAQmx uses for
+// the z index value and the initial angle
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncoderConfig {
+	pub decoding: DecodingType,
+	pub pulses_per_rev: u32,
+	pub z_index: Option<ZIndex>,
+	pub units: AngleUnits,
+	pub initial_angle: f64,
+	pub setup: CiSetup,
+}
+
+impl EncoderConfig {
+	pub fn ticks_per_rev(&self) -> u32 {
+		self.pulses_per_rev * self.decoding.factor()
+	}
+}
 
-		AsyncEncoderChannel
-		{
-			_encoder_chan: self,
-			recv,
+impl Default for EncoderConfig {
+	fn default() -> Self {
+		EncoderConfig {
+			decoding: DecodingType::X4,
+			pulses_per_rev: 500,
+			z_index: Some(ZIndex {
+				value: 0.0,
+				phase: ZIndexPhase::ALowBLow,
+			}),
+			units: AngleUnits::Ticks,
+			initial_angle: 0.0,
+			setup: CiSetup::default(),
 		}
 	}
+}
 
-	fn setup(&mut self) {
-		let internal_daqmx_buf_size = 10 * self.sample_rate as u64;
+impl CiMeasurement for EncoderConfig {
+	type Raw = u32;
+	type Reading = EncoderReading;
 
-		self.create_channel(ENCODER_COUNTER_ID);
+	fn setup(&self) -> &CiSetup {
+		&self.setup
+	}
 
-		let clk_src = generate_clock_src_desc(CLK_SRC_OUTPUT_PFI_ID);
-		self.task_handle.configure_sample_clock(
-			&clk_src,
-			self.sample_rate as f64,
-			internal_daqmx_buf_size,
-		);
+	fn create_channel(&self, task_handle: &mut TaskHandle, chan_desc: &str) -> Result<(), DaqError> {
+		let (use_z_idx, z_idx) = z_index_args(self.z_index);
+
+		task_handle.create_ci_ang_encoder_chan(
+			chan_desc,
+			self.decoding.as_daqmx(),
+			use_z_idx,
+			z_idx.value,
+			z_idx.phase.as_daqmx(),
+			self.units.as_daqmx(),
+			self.pulses_per_rev,
+			self.initial_angle,
+		)
 	}
 
-	fn create_channel(&mut self, id: u8) {
-		const USE_ENCODER_IDX: bool = true;
-		const IDX_PULSE_POSITION: f64 = 0.0;
-		const PULSE_PER_REV: u32 = 500;
-		const INITIAL_POSITION: f64 = 0.0;
-
-		let name_of_channel = EMPTY_CSTRING;
-		let chan_desc = CString::new(counter_generate_chan_desc(id)).unwrap();
-
-		let err_code = unsafe {
-			nidaqmx_sys::DAQmxCreateCIAngEncoderChan(
-				self.task_handle.get(),
-				chan_desc.as_ptr(),
-				name_of_channel,
-				nidaqmx_sys::DAQmx_Val_X4 as i32,
-				USE_ENCODER_IDX as u32,
-				IDX_PULSE_POSITION,
-				nidaqmx_sys::DAQmx_Val_ALowBLow as i32,
-				nidaqmx_sys::DAQmx_Val_Ticks as i32,
-				PULSE_PER_REV,
-				INITIAL_POSITION,
-				ptr::null_mut(),
-			)
-		};
-
-		self.task_handle.chk_err_code(err_code);
+	fn reading(sample: u64, time: f64, raw: u32) -> EncoderReading {
+		EncoderReading::new(sample, time, raw as EncoderTick)	// Counts down below zero
 	}
 }
 
-struct AsyncEncoderChanInternal {
-	sender: UnboundedSender<EncoderReading>,
-	sample_rate: usize,
+// Readings are scaled to `units`, `dist_per_pulse` and the z index value are in them as well
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinEncoderConfig {
+	pub decoding: DecodingType,
+	pub dist_per_pulse: f64,
+	pub z_index: Option<ZIndex>,
+	pub units: LengthUnits,
+	pub initial_pos: f64,
+	pub setup: CiSetup,
 }
 
-pub struct AsyncEncoderChannel {
-	_encoder_chan: CiEncoderChannel,
-	recv: UnboundedReceiver<EncoderReading>,
+impl Default for LinEncoderConfig {
+	fn default() -> Self {
+		LinEncoderConfig {
+			decoding: DecodingType::X4,
+			dist_per_pulse: 1e-5,
+			z_index: None,
+			units: LengthUnits::Meters,
+			initial_pos: 0.0,
+			setup: CiSetup::default(),
+		}
+	}
 }
 
-impl AsyncEncoderChannel
-{
-	pub fn launch_task(&mut self) -> ()
-	{
-		self._encoder_chan.task_handle.launch();
+impl CiMeasurement for LinEncoderConfig {
+	type Raw = f64;
+	type Reading = LinEncoderReading;
+
+	fn setup(&self) -> &CiSetup {
+		&self.setup
 	}
-}
 
-impl Stream for AsyncEncoderChannel {
-	type Item = EncoderReading;
-	type Error = ();
+	fn create_channel(&self, task_handle: &mut TaskHandle, chan_desc: &str) -> Result<(), DaqError> {
+		let (use_z_idx, z_idx) = z_index_args(self.z_index);
+
+		task_handle.create_ci_lin_encoder_chan(
+			chan_desc,
+			self.decoding.as_daqmx(),
+			use_z_idx,
+			z_idx.value,
+			z_idx.phase.as_daqmx(),
+			self.units.as_daqmx(),
+			self.dist_per_pulse,
+			self.initial_pos,
+		)
+	}
 
-	fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-		self.recv.poll()
+	fn reading(sample: u64, time: f64, pos: f64) -> LinEncoderReading {
+		LinEncoderReading {
+			sample,
+			time,
+			pos,
+		}
 	}
 }
 
-fn generate_clock_src_desc(pfi_id: u8) -> String {
-	let desc = format!("/Dev1/PFI{}", pfi_id);
-	desc
+// `min_val` and `max_val` are the expected velocities, DAQmx sizes its measurement window on them
+#[derive(Debug, Clone, PartialEq)]
+pub struct AngVelocityConfig {
+	pub decoding: DecodingType,
+	pub pulses_per_rev: u32,
+	pub units: AngVelocityUnits,
+	pub min_val: f64,
+	pub max_val: f64,
+	pub setup: CiSetup,
 }
 
-unsafe fn read_digital_u32(
-	task_handle: &mut RawTaskHandle,
-	n_samps: u32,
-) -> Result<BatchedScan, i32> {
-	let mut samps_read = 0i32;
-	let samps_read_ptr = &mut samps_read as *mut _;
-
-	let mut scan = BatchedScan::new_uninit(n_samps as usize);
-
-	ENCODER_CALLBACKS += 1;
-	// if ENC_CALLBACK_INIT_TIME == None
-	// {
-	// 	ENC_CALLBACK_INIT_TIME = Some(get_steady_time_nanoseconds());
-	// };
-
-	// scan.timestamp = ENC_CALLBACK_INIT_TIME.unwrap() + ENCODER_CALLBACKS * CALLBACK_PERIOD;
-	// scan.timestamp = get_steady_time_nanoseconds();	// Actual timestamp (jittery)
-	scan.timestamp = ENCODER_CALLBACKS * n_samps as u64;	// Sample count
-
-	let buf_len = scan.data.len();
-	let buf_ptr = scan.data.as_mut_ptr() as *mut u32; // pretend the i32 is a u32
-
-	let err_code = nidaqmx_sys::DAQmxReadCounterU32(
-		task_handle.get().as_ptr(),
-		n_samps as i32,
-		SAMPLE_TIMEOUT_SECS,
-		buf_ptr,
-		buf_len as u32,
-		samps_read_ptr,
-		ptr::null_mut(),
-	);
-
-	match err_code {
-		0 if samps_read == n_samps as i32 => Ok(scan),
-		0 => Err(SCAN_WARNING),
-		_ => Err(err_code),
+impl Default for AngVelocityConfig {
+	fn default() -> Self {
+		AngVelocityConfig {
+			decoding: DecodingType::X4,
+			pulses_per_rev: 500,
+			units: AngVelocityUnits::Rpm,
+			min_val: 0.0,
+			max_val: 1000.0,
+			setup: CiSetup::default(),
+		}
 	}
 }
 
-fn async_read_callback_impl(
-	scan_chan: &mut AsyncEncoderChanInternal,
-	task_handle: &mut RawTaskHandle,
-	n_samps: u32,
-) -> Result<(), ()> {
-	let send_channel = &scan_chan.sender;
-	let sample_rate = scan_chan.sample_rate;
+impl CiMeasurement for AngVelocityConfig {
+	type Raw = f64;
+	type Reading = AngVelocityReading;
 
-	let batch = unsafe { read_digital_u32(task_handle, n_samps) }
-		.map_err(|err_code| task_handle.chk_err_code(err_code))?;
+	fn setup(&self) -> &CiSetup {
+		&self.setup
+	}
 
-	batch
-		.as_encoder_reading_iter(sample_rate)
-		.try_for_each(|enc| send_channel.unbounded_send(enc))
-		.map_err(|_| ())?;
+	fn create_channel(&self, task_handle: &mut TaskHandle, chan_desc: &str) -> Result<(), DaqError> {
+		task_handle.create_ci_ang_velocity_chan(
+			chan_desc,
+			self.min_val,
+			self.max_val,
+			self.decoding.as_daqmx(),
+			self.units.as_daqmx(),
+			self.pulses_per_rev,
+		)
+	}
 
-	Ok(())
+	fn reading(sample: u64, time: f64, velocity: f64) -> AngVelocityReading {
+		AngVelocityReading {
+			sample,
+			time,
+			velocity,
+		}
+	}
 }
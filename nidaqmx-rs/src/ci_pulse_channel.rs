@@ -0,0 +1,291 @@
+use super::{
+	ci_channel::{AsyncCiChannel, CiChannel, CiMeasurement, CiSetup},
+	error::DaqError,
+	task_handle::TaskHandle,
+	trigger::Edge,
+};
+
+use std::fmt;
+
+// Pulses are counted on, and frequencies and periods measured at, the counter's default input
+// terminal, e.g. PFI8 for ctr0 on X series devices
+
+// Edges counted since the task started, offset by the initial count
+pub struct EdgeCountReading {
+	pub sample: u64,
+	pub time: f64,	// [s]
+	pub count: u32,
+}
+
+impl fmt::Display for EdgeCountReading {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{},{},{}", self.sample, self.time, self.count)
+	}
+}
+
+// Pulse frequency in the channel's units, [Hz] unless read in ticks
+pub struct FrequencyReading {
+	pub sample: u64,
+	pub time: f64,	// [s]
+	pub freq: f64,
+}
+
+impl fmt::Display for FrequencyReading {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{},{},{}", self.sample, self.time, self.freq)
+	}
+}
+
+// Pulse period in the channel's units, [s] unless read in ticks
+pub struct PeriodReading {
+	pub sample: u64,
+	pub time: f64,	// [s]
+	pub period: f64,
+}
+
+impl fmt::Display for PeriodReading {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{},{},{}", self.sample, self.time, self.period)
+	}
+}
+
+pub type CiCountEdgesChannel = CiChannel<CountEdgesConfig>;
+pub type AsyncCountEdgesChannel = AsyncCiChannel<CountEdgesConfig>;
+pub type CiFreqChannel = CiChannel<FreqConfig>;
+pub type AsyncFreqChannel = AsyncCiChannel<FreqConfig>;
+pub type CiPeriodChannel = CiChannel<PeriodConfig>;
+pub type AsyncPeriodChannel = AsyncCiChannel<PeriodConfig>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CountDirection {
+	Up,
+	Down,
+	// Counts up while the counter's aux line is high
+	ExternallyControlled,
+}
+
+impl CountDirection {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			CountDirection::Up => nidaqmx_sys::DAQmx_Val_CountUp as i32,
+			CountDirection::Down => nidaqmx_sys::DAQmx_Val_CountDown as i32,
+			CountDirection::ExternallyControlled => nidaqmx_sys::DAQmx_Val_ExtControlled as i32,
+		}
+	}
+}
+
+// How the counter measures a frequency or period
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MeasMethod {
+	// Times a single period of the signal against the timebase, best for slow signals
+	LowFreq1Ctr,
+	// Counts pulses over `meas_time`, best for fast signals
+	HighFreq2Ctr,
+	// Times `divisor` periods at once, for signals spanning a large range
+	LargeRange2Ctr,
+}
+
+impl MeasMethod {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			MeasMethod::LowFreq1Ctr => nidaqmx_sys::DAQmx_Val_LowFreq1Ctr as i32,
+			MeasMethod::HighFreq2Ctr => nidaqmx_sys::DAQmx_Val_HighFreq2Ctr as i32,
+			MeasMethod::LargeRange2Ctr => nidaqmx_sys::DAQmx_Val_LargeRng2Ctr as i32,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrequencyUnits {
+	Hz,
+	// Timebase ticks per period
+	Ticks,
+}
+
+impl FrequencyUnits {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			FrequencyUnits::Hz => nidaqmx_sys::DAQmx_Val_Hz as i32,
+			FrequencyUnits::Ticks => nidaqmx_sys::DAQmx_Val_Ticks as i32,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeUnits {
+	Seconds,
+	// Timebase ticks
+	Ticks,
+}
+
+impl TimeUnits {
+	fn as_daqmx(self) -> i32 {
+		match self {
+			TimeUnits::Seconds => nidaqmx_sys::DAQmx_Val_Seconds as i32,
+			TimeUnits::Ticks => nidaqmx_sys::DAQmx_Val_Ticks as i32,
+		}
+	}
+}
+
+// Readings are raw counts (DAQmxReadCounterU32), counting down wraps below zero
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountEdgesConfig {
+	pub edge: Edge,
+	pub initial_count: u32,
+	pub direction: CountDirection,
+	pub setup: CiSetup,
+}
+
+impl Default for CountEdgesConfig {
+	fn default() -> Self {
+		CountEdgesConfig {
+			edge: Edge::Rising,
+			initial_count: 0,
+			direction: CountDirection::Up,
+			setup: CiSetup::default(),
+		}
+	}
+}
+
+impl CiMeasurement for CountEdgesConfig {
+	type Raw = u32;
+	type Reading = EdgeCountReading;
+
+	fn setup(&self) -> &CiSetup {
+		&self.setup
+	}
+
+	fn create_channel(&self, task_handle: &mut TaskHandle, chan_desc: &str) -> Result<(), DaqError> {
+		task_handle.create_ci_count_edges_chan(
+			chan_desc,
+			self.edge.as_daqmx(),
+			self.initial_count,
+			self.direction.as_daqmx(),
+		)
+	}
+
+	fn reading(sample: u64, time: f64, count: u32) -> EdgeCountReading {
+		EdgeCountReading {
+			sample,
+			time,
+			count,
+		}
+	}
+}
+
+// `min_val` and `max_val` are the expected frequencies, `meas_time` [s] is only used by
+// HighFreq2Ctr and `divisor` by LargeRange2Ctr
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreqConfig {
+	pub min_val: f64,
+	pub max_val: f64,
+	pub units: FrequencyUnits,
+	pub edge: Edge,
+	pub method: MeasMethod,
+	pub meas_time: f64,
+	pub divisor: u32,
+	pub setup: CiSetup,
+}
+
+impl Default for FreqConfig {
+	fn default() -> Self {
+		FreqConfig {
+			min_val: 1.0,
+			max_val: 1000.0,
+			units: FrequencyUnits::Hz,
+			edge: Edge::Rising,
+			method: MeasMethod::LowFreq1Ctr,
+			meas_time: 0.001,
+			divisor: 4,
+			setup: CiSetup::default(),
+		}
+	}
+}
+
+impl CiMeasurement for FreqConfig {
+	type Raw = f64;
+	type Reading = FrequencyReading;
+
+	fn setup(&self) -> &CiSetup {
+		&self.setup
+	}
+
+	fn create_channel(&self, task_handle: &mut TaskHandle, chan_desc: &str) -> Result<(), DaqError> {
+		task_handle.create_ci_freq_chan(
+			chan_desc,
+			self.min_val,
+			self.max_val,
+			self.units.as_daqmx(),
+			self.edge.as_daqmx(),
+			self.method.as_daqmx(),
+			self.meas_time,
+			self.divisor,
+		)
+	}
+
+	fn reading(sample: u64, time: f64, freq: f64) -> FrequencyReading {
+		FrequencyReading {
+			sample,
+			time,
+			freq,
+		}
+	}
+}
+
+// `min_val` and `max_val` are the expected periods, `meas_time` and `divisor` as for FreqConfig
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodConfig {
+	pub min_val: f64,
+	pub max_val: f64,
+	pub units: TimeUnits,
+	pub edge: Edge,
+	pub method: MeasMethod,
+	pub meas_time: f64,
+	pub divisor: u32,
+	pub setup: CiSetup,
+}
+
+impl Default for PeriodConfig {
+	fn default() -> Self {
+		PeriodConfig {
+			min_val: 0.001,
+			max_val: 1.0,
+			units: TimeUnits::Seconds,
+			edge: Edge::Rising,
+			method: MeasMethod::LowFreq1Ctr,
+			meas_time: 0.001,
+			divisor: 4,
+			setup: CiSetup::default(),
+		}
+	}
+}
+
+impl CiMeasurement for PeriodConfig {
+	type Raw = f64;
+	type Reading = PeriodReading;
+
+	fn setup(&self) -> &CiSetup {
+		&self.setup
+	}
+
+	fn create_channel(&self, task_handle: &mut TaskHandle, chan_desc: &str) -> Result<(), DaqError> {
+		task_handle.create_ci_period_chan(
+			chan_desc,
+			self.min_val,
+			self.max_val,
+			self.units.as_daqmx(),
+			self.edge.as_daqmx(),
+			self.method.as_daqmx(),
+			self.meas_time,
+			self.divisor,
+		)
+	}
+
+	fn reading(sample: u64, time: f64, period: f64) -> PeriodReading {
+		PeriodReading {
+			sample,
+			time,
+			period,
+		}
+	}
+}
@@ -0,0 +1,87 @@
+use super::TIMEPOINT;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+// The Unix epoch (Jan 1, 1970) in the 100 ns FILETIME ticks since Jan 1, 1601 that the
+// TIMEPOINT is given in
+const UNIX_EPOCH_FILETIME: u64 = 116_444_736_000_000_000;
+
+// Nanoseconds from an arbitrary point, e.g. boot. Never jumps, so it's the one to measure
+// intervals with, but it can't be compared across machines or reboots.
+pub fn monotonic_ns() -> u64 {
+	platform::monotonic_ns()
+}
+
+// Nanoseconds since the Unix epoch, as precise as the OS keeps it. Steps when the system clock
+// is adjusted.
+pub fn utc_ns() -> u64 {
+	platform::utc_ns()
+}
+
+// UTC minus the TIMEPOINT, small enough to write to data files without losing precision
+pub fn utc_since_timepoint_ns() -> u64 {
+	utc_ns().saturating_sub((TIMEPOINT - UNIX_EPOCH_FILETIME) * 100)
+}
+
+#[cfg(unix)]
+mod platform {
+	use super::NANOS_PER_SEC;
+
+	pub fn monotonic_ns() -> u64 {
+		clock_ns(libc::CLOCK_MONOTONIC)
+	}
+
+	pub fn utc_ns() -> u64 {
+		clock_ns(libc::CLOCK_REALTIME)
+	}
+
+	fn clock_ns(clock: libc::clockid_t) -> u64 {
+		let mut ts = libc::timespec {
+			tv_sec: 0,
+			tv_nsec: 0,
+		};
+
+		// Only fails for clocks the system doesn't have, and these two are always there
+		unsafe { libc::clock_gettime(clock, &mut ts) };
+
+		ts.tv_sec as u64 * NANOS_PER_SEC + ts.tv_nsec as u64
+	}
+}
+
+#[cfg(windows)]
+mod platform {
+	use super::{NANOS_PER_SEC, UNIX_EPOCH_FILETIME};
+
+	use winapi::{
+		shared::minwindef::FILETIME,
+		um::{
+			profileapi::{QueryPerformanceCounter, QueryPerformanceFrequency},
+			sysinfoapi::GetSystemTimePreciseAsFileTime,
+			winnt::LARGE_INTEGER,
+		},
+	};
+
+	// The performance counter never fails on Windows XP and later
+	pub fn monotonic_ns() -> u64 {
+		let mut count = LARGE_INTEGER::default();
+		let mut freq = LARGE_INTEGER::default();
+
+		let (count, freq) = unsafe {
+			QueryPerformanceCounter(&mut count);
+			QueryPerformanceFrequency(&mut freq);
+
+			(*count.QuadPart() as u128, *freq.QuadPart() as u128)
+		};
+
+		(count * NANOS_PER_SEC as u128 / freq) as u64
+	}
+
+	pub fn utc_ns() -> u64 {
+		let mut file_t = FILETIME::default();
+		unsafe { GetSystemTimePreciseAsFileTime(&mut file_t) };
+
+		let t = ((file_t.dwHighDateTime as u64) << 32) | file_t.dwLowDateTime as u64;
+
+		(t - UNIX_EPOCH_FILETIME) * 100	// Windows counts in 100s of ns
+	}
+}
@@ -1,38 +1,71 @@
-use super::{task_handle::TaskHandle, counter_generate_chan_desc};
+use super::{
+	backend::{self, Backend},
+	counter_generate_chan_desc,
+	error::DaqError,
+	task_handle::TaskHandle,
+};
 
 pub struct CoFreqChannel {
 	task_handle: TaskHandle,
+	chan_desc: String,
 }
 
 impl CoFreqChannel {
-	pub fn new(counter_id: u8, freq: f64, duty_cycle: f64) -> Self {
-		let task_handle = TaskHandle::new();
-		let mut co_freq_channel = CoFreqChannel { task_handle };
+	pub fn new(device: &str, counter_id: u8, freq: f64, duty_cycle: f64) -> Result<Self, DaqError> {
+		Self::with_backend(backend::ni_daqmx(), device, counter_id, freq, duty_cycle)
+	}
+
+	pub fn with_backend(
+		backend: Backend,
+		device: &str,
+		counter_id: u8,
+		freq: f64,
+		duty_cycle: f64,
+	) -> Result<Self, DaqError> {
+		let mut task_handle = TaskHandle::with_backend(backend)?;
 
-		let chan_desc = counter_generate_chan_desc(counter_id);
+		let chan_desc = counter_generate_chan_desc(device, counter_id);
 
-		co_freq_channel.task_handle.create_co_freq_chan(&chan_desc, freq, duty_cycle);
-		co_freq_channel.configure_timing();
+		task_handle.create_co_freq_chan(&chan_desc, freq, duty_cycle)?;
+
+		let mut co_freq_channel = CoFreqChannel {
+			task_handle,
+			chan_desc,
+		};
+		co_freq_channel.configure_timing()?;
 
 		// We don't care about the done callback
-		unsafe { co_freq_channel.task_handle.register_done_callback(|_| (), ()) };
+		unsafe { co_freq_channel.task_handle.register_done_callback(|_, _, _| (), ())? };
 
-		co_freq_channel.task_handle.launch();
+		Ok(co_freq_channel)
+	}
 
-		co_freq_channel
+	// The pulse train only starts once launched, so tasks clocked or triggered by it can be
+	// started first
+	pub fn launch(&mut self) -> Result<(), DaqError> {
+		self.task_handle.start()
 	}
 
-	fn configure_timing(&mut self) {
-		const BUF_SIZE: u64 = 0;
+	pub fn stop(&mut self) -> Result<(), DaqError> {
+		self.task_handle.stop()
+	}
 
-		let err_code = unsafe {
-			nidaqmx_sys::DAQmxCfgImplicitTiming(
-				self.task_handle.get(),
-				nidaqmx_sys::DAQmx_Val_ContSamps as i32,
-				BUF_SIZE,
-			)
-		};
+	pub fn verify(&mut self) -> Result<(), DaqError> {
+		self.task_handle.verify()
+	}
+
+	pub fn commit(&mut self) -> Result<(), DaqError> {
+		self.task_handle.commit()
+	}
+
+	// The frequency asked for, coerced to what the counter's timebase divides down to [Hz]
+	pub fn freq(&mut self) -> Result<f64, DaqError> {
+		self.task_handle.co_pulse_freq(&self.chan_desc)
+	}
+
+	fn configure_timing(&mut self) -> Result<(), DaqError> {
+		const BUF_SIZE: u64 = 0;
 
-		self.task_handle.chk_err_code(err_code);
+		self.task_handle.configure_implicit_timing(BUF_SIZE)
 	}
 }
@@ -0,0 +1,259 @@
+use super::{
+	backend::{self, DaqBackend},
+	error::{self, DaqError},
+};
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+	// Name used in channel and terminal descriptions, e.g. "Dev1"
+	pub name: String,
+	pub product_type: String,
+	pub serial_number: u32,
+	// Fully qualified physical channels, e.g. "Dev1/ai0"
+	pub ai_channels: Vec<String>,
+	pub ci_channels: Vec<String>,
+	// Fully qualified terminals, e.g. "/Dev1/PFI13"
+	pub pfi_terminals: Vec<String>,
+}
+
+impl DeviceInfo {
+	pub fn query(backend: &dyn DaqBackend, name: &str) -> Result<Self, DaqError> {
+		let device = error::c_string(name, "DAQmxGetDevSerialNum")?;
+
+		let mut serial_number = 0u32;
+		let err_code = backend.get_dev_serial_num(&device, &mut serial_number);
+		error::chk_err_code(backend, err_code, "DAQmxGetDevSerialNum")?;
+
+		let product_type = query_string(backend, "DAQmxGetDevProductType", |buf| {
+			backend.get_dev_product_type(&device, buf)
+		})?;
+		let ai_channels = query_list(backend, "DAQmxGetDevAIPhysicalChans", |buf| {
+			backend.get_dev_ai_physical_chans(&device, buf)
+		})?;
+		let ci_channels = query_list(backend, "DAQmxGetDevCIPhysicalChans", |buf| {
+			backend.get_dev_ci_physical_chans(&device, buf)
+		})?;
+		let pfi_terminals = query_list(backend, "DAQmxGetDevTerminals", |buf| {
+			backend.get_dev_terminals(&device, buf)
+		})?
+		.into_iter()
+		.filter(|terminal| terminal.rsplit('/').next().is_some_and(|t| t.starts_with("PFI")))
+		.collect();
+
+		Ok(DeviceInfo {
+			name: name.to_string(),
+			product_type,
+			serial_number,
+			ai_channels,
+			ci_channels,
+			pfi_terminals,
+		})
+	}
+}
+
+// Local date and time a calibration was done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalDate {
+	pub year: u32,
+	pub month: u32,
+	pub day: u32,
+	pub hour: u32,
+	pub minute: u32,
+}
+
+impl fmt::Display for CalDate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{:04}-{:02}-{:02} {:02}:{:02}",
+			self.year, self.month, self.day, self.hour, self.minute
+		)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+	pub date: CalDate,
+	pub temperature: f64,	// Device temperature while calibrating [°C]
+}
+
+impl fmt::Display for Calibration {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at {:.1} °C", self.date, self.temperature)
+	}
+}
+
+// Devices without self-calibration or a temperature sensor, like the USB-600x, leave those None
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationInfo {
+	pub external: Calibration,
+	pub self_cal: Option<Calibration>,
+	pub temperature: Option<f64>,	// Current device temperature [°C]
+}
+
+impl CalibrationInfo {
+	pub fn query(backend: &dyn DaqBackend, name: &str) -> Result<Self, DaqError> {
+		let device = error::c_string(name, "DAQmxGetExtCalLastDateAndTime")?;
+
+		let external = query_calibration(
+			backend,
+			("DAQmxGetExtCalLastDateAndTime", "DAQmxGetExtCalLastTemp"),
+			|date| {
+				let CalDate {
+					year,
+					month,
+					day,
+					hour,
+					minute,
+				} = date;
+				backend.get_ext_cal_last_date_and_time(&device, year, month, day, hour, minute)
+			},
+			|temp| backend.get_ext_cal_last_temp(&device, temp),
+		)?;
+		let self_cal = not_supported_as_none(query_calibration(
+			backend,
+			("DAQmxGetSelfCalLastDateAndTime", "DAQmxGetSelfCalLastTemp"),
+			|date| {
+				let CalDate {
+					year,
+					month,
+					day,
+					hour,
+					minute,
+				} = date;
+				backend.get_self_cal_last_date_and_time(&device, year, month, day, hour, minute)
+			},
+			|temp| backend.get_self_cal_last_temp(&device, temp),
+		))?;
+
+		let mut temperature = 0.0;
+		let err_code = backend.get_cal_dev_temp(&device, &mut temperature);
+		let temperature = not_supported_as_none(
+			error::chk_err_code(backend, err_code, "DAQmxGetCalDevTemp").map(|_| temperature),
+		)?;
+
+		Ok(CalibrationInfo {
+			external,
+			self_cal,
+			temperature,
+		})
+	}
+}
+
+impl fmt::Display for CalibrationInfo {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "external calibration {}", self.external)?;
+
+		match self.self_cal {
+			Some(self_cal) => write!(f, ", self-calibration {}", self_cal)?,
+			None => write!(f, ", no self-calibration")?,
+		}
+
+		match self.temperature {
+			Some(temperature) => write!(f, ", now at {:.1} °C", temperature),
+			None => Ok(()),
+		}
+	}
+}
+
+// Aborts every task using the device and returns it to its power-on state, which also frees
+// whatever a crashed program left reserved
+pub fn reset_device(backend: &dyn DaqBackend, name: &str) -> Result<(), DaqError> {
+	let device = error::c_string(name, "DAQmxResetDevice")?;
+	let err_code = backend.reset_device(&device);
+	error::chk_err_code(backend, err_code, "DAQmxResetDevice")
+}
+
+// Fails with DAQmxErrorSelfTestFailed if the device doesn't pass its self-test
+pub fn self_test_device(backend: &dyn DaqBackend, name: &str) -> Result<(), DaqError> {
+	let device = error::c_string(name, "DAQmxSelfTestDevice")?;
+	let err_code = backend.self_test_device(&device);
+	error::chk_err_code(backend, err_code, "DAQmxSelfTestDevice")
+}
+
+// Every device known to the installed NI-DAQmx driver
+pub fn list_devices() -> Result<Vec<DeviceInfo>, DaqError> {
+	list_devices_with_backend(&*backend::ni_daqmx())
+}
+
+pub fn list_devices_with_backend(backend: &dyn DaqBackend) -> Result<Vec<DeviceInfo>, DaqError> {
+	query_list(backend, "DAQmxGetSysDevNames", |buf| backend.get_sys_dev_names(buf))?
+		.iter()
+		.map(|name| DeviceInfo::query(backend, name))
+		.collect()
+}
+
+// Runs a DAQmx string query twice, once for the size and once for the actual string
+fn query_string<F>(backend: &dyn DaqBackend, call: &'static str, query: F) -> Result<String, DaqError>
+where
+	F: Fn(&mut [u8]) -> i32,
+{
+	let size = query(&mut []);
+	error::chk_err_code(backend, size, call)?;
+
+	if size == 0 {
+		return Ok(String::new());
+	}
+
+	let mut buf = vec![0u8; size as usize];
+	let err_code = query(&mut buf);
+	error::chk_err_code(backend, err_code, call)?;
+
+	let len = buf.iter().position(|&c| c == b'\0').unwrap_or(buf.len());
+	buf.truncate(len);
+
+	Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+// DAQmx lists are comma separated, e.g. "Dev1/ai0, Dev1/ai1"
+fn query_list<F>(backend: &dyn DaqBackend, call: &'static str, query: F) -> Result<Vec<String>, DaqError>
+where
+	F: Fn(&mut [u8]) -> i32,
+{
+	let list = query_string(backend, call, query)?;
+
+	Ok(list
+		.split(',')
+		.map(str::trim)
+		.filter(|item| !item.is_empty())
+		.map(String::from)
+		.collect())
+}
+
+// Date and temperature of a calibration, `calls` names the two queries for errors
+fn query_calibration<D, T>(
+	backend: &dyn DaqBackend,
+	calls: (&'static str, &'static str),
+	date_query: D,
+	temp_query: T,
+) -> Result<Calibration, DaqError>
+where
+	D: Fn(&mut CalDate) -> i32,
+	T: Fn(&mut f64) -> i32,
+{
+	let mut date = CalDate {
+		year: 0,
+		month: 0,
+		day: 0,
+		hour: 0,
+		minute: 0,
+	};
+	let err_code = date_query(&mut date);
+	error::chk_err_code(backend, err_code, calls.0)?;
+
+	let mut temperature = 0.0;
+	let err_code = temp_query(&mut temperature);
+	error::chk_err_code(backend, err_code, calls.1)?;
+
+	Ok(Calibration { date, temperature })
+}
+
+// Attributes the device doesn't have are None rather than an error
+fn not_supported_as_none<T>(result: Result<T, DaqError>) -> Result<Option<T>, DaqError> {
+	match result {
+		Err(ref err) if err.code == nidaqmx_sys::DAQmxErrorAttrNotSupported => Ok(None),
+		result => result.map(Some),
+	}
+}
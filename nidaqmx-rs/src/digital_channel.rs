@@ -0,0 +1,443 @@
+use super::{
+	async_channel::{AsyncChannel, StreamChannel, StreamRead, StreamSetup},
+	backend::{self, Backend},
+	blocking::BlockingRead,
+	error::DaqError,
+	physical_chan_desc,
+	task_control::TaskControl,
+	task_handle::{RawTaskHandle, TaskHandle},
+	terminal_desc,
+	timing::{SampleMode, TimeAxis},
+	trigger::Arm,
+	get_steady_time_nanoseconds, DAQ_CALLBACK_FREQ, SAMPLE_TIMEOUT_SECS,
+};
+
+use std::{fmt, time::Duration};
+
+// One level per line, in the order the lines were given
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigitalScan {
+	pub data: Box<[bool]>,
+	// Counts from 1 at the start of the acquisition
+	pub sample: u64,
+	// Since the first sample, from the coerced sample clock rate [s]
+	pub time: f64,
+}
+
+impl fmt::Display for DigitalScan {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{},{}", self.sample, self.time)?;
+
+		for &level in self.data.iter() {
+			write!(f, ",{}", level as u8)?;
+		}
+
+		Ok(())
+	}
+}
+
+// Scans from a buffer of one byte per line, numbered up to `last_sample`. Reads without a sample
+// clock have no time axis, their scans are all at 0 s.
+fn to_scans(
+	buf: &[u8],
+	num_channels: usize,
+	last_sample: u64,
+	time_axis: Option<TimeAxis>,
+) -> Vec<DigitalScan> {
+	let n_scans = (buf.len() / num_channels.max(1)) as u64;
+
+	buf.chunks(num_channels.max(1))
+		.zip(last_sample + 1 - n_scans..)
+		.map(|(scan, sample)| DigitalScan {
+			data: scan.iter().map(|&level| level != 0).collect(),
+			sample,
+			time: time_axis.map_or(0.0, |time_axis| time_axis.seconds(sample)),
+		})
+		.collect()
+}
+
+fn to_bytes(levels: &[bool]) -> Vec<u8> {
+	levels.iter().map(|&level| level as u8).collect()
+}
+
+fn no_sample_clock(call: &'static str) -> DaqError {
+	DaqError::new(
+		nidaqmx_sys::DAQmxErrorInvalidTimingType,
+		"Streaming needs a channel with a sample clock.",
+		call,
+	)
+}
+
+pub struct DiChannelBuilder {
+	backend: Backend,
+	device: String,
+	lines: String,
+	sample_clock: Option<(String, usize)>,
+	sample_mode: SampleMode,
+}
+
+impl DiChannelBuilder {
+	pub fn backend(mut self, backend: Backend) -> Self {
+		self.backend = backend;
+		self
+	}
+
+	// Needed for streams, without a sample clock every read returns the current levels.
+	// `clk_src` is a terminal of the device like "PFI13", or empty for the onboard clock.
+	pub fn sample_clock<S: Into<String>>(mut self, clk_src: S, sample_rate: usize) -> Self {
+		self.sample_clock = Some((clk_src.into(), sample_rate));
+		self
+	}
+
+	// Continuous by default, only used with a sample clock
+	pub fn sample_mode(mut self, sample_mode: SampleMode) -> Self {
+		self.sample_mode = sample_mode;
+		self
+	}
+
+	pub fn build(self) -> Result<DiChannel, DaqError> {
+		let task_handle = TaskHandle::with_backend(self.backend)?;
+
+		let mut di_channel = DiChannel {
+			task_handle,
+			num_channels: 0,
+			sample_rate: None,
+			time_axis: None,
+			samples_read: 0,
+			sample_mode: self.sample_mode,
+		};
+
+		di_channel.init(&self.device, &self.lines, self.sample_clock)?;
+
+		Ok(di_channel)
+	}
+}
+
+// Digital input lines, e.g. "port0/line0:3", read as one channel per line
+pub struct DiChannel {
+	task_handle: TaskHandle,
+	num_channels: usize,
+	sample_rate: Option<usize>,
+	time_axis: Option<TimeAxis>,	// Set with the sample clock
+	samples_read: u64,	// # of samples per line read with BlockingRead
+	sample_mode: SampleMode,
+}
+
+impl DiChannel {
+	pub fn builder<S1: Into<String>, S2: Into<String>>(device: S1, lines: S2) -> DiChannelBuilder {
+		DiChannelBuilder {
+			backend: backend::ni_daqmx(),
+			device: device.into(),
+			lines: lines.into(),
+			sample_clock: None,
+			sample_mode: SampleMode::Continuous,
+		}
+	}
+
+	// On-demand inputs on the given lines, see `read_lines`
+	pub fn new<S1: AsRef<str>, S2: AsRef<str>>(device: S1, lines: S2) -> Result<Self, DaqError> {
+		Self::with_backend(backend::ni_daqmx(), device, lines)
+	}
+
+	pub fn with_backend<S1: AsRef<str>, S2: AsRef<str>>(
+		backend: Backend,
+		device: S1,
+		lines: S2,
+	) -> Result<Self, DaqError> {
+		Self::builder(device.as_ref(), lines.as_ref()).backend(backend).build()
+	}
+
+	fn init(
+		&mut self,
+		device: &str,
+		lines: &str,
+		sample_clock: Option<(String, usize)>,
+	) -> Result<(), DaqError> {
+		self.task_handle.create_di_chan(&physical_chan_desc(device, lines), "")?;
+
+		// One channel per line, so this is the number of lines
+		self.num_channels = self.task_handle.num_channels()?;
+
+		match sample_clock {
+			Some((clk_src, sample_rate)) => {
+				self.sample_rate = Some(sample_rate);
+
+				let internal_buf_size = 10 * sample_rate as u64;
+				let (sample_mode, samps_per_chan) = self.sample_mode.as_daqmx(internal_buf_size);
+				self.task_handle.configure_sample_clock(
+					&terminal_desc(device, &clk_src),
+					sample_rate as f64,
+					sample_mode,
+					samps_per_chan,
+				)?;
+
+				self.time_axis = Some(TimeAxis::new(self.task_handle.sample_clock_rate()?));
+
+				Ok(())
+			}
+			None => Ok(()),
+		}
+	}
+
+	pub fn num_channels(&self) -> usize {
+		self.num_channels
+	}
+
+	// Coerced sample clock rate and launch time, None without a sample clock
+	pub fn time_axis(&self) -> Option<TimeAxis> {
+		self.time_axis
+	}
+
+	// Current level of every line, only for channels without a sample clock
+	pub fn read_lines(&mut self) -> Result<Vec<bool>, DaqError> {
+		let mut buf = vec![0; self.num_channels];
+		self.task_handle.read_digital_lines(1, SAMPLE_TIMEOUT_SECS, &mut buf)?;
+
+		Ok(buf.into_iter().map(|level| level != 0).collect())
+	}
+
+	// Needed before reading a clocked channel with BlockingRead, streams launch through their
+	// own handle. A relaunched task counts its samples from 1 again.
+	pub fn launch_task(&mut self) -> Result<(), DaqError> {
+		self.task_handle.start()?;
+		if let Some(time_axis) = &mut self.time_axis {
+			time_axis.start = Some(get_steady_time_nanoseconds());
+		}
+		self.samples_read = 0;
+
+		Ok(())
+	}
+}
+
+impl Arm for DiChannel {
+	fn arm(&mut self) -> Result<(), DaqError> {
+		self.launch_task()
+	}
+}
+
+impl TaskControl for DiChannel {
+	fn task_handle(&mut self) -> &mut TaskHandle {
+		&mut self.task_handle
+	}
+}
+
+impl BlockingRead for DiChannel {
+	type Sample = DigitalScan;
+
+	fn read(&mut self, n_samps: usize, timeout: Duration) -> Result<Vec<DigitalScan>, DaqError> {
+		let mut buf = vec![0; n_samps * self.num_channels];
+
+		let n_read =
+			self.task_handle.read_digital_lines(n_samps, timeout.as_secs_f64(), &mut buf)?;
+		buf.truncate(n_read * self.num_channels);
+
+		self.samples_read += n_read as u64;
+
+		Ok(to_scans(&buf, self.num_channels, self.samples_read, self.time_axis))
+	}
+
+	fn sample_mode(&self) -> SampleMode {
+		self.sample_mode
+	}
+
+	fn samples_read(&self) -> u64 {
+		self.samples_read
+	}
+}
+
+// Scans of a stream, only channels with a sample clock have one
+pub struct DiReader {
+	num_channels: usize,
+	time_axis: TimeAxis,
+}
+
+impl StreamRead for DiReader {
+	type Sample = DigitalScan;
+	const READ_CALL: &'static str = "DAQmxReadDigitalLines";
+
+	fn read(
+		&mut self,
+		task_handle: &mut RawTaskHandle,
+		n_samps: u32,
+		first: u64,
+	) -> Result<Vec<DigitalScan>, DaqError> {
+		let mut buf = vec![0; n_samps as usize * self.num_channels];
+
+		let n_read =
+			task_handle.read_digital_lines(n_samps as usize, SAMPLE_TIMEOUT_SECS, &mut buf)?;
+		buf.truncate(n_read * self.num_channels);
+		let last_sample = first + n_read as u64 - 1;
+
+		Ok(to_scans(&buf, self.num_channels, last_sample, Some(self.time_axis)))
+	}
+}
+
+impl StreamChannel for DiChannel {
+	type Reader = DiReader;
+
+	fn stream_setup(&self) -> Result<StreamSetup<DiReader>, DaqError> {
+		let sample_rate =
+			self.sample_rate.ok_or_else(|| no_sample_clock("DAQmxRegisterEveryNSamplesEvent"))?;
+		let time_axis =
+			self.time_axis.ok_or_else(|| no_sample_clock("DAQmxRegisterEveryNSamplesEvent"))?;
+
+		Ok(StreamSetup {
+			reader: DiReader {
+				num_channels: self.num_channels,
+				time_axis,
+			},
+			batch_size: (sample_rate / DAQ_CALLBACK_FREQ).max(1) as u32,
+			sample_mode: self.sample_mode,
+		})
+	}
+}
+
+pub type AsyncDiChannel = AsyncChannel<DiChannel>;
+
+pub struct DoChannelBuilder {
+	backend: Backend,
+	device: String,
+	lines: String,
+	sample_clock: Option<(String, usize)>,
+}
+
+impl DoChannelBuilder {
+	pub fn backend(mut self, backend: Backend) -> Self {
+		self.backend = backend;
+		self
+	}
+
+	// Needed for `write`, without a sample clock every write is output right away.
+	// `clk_src` is a terminal of the device like "PFI13", or empty for the onboard clock.
+	pub fn sample_clock<S: Into<String>>(mut self, clk_src: S, sample_rate: usize) -> Self {
+		self.sample_clock = Some((clk_src.into(), sample_rate));
+		self
+	}
+
+	pub fn build(self) -> Result<DoChannel, DaqError> {
+		let task_handle = TaskHandle::with_backend(self.backend)?;
+
+		let mut do_channel = DoChannel {
+			task_handle,
+			num_channels: 0,
+			clocked: false,
+			launched: false,
+		};
+
+		do_channel.init(&self.device, &self.lines, self.sample_clock)?;
+
+		Ok(do_channel)
+	}
+}
+
+// Digital output lines, e.g. "port0/line4:7", written as one channel per line
+pub struct DoChannel {
+	task_handle: TaskHandle,
+	num_channels: usize,
+	clocked: bool,
+	launched: bool,
+}
+
+impl DoChannel {
+	pub fn builder<S1: Into<String>, S2: Into<String>>(device: S1, lines: S2) -> DoChannelBuilder {
+		DoChannelBuilder {
+			backend: backend::ni_daqmx(),
+			device: device.into(),
+			lines: lines.into(),
+			sample_clock: None,
+		}
+	}
+
+	// On-demand outputs on the given lines, see `write_lines`
+	pub fn new<S1: AsRef<str>, S2: AsRef<str>>(device: S1, lines: S2) -> Result<Self, DaqError> {
+		Self::with_backend(backend::ni_daqmx(), device, lines)
+	}
+
+	pub fn with_backend<S1: AsRef<str>, S2: AsRef<str>>(
+		backend: Backend,
+		device: S1,
+		lines: S2,
+	) -> Result<Self, DaqError> {
+		Self::builder(device.as_ref(), lines.as_ref()).backend(backend).build()
+	}
+
+	fn init(
+		&mut self,
+		device: &str,
+		lines: &str,
+		sample_clock: Option<(String, usize)>,
+	) -> Result<(), DaqError> {
+		self.task_handle.create_do_chan(&physical_chan_desc(device, lines), "")?;
+
+		// One channel per line, so this is the number of lines
+		self.num_channels = self.task_handle.num_channels()?;
+
+		match sample_clock {
+			Some((clk_src, sample_rate)) => {
+				self.clocked = true;
+
+				let internal_buf_size = 10 * sample_rate as u64;
+				self.task_handle.configure_sample_clock(
+					&terminal_desc(device, &clk_src),
+					sample_rate as f64,
+					nidaqmx_sys::DAQmx_Val_ContSamps as i32,
+					internal_buf_size,
+				)
+			}
+			None => Ok(()),
+		}
+	}
+
+	pub fn num_channels(&self) -> usize {
+		self.num_channels
+	}
+
+	// Sets every line to its level right away, only for channels without a sample clock
+	pub fn write_lines(&mut self, levels: &[bool]) -> Result<(), DaqError> {
+		if levels.len() != self.num_channels {
+			return Err(num_chans_mismatch());
+		}
+
+		self.task_handle.write_digital_lines(1, true, SAMPLE_TIMEOUT_SECS, &to_bytes(levels))?;
+
+		Ok(())
+	}
+
+	// Streams interleaved scans, waiting up to `timeout` for room in the output buffer. The
+	// task starts with the first write and fails if it runs out of samples, so keep writing.
+	pub fn write(&mut self, scans: &[bool], timeout: Duration) -> Result<usize, DaqError> {
+		if !self.clocked {
+			return Err(no_sample_clock("DAQmxWriteDigitalLines"));
+		}
+		if self.num_channels == 0 || !scans.len().is_multiple_of(self.num_channels) {
+			return Err(num_chans_mismatch());
+		}
+
+		if !self.launched {
+			self.task_handle.set_regeneration(false)?;
+		}
+
+		let n_scans = scans.len() / self.num_channels;
+		let n_written = self.task_handle.write_digital_lines(
+			n_scans,
+			false,
+			timeout.as_secs_f64(),
+			&to_bytes(scans),
+		)?;
+
+		if !self.launched {
+			self.task_handle.start()?;
+			self.launched = true;
+		}
+
+		Ok(n_written)
+	}
+}
+
+fn num_chans_mismatch() -> DaqError {
+	DaqError::new(
+		nidaqmx_sys::DAQmxErrorWriteNumChansMismatch,
+		"Number of samples doesn't match the number of channels in the task.",
+		"DAQmxWriteDigitalLines",
+	)
+}
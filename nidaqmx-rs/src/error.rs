@@ -0,0 +1,70 @@
+use super::{backend::DaqBackend, SCAN_WARNING};
+
+use std::{error::Error, ffi::CString, fmt};
+
+#[derive(Debug, Clone)]
+pub struct DaqError {
+	pub code: i32,
+	pub message: String,
+	pub call: &'static str,
+}
+
+impl DaqError {
+	pub fn new<S: Into<String>>(code: i32, message: S, call: &'static str) -> Self {
+		DaqError {
+			code,
+			message: message.into(),
+			call,
+		}
+	}
+
+	// Builds an error from the extended error info of the last failed DAQmx call
+	pub fn from_code(backend: &dyn DaqBackend, code: i32, call: &'static str) -> Self {
+		Self::new(code, backend.extended_error_info(), call)
+	}
+}
+
+impl fmt::Display for DaqError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{} failed with error {}: {}",
+			self.call, self.code, self.message
+		)
+	}
+}
+
+impl Error for DaqError {}
+
+// A read that succeeded, but returned fewer samples than were asked for
+pub fn incomplete_read(samps_read: i32, n_samps: u32, call: &'static str) -> DaqError {
+	DaqError::new(
+		SCAN_WARNING,
+		format!("Only {} of the {} samples requested were read.", samps_read, n_samps),
+		call,
+	)
+}
+
+// Names handed to `call` can't hold a NUL byte, it would cut them short
+pub fn c_string(s: &str, call: &'static str) -> Result<CString, DaqError> {
+	CString::new(s).map_err(|_| {
+		DaqError::new(
+			nidaqmx_sys::DAQmxErrorInvalidCharInString,
+			format!("{:?} contains a NUL byte.", s),
+			call,
+		)
+	})
+}
+
+// Returns an error for negative (failure) codes, positive codes are warnings and are ignored
+pub fn chk_err_code(
+	backend: &dyn DaqBackend,
+	err_code: i32,
+	call: &'static str,
+) -> Result<(), DaqError> {
+	if err_code < 0 {
+		Err(DaqError::from_code(backend, err_code, call))
+	} else {
+		Ok(())
+	}
+}
@@ -1,10 +1,34 @@
+mod ai_accel_channel;
 mod ai_channel;
+mod ai_force_channel;
+mod ao_channel;
+mod async_channel;
+mod blocking;
+mod backend;
+mod buffer;
+mod simulated;
+mod task_control;
 mod task_handle;
 mod co_channel;
 mod callback_utils;
+mod ci_channel;
+mod clock;
 mod ci_encoder_channel;
+mod ci_pulse_channel;
+mod device;
+mod digital_channel;
+mod error;
+mod sample_queue;
+mod timing;
+mod trigger;
 
-const EMPTY_CSTRING: *const i8 = b"\0".as_ptr() as *const i8;
+#[cfg(test)]
+mod test;
+
+use std::ffi::CStr;
+
+// This is safe since the byte string is null terminated and has no interior nulls
+const EMPTY_CSTRING: &CStr = unsafe { CStr::from_bytes_with_nul_unchecked(b"\0") };
 const DAQ_CALLBACK_FREQ: usize = 100; // hz
 const SAMPLE_TIMEOUT_SECS: f64 = 1.0;
 const SCAN_WARNING: i32 = i32::max_value();
@@ -20,30 +44,53 @@ const CALLBACK_PERIOD: u64 =		// DAQ Callback period [ns]
 // in the 18-digit LDAP/FILETIME timestamp format.
 const TIMEPOINT: u64 = 132080302920000000;
 
-fn counter_generate_chan_desc(counter_id: u8) -> String {
-	let desc = format!("Dev1/ctr{}", counter_id);
+fn counter_generate_chan_desc(device: &str, counter_id: u8) -> String {
+	let desc = format!("{}/ctr{}", device, counter_id);
 	desc
 }
 
-// Current time minus the TIMEPOINT in nanoseconds
-pub fn get_steady_time_nanoseconds() -> u64 {
-	get_steady_time_pretty_please_windows_i_swear_to_god()
+// Prefixes every channel of a list like "ai0:1, ai3" with the device name
+fn physical_chan_desc(device: &str, channels: &str) -> String {
+	channels
+		.split(',')
+		.map(|chan| format!("{}/{}", device, chan.trim()))
+		.collect::<Vec<_>>()
+		.join(", ")
 }
 
-use winapi::um::sysinfoapi::GetSystemTimePreciseAsFileTime;
-use winapi::shared::minwindef::FILETIME;
-
-// Current time minus the TIMEPOINT in nanoseconds
-fn get_steady_time_pretty_please_windows_i_swear_to_god() -> u64 {
-    let mut file_t = FILETIME::default();
-    unsafe { GetSystemTimePreciseAsFileTime(&mut file_t) };
-    
-    let t = ((file_t.dwHighDateTime as u64) << 32) | file_t.dwLowDateTime as u64;
-
-	let t = t - TIMEPOINT;
+// Fully qualified terminal name, e.g. "/Dev1/PFI13". An empty terminal is left empty so DAQmx
+// falls back to the onboard clock.
+fn terminal_desc(device: &str, terminal: &str) -> String {
+	if terminal.is_empty() {
+		String::new()
+	} else {
+		format!("/{}/{}", device, terminal)
+	}
+}
 
-    t * 100 // windows returns time as 100s of ns, convert to ns
+// Current UTC time minus the TIMEPOINT in nanoseconds
+pub fn get_steady_time_nanoseconds() -> u64 {
+	clock::utc_since_timepoint_ns()
 }
 
+pub use ai_accel_channel::*;
 pub use ai_channel::*;
+pub use ai_force_channel::*;
+pub use ao_channel::*;
+pub use async_channel::{AsyncChannel, StreamChannel};
+pub use blocking::{Batches, BlockingRead};
+pub use ci_channel::{AsyncCiChannel, CiChannel, CiMeasurement, CiSetup, CounterSample};
 pub use ci_encoder_channel::*;
+pub use clock::{monotonic_ns, utc_ns};
+pub use ci_pulse_channel::*;
+pub use co_channel::CoFreqChannel;
+pub use device::*;
+pub use digital_channel::*;
+pub use buffer::*;
+pub use backend::{ni_daqmx, Backend, DaqBackend, NiDaqmx, TaskPtr};
+pub use error::DaqError;
+pub use sample_queue::{OverflowPolicy, QueueStats};
+pub use simulated::*;
+pub use task_control::TaskControl;
+pub use timing::{SampleMode, TimeAxis};
+pub use trigger::*;
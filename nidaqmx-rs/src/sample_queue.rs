@@ -0,0 +1,266 @@
+use super::error::DaqError;
+
+use std::{
+	collections::VecDeque,
+	pin::Pin,
+	sync::{
+		atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+		Arc, Mutex, MutexGuard,
+	},
+	task::{Context, Poll},
+};
+
+use futures::{stream::Stream, task::AtomicWaker};
+
+// What happens to a new sample when a bounded queue is full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+	// Discard the oldest queued sample to make room
+	DropOldest,
+	// Discard the new sample
+	DropNewest,
+	// Stop the acquisition and end the stream with an error
+	Error,
+}
+
+// Counters of a queue between a DAQ callback and its async stream, safe to poll from anywhere
+#[derive(Debug, Default)]
+pub struct QueueStats {
+	dropped: AtomicU64,
+	high_water: AtomicUsize,
+	len: AtomicUsize,
+	buffered: AtomicUsize,
+}
+
+impl QueueStats {
+	// Samples discarded because the queue was full
+	pub fn dropped(&self) -> u64 {
+		self.dropped.load(Ordering::Relaxed)
+	}
+
+	// Most samples ever waiting in the queue at once
+	pub fn high_water(&self) -> usize {
+		self.high_water.load(Ordering::Relaxed)
+	}
+
+	// Samples currently waiting in the queue
+	pub fn len(&self) -> usize {
+		self.len.load(Ordering::Relaxed)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	// Samples per channel still in the DAQmx buffer after the last batch was read
+	pub fn buffered(&self) -> usize {
+		self.buffered.load(Ordering::Relaxed)
+	}
+
+	// How far the stream lags behind the acquisition, queued samples plus the ones still in the
+	// DAQmx buffer. A backlog that keeps growing ends in an overflow.
+	pub fn backlog(&self) -> usize {
+		self.len() + self.buffered()
+	}
+}
+
+struct Shared<T> {
+	queue: Mutex<VecDeque<T>>,
+	capacity: Option<usize>,
+	policy: OverflowPolicy,
+	stats: Arc<QueueStats>,
+	overflowed: AtomicBool,
+	// Why the sender stopped early, yielded once the queue is drained
+	error: Mutex<Option<DaqError>>,
+	sender_alive: AtomicBool,
+	receiver_alive: AtomicBool,
+	recv_waker: AtomicWaker,
+}
+
+// Fed from the DAQ callbacks
+pub struct QueueSender<T>(Arc<Shared<T>>);
+
+// The async end of the queue, ends once the sender is gone and the queue is drained
+pub struct QueueReceiver<T> {
+	shared: Arc<Shared<T>>,
+	failed: bool,
+}
+
+// `capacity` of None never drops anything, like an unbounded channel
+pub fn sample_queue<T>(
+	capacity: Option<usize>,
+	policy: OverflowPolicy,
+) -> (QueueSender<T>, QueueReceiver<T>) {
+	let capacity = capacity.map(|capacity| capacity.max(1));
+
+	queue_with_stats(capacity, policy, Arc::new(QueueStats::default()))
+}
+
+// Shares `stats` with the queue a renewed receiver replaces
+fn queue_with_stats<T>(
+	capacity: Option<usize>,
+	policy: OverflowPolicy,
+	stats: Arc<QueueStats>,
+) -> (QueueSender<T>, QueueReceiver<T>) {
+	let shared = Arc::new(Shared {
+		queue: Mutex::new(VecDeque::new()),
+		capacity,
+		policy,
+		stats,
+		overflowed: AtomicBool::new(false),
+		error: Mutex::new(None),
+		sender_alive: AtomicBool::new(true),
+		receiver_alive: AtomicBool::new(true),
+		recv_waker: AtomicWaker::new(),
+	});
+
+	let receiver = QueueReceiver {
+		shared: Arc::clone(&shared),
+		failed: false,
+	};
+
+	(QueueSender(shared), receiver)
+}
+
+impl<T> Shared<T> {
+	fn queue(&self) -> MutexGuard<'_, VecDeque<T>> {
+		self.queue.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl<T> QueueSender<T> {
+	// Queues a batch of samples, fails once the receiver is gone or the queue overflowed with
+	// the Error policy
+	pub fn send_all<I: IntoIterator<Item = T>>(&self, samples: I) -> Result<(), ()> {
+		let shared = &*self.0;
+
+		if !shared.receiver_alive.load(Ordering::SeqCst) {
+			return Err(());
+		}
+
+		let result = {
+			let mut queue = shared.queue();
+			let mut dropped = 0;
+			let mut result = Ok(());
+
+			for sample in samples {
+				match shared.capacity {
+					Some(capacity) if queue.len() >= capacity => match shared.policy {
+						OverflowPolicy::DropOldest => {
+							queue.pop_front();
+							queue.push_back(sample);
+							dropped += 1;
+						}
+						OverflowPolicy::DropNewest => dropped += 1,
+						OverflowPolicy::Error => {
+							shared.overflowed.store(true, Ordering::SeqCst);
+							dropped += 1;
+							result = Err(());
+						}
+					},
+					_ => queue.push_back(sample),
+				}
+			}
+
+			let stats = &shared.stats;
+			stats.dropped.fetch_add(dropped, Ordering::Relaxed);
+			stats.len.store(queue.len(), Ordering::Relaxed);
+			if queue.len() > stats.high_water.load(Ordering::Relaxed) {
+				stats.high_water.store(queue.len(), Ordering::Relaxed);
+			}
+
+			result
+		};
+
+		shared.recv_waker.wake();
+
+		result
+	}
+
+	// Samples per channel the DAQ callback left in the DAQmx buffer
+	pub fn set_buffered(&self, buffered: usize) {
+		self.0.stats.buffered.store(buffered, Ordering::Relaxed);
+	}
+
+	// Ends the stream with `err` after the samples already queued
+	pub fn fail(self, err: DaqError) {
+		*self.0.error.lock().unwrap_or_else(|e| e.into_inner()) = Some(err);
+	}
+}
+
+impl<T> Drop for QueueSender<T> {
+	fn drop(&mut self) {
+		self.0.sender_alive.store(false, Ordering::SeqCst);
+		self.0.recv_waker.wake();
+	}
+}
+
+impl<T> QueueReceiver<T> {
+	pub fn stats(&self) -> Arc<QueueStats> {
+		Arc::clone(&self.shared.stats)
+	}
+
+	// Starts over with an empty queue for a relaunched task, whatever the last run left queued
+	// is discarded. The old sender fails its next send, the stats keep counting.
+	pub fn renew(&mut self) -> QueueSender<T> {
+		let stats = Arc::clone(&self.shared.stats);
+		stats.len.store(0, Ordering::Relaxed);
+		stats.buffered.store(0, Ordering::Relaxed);
+
+		let (sender, receiver) = queue_with_stats(self.shared.capacity, self.shared.policy, stats);
+		*self = receiver;
+
+		sender
+	}
+}
+
+// Yields a single error and ends once a queue with the Error policy overflowed, or once the
+// queue is drained after the sender failed
+impl<T> Stream for QueueReceiver<T> {
+	type Item = Result<T, DaqError>;
+
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		if self.failed {
+			return Poll::Ready(None);
+		}
+
+		let shared = Arc::clone(&self.shared);
+
+		// Register first so a sample sent right after the check below still wakes us up
+		shared.recv_waker.register(cx.waker());
+
+		if shared.overflowed.load(Ordering::SeqCst) {
+			self.failed = true;
+			return Poll::Ready(Some(Err(overflow_error())));
+		}
+
+		let mut queue = shared.queue();
+
+		match queue.pop_front() {
+			Some(sample) => {
+				shared.stats.len.store(queue.len(), Ordering::Relaxed);
+				Poll::Ready(Some(Ok(sample)))
+			}
+			None if !shared.sender_alive.load(Ordering::SeqCst) => {
+				let error = shared.error.lock().unwrap_or_else(|e| e.into_inner()).take();
+				self.failed = true;
+				Poll::Ready(error.map(Err))
+			}
+			None => Poll::Pending,
+		}
+	}
+}
+
+impl<T> Drop for QueueReceiver<T> {
+	fn drop(&mut self) {
+		self.shared.receiver_alive.store(false, Ordering::SeqCst);
+	}
+}
+
+fn overflow_error() -> DaqError {
+	DaqError::new(
+		nidaqmx_sys::DAQmxErrorSamplesNoLongerAvailable,
+		"Samples were dropped because the stream wasn't polled fast enough to keep up.",
+		"QueueSender::send_all",
+	)
+}
@@ -0,0 +1,2524 @@
+use super::{
+	backend::{Backend, DaqBackend, TaskPtr},
+	callback_utils::{RawDoneCallback, RawReadCallback},
+	device::{CalDate, Calibration, CalibrationInfo},
+	trigger::{AnalogCondition, Slope, WindowCondition},
+};
+
+use std::{
+	cell::RefCell,
+	collections::{HashMap, VecDeque},
+	f64::consts::PI,
+	ffi::CStr,
+	os::raw::c_void,
+	sync::{
+		atomic::{AtomicBool, AtomicUsize, Ordering},
+		Arc, Condvar, Mutex, MutexGuard,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+use nidaqmx_sys::{
+	DAQmxErrorAttrNotSupported, DAQmxErrorCanNotPerformOpWhenNoChansInTask,
+	DAQmxErrorCannotWriteWhenAutoStartFalseAndTaskNotRunning, DAQmxErrorChanNotInTask,
+	DAQmxErrorDeviceRemoved, DAQmxErrorForwardPolynomialCoefNotSpecd, DAQmxErrorInvalidAnalogTrigSrc,
+	DAQmxErrorInvalidAODataWrite, DAQmxErrorInvalidActionInControlTask, DAQmxErrorInvalidDeviceID,
+	DAQmxErrorInvalidRoutingSourceTerminalName, DAQmxErrorInvalidTask,
+	DAQmxErrorPhysicalChanDoesNotExist, DAQmxErrorReadBufferTooSmall,
+	DAQmxErrorReadChanTypeMismatch, DAQmxErrorReadNoInputChansInTask,
+	DAQmxErrorRefTrigWhenContinuous, DAQmxErrorSamplesNoLongerAvailable, DAQmxErrorSamplesNotYetAvailable, DAQmxErrorSelfTestFailed,
+	DAQmxErrorTableScaleNumPreScaledAndScaledValsNotEqual,
+	DAQmxErrorTableScalePreScaledValsNotSpecd, DAQmxErrorTooManyPretrigPlusMinPostTrigSamps,
+	DAQmxErrorWriteChanTypeMismatch,
+	DAQmxErrorWriteNoOutputChansInTask, DAQmxErrorWriteNumChansMismatch,
+	DAQmxErrorZeroSlopeLinearScale, DAQmx_Val_AC, DAQmx_Val_MetersPerSecondSquared,
+};
+
+// Longest the sample generator sleeps at once, so stopping a task stays responsive
+const MAX_SLEEP: Duration = Duration::from_millis(10);
+
+// Onboard timebase the sample clocks are divided down from [Hz], as on X series devices
+const SIM_TIMEBASE: f64 = 100e6;
+
+const DEVICE_REMOVED: &str = "The device has been removed from the system.";
+const ATTR_NOT_SUPPORTED: &str =
+	"Specified property is not supported by the device or is not applicable to the task.";
+
+// Signal produced by a simulated input, `t` is the time since the task started [s]
+#[derive(Debug, Clone)]
+pub enum Waveform {
+	Constant(f64),
+	Sine {
+		amplitude: f64,
+		frequency: f64,
+		offset: f64,
+	},
+	// Uniform noise in [offset - amplitude, offset + amplitude]
+	Noise {
+		amplitude: f64,
+		offset: f64,
+	},
+	// Sawtooth going from start to end once every period [s]
+	Ramp {
+		start: f64,
+		end: f64,
+		period: f64,
+	},
+	Sum(Vec<Waveform>),
+}
+
+impl Waveform {
+	// Rate of change at `t` [1/s], noise doesn't have one
+	fn derivative(&self, t: f64) -> f64 {
+		match self {
+			Waveform::Constant(_) | Waveform::Noise { .. } => 0.0,
+			Waveform::Sine {
+				amplitude,
+				frequency,
+				..
+			} => amplitude * 2.0 * PI * frequency * (2.0 * PI * frequency * t).cos(),
+			Waveform::Ramp {
+				start, end, period, ..
+			} => (end - start) / period,
+			Waveform::Sum(waveforms) => waveforms.iter().map(|waveform| waveform.derivative(t)).sum(),
+		}
+	}
+
+	fn mean(&self) -> f64 {
+		match self {
+			Waveform::Constant(val) => *val,
+			Waveform::Sine { offset, .. } | Waveform::Noise { offset, .. } => *offset,
+			Waveform::Ramp { start, end, .. } => (start + end) / 2.0,
+			Waveform::Sum(waveforms) => waveforms.iter().map(Waveform::mean).sum(),
+		}
+	}
+
+	fn sample(&self, t: f64, rng: &mut XorShift) -> f64 {
+		match self {
+			Waveform::Constant(val) => *val,
+			Waveform::Sine {
+				amplitude,
+				frequency,
+				offset,
+			} => offset + amplitude * (2.0 * PI * frequency * t).sin(),
+			Waveform::Noise { amplitude, offset } => {
+				offset + amplitude * (2.0 * rng.next_f64() - 1.0)
+			}
+			Waveform::Ramp { start, end, period } => {
+				let phase = (t / period).fract();
+				start + (end - start) * phase
+			}
+			Waveform::Sum(waveforms) => waveforms.iter().map(|w| w.sample(t, rng)).sum(),
+		}
+	}
+}
+
+// What the simulated device reports about itself and feeds into its inputs
+#[derive(Debug, Clone)]
+pub struct SimulatedDevice {
+	pub name: String,
+	pub product_type: String,
+	pub serial_number: u32,
+	// Number of ai, ao, ctr, PFI and port0 lines the device has
+	pub ai_channels: u32,
+	pub ao_channels: u32,
+	pub counters: u32,
+	pub pfi_lines: u32,
+	pub dio_lines: u32,
+	// Analog input channel N reads analog[N % analog.len()] [V], or [mV/V] for force
+	// bridges and [g] for accelerometers
+	pub analog: Vec<Waveform>,
+	// Shaft position seen by every angular encoder and velocity channel [revolutions]
+	pub encoder: Waveform,
+	// Slider position seen by every linear encoder channel [m]
+	pub linear: Waveform,
+	// Pulses seen so far by every edge counting, frequency and period channel, its rate of
+	// change is the pulse frequency [pulses]
+	pub pulses: Waveform,
+	// Digital line N is high while digital[N % digital.len()] is above 0.5, unless a digital
+	// output drives it
+	pub digital: Vec<Waveform>,
+	// Unplugs the device this long after a clocked input task starts [s]. The task then stops
+	// with an error and its reads fail, like a cable pulled mid-trial.
+	pub removed_after: Option<f64>,
+	// What the calibration and temperature queries report, a missing self-calibration or
+	// temperature isn't supported by the device
+	pub calibration: CalibrationInfo,
+	// Makes the self-test fail, like a device with a damaged converter
+	pub fails_self_test: bool,
+}
+
+impl Default for SimulatedDevice {
+	fn default() -> Self {
+		let noisy_sine = |amplitude, frequency| {
+			Waveform::Sum(vec![
+				Waveform::Sine {
+					amplitude,
+					frequency,
+					offset: 0.0,
+				},
+				Waveform::Noise {
+					amplitude: 0.01,
+					offset: 0.0,
+				},
+			])
+		};
+
+		SimulatedDevice {
+			name: "Dev1".to_string(),
+			product_type: "PCIe-6321".to_string(),
+			serial_number: 0x01A2_B3C4,
+			ai_channels: 16,
+			ao_channels: 2,
+			counters: 4,
+			pfi_lines: 16,
+			dio_lines: 8,
+			analog: vec![noisy_sine(1.0, 0.5), noisy_sine(0.5, 1.0)],
+			encoder: Waveform::Sine {
+				amplitude: 0.25,
+				frequency: 0.5,
+				offset: 0.5,
+			},
+			linear: Waveform::Sine {
+				amplitude: 0.05,
+				frequency: 0.25,
+				offset: 0.1,
+			},
+			pulses: Waveform::Ramp {
+				start: 0.0,
+				end: 50_000.0,
+				period: 1000.0,
+			},
+			digital: vec![Waveform::Ramp {
+				start: 0.0,
+				end: 1.0,
+				period: 1.0,
+			}],
+			removed_after: None,
+			calibration: CalibrationInfo {
+				external: Calibration {
+					date: CalDate {
+						year: 2019,
+						month: 3,
+						day: 14,
+						hour: 9,
+						minute: 30,
+					},
+					temperature: 23.4,
+				},
+				self_cal: Some(Calibration {
+					date: CalDate {
+						year: 2019,
+						month: 11,
+						day: 2,
+						hour: 16,
+						minute: 5,
+					},
+					temperature: 31.8,
+				}),
+				temperature: Some(32.5),
+			},
+			fails_self_test: false,
+		}
+	}
+}
+
+// Pure rust stand-in for the NI-DAQmx driver. Input tasks generate samples from the
+// `SimulatedDevice` waveforms at their sample clock rate on a background thread, and fire
+// the registered every N samples and done callbacks just like the driver does.
+pub struct SimulatedBackend {
+	device: SimulatedDevice,
+	tasks: Mutex<HashMap<usize, Arc<SimTask>>>,
+	triggers: Arc<SimTriggers>,
+	lines: Arc<SimLines>,
+	next_task_id: AtomicUsize,
+}
+
+thread_local! {
+	// Like DAQmx, the extended error info is that of the last call failing on the same thread
+	static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn set_last_error<S: Into<String>>(msg: S) {
+	LAST_ERROR.with(|last_error| *last_error.borrow_mut() = msg.into());
+}
+
+pub fn simulated(device: SimulatedDevice) -> Backend {
+	Arc::new(SimulatedBackend::new(device))
+}
+
+impl SimulatedBackend {
+	pub fn new(device: SimulatedDevice) -> Self {
+		SimulatedBackend {
+			device,
+			tasks: Mutex::new(HashMap::new()),
+			triggers: Arc::new(SimTriggers::default()),
+			lines: Arc::new(SimLines::default()),
+			next_task_id: AtomicUsize::new(1),
+		}
+	}
+
+	fn fail<S: Into<String>>(&self, err_code: i32, msg: S) -> i32 {
+		set_last_error(msg);
+		err_code
+	}
+
+	fn task(&self, task: TaskPtr) -> Result<Arc<SimTask>, i32> {
+		lock(&self.tasks)
+			.get(&(task as usize))
+			.cloned()
+			.ok_or_else(|| {
+				self.fail(
+					DAQmxErrorInvalidTask,
+					"Task specified is invalid or does not exist.",
+				)
+			})
+	}
+
+	// For the attribute setters and getters, which only touch the task's state
+	fn with_state(&self, task: TaskPtr, f: impl FnOnce(&mut SimTaskState)) -> i32 {
+		match self.task(task) {
+			Ok(sim_task) => {
+				f(&mut lock(&sim_task.state));
+				0
+			}
+			Err(err_code) => err_code,
+		}
+	}
+
+	// Channels of the given kind ("ai", "ao", "ctr", "port0/line") on this device, or an error if
+	// the name is invalid
+	fn channel_indices(&self, physical_channel: &CStr, kind: &str) -> Result<Vec<u32>, i32> {
+		let physical_channel = physical_channel.to_string_lossy();
+		let device = &self.device;
+
+		let count = match kind {
+			"ai" => device.ai_channels,
+			"ao" => device.ao_channels,
+			"port0/line" => device.dio_lines,
+			_ => device.counters,
+		};
+
+		parse_physical_channels(&device.name, kind, &physical_channel)
+			.filter(|indices| indices.iter().all(|&ind| ind < count))
+			.ok_or_else(|| {
+				self.fail(
+					DAQmxErrorPhysicalChanDoesNotExist,
+					format!("Physical channel '{}' does not exist.", physical_channel),
+				)
+			})
+	}
+
+	fn is_pfi_terminal(&self, terminal: &str) -> bool {
+		let prefix = format!("/{}/PFI", self.device.name);
+
+		terminal.starts_with(&prefix)
+			&& terminal[prefix.len()..]
+				.parse::<u32>()
+				.is_ok_and(|line| line < self.device.pfi_lines)
+	}
+
+	fn is_counter_output_terminal(&self, terminal: &str) -> bool {
+		let prefix = format!("/{}/Ctr", self.device.name);
+
+		terminal.starts_with(&prefix)
+			&& terminal.ends_with("InternalOutput")
+			&& terminal[prefix.len()..terminal.len() - "InternalOutput".len()]
+				.parse::<u32>()
+				.is_ok_and(|counter| counter < self.device.counters)
+	}
+
+	// Terminal the pulse train of a counter output task shows up on
+	fn counter_output(&self, sim_task: &SimTask) -> Option<String> {
+		lock(&sim_task.state)
+			.channels
+			.iter()
+			.filter_map(|chan| match chan {
+				SimChannel::PulseOutput { counter, .. } => {
+					Some(format!("/{}/Ctr{}InternalOutput", self.device.name, counter))
+				}
+				_ => None,
+			})
+			.next()
+	}
+
+	fn query_device<F>(&self, device: &CStr, query: F) -> i32
+	where
+		F: FnOnce(&SimulatedDevice) -> i32,
+	{
+		if device.to_string_lossy() == self.device.name {
+			query(&self.device)
+		} else {
+			self.fail(
+				DAQmxErrorInvalidDeviceID,
+				format!("Device identifier '{}' is invalid.", device.to_string_lossy()),
+			)
+		}
+	}
+
+	// Analog triggers watch a channel of their own task
+	fn analog_trigger(
+		&self,
+		task: TaskPtr,
+		source: &CStr,
+		condition: AnalogCondition,
+	) -> Result<(Arc<SimTask>, SimAnalogTrigger), i32> {
+		let sim_task = self.task(task)?;
+		let source = source.to_string_lossy();
+
+		let channel = lock(&sim_task.state).channels.iter().position(|chan| match chan {
+			SimChannel::Analog { name, .. } => *name == source,
+			_ => false,
+		});
+
+		match channel {
+			Some(channel) => Ok((
+				sim_task,
+				SimAnalogTrigger {
+					channel,
+					condition,
+					last: None,
+				},
+			)),
+			None => Err(self.fail(
+				DAQmxErrorInvalidAnalogTrigSrc,
+				format!("Analog trigger source '{}' is not a channel in the task.", source),
+			)),
+		}
+	}
+
+	fn check_reference_trigger(&self, sim_task: &SimTask) -> Result<(), i32> {
+		let state = lock(&sim_task.state);
+
+		match (&state.ref_trigger, &state.timing) {
+			(None, _) => Ok(()),
+			(Some(_), Some(SimTiming { finite: false, .. })) => Err(self.fail(
+				DAQmxErrorRefTrigWhenContinuous,
+				"Reference triggers are only valid for finite acquisitions.",
+			)),
+			(Some((_, pretrig)), Some(timing)) if timing.samps_per_chan < pretrig + 2 => {
+				Err(self.fail(
+					DAQmxErrorTooManyPretrigPlusMinPostTrigSamps,
+					"Pretrigger samples plus the minimum posttrigger samples exceed the samples \
+					 per channel.",
+				))
+			}
+			_ => Ok(()),
+		}
+	}
+
+	fn stop(&self, sim_task: &SimTask) {
+		sim_task.stop();
+
+		if let Some(terminal) = self.counter_output(sim_task) {
+			self.triggers.reset(&terminal);
+		}
+	}
+
+	fn add_channels(&self, task: TaskPtr, channels: Vec<SimChannel>) -> i32 {
+		match self.task(task) {
+			Ok(task) => {
+				lock(&task.state).channels.extend(channels);
+				0
+			}
+			Err(err_code) => err_code,
+		}
+	}
+
+	// Analog inputs see the device's analog signals, through `scale` for bridges
+	fn create_ai_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		scale: SimScale,
+	) -> i32 {
+		let indices = match self.channel_indices(physical_channel, "ai") {
+			Ok(indices) => indices,
+			Err(err_code) => return err_code,
+		};
+
+		let name_to_assign = name_to_assign.to_string_lossy();
+		let num_chans = indices.len();
+		let analog = &self.device.analog;
+		let channels = indices
+			.into_iter()
+			.enumerate()
+			.map(|(pos, ind)| SimChannel::Analog {
+				name: assigned_name(&name_to_assign, pos, num_chans)
+					.unwrap_or_else(|| format!("{}/ai{}", self.device.name, ind)),
+				waveform: match analog.len() {
+					0 => Waveform::Constant(0.0),
+					len => analog[ind as usize % len].clone(),
+				},
+				scale: scale.clone(),
+				ac_coupled: false,
+				min: min_val,
+				max: max_val,
+			})
+			.collect();
+
+		self.add_channels(task, channels)
+	}
+
+	fn read_scans(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		kind: SimKind,
+		mut write: impl FnMut(&[f64], usize),
+	) -> Result<i32, i32> {
+		let task = self.task(task)?;
+		let mut state = lock(&task.state);
+
+		let n_chans = state.channels.len();
+		if n_chans == 0 {
+			return Err(self.fail(
+				DAQmxErrorReadNoInputChansInTask,
+				"Task contains no input channels.",
+			));
+		}
+
+		let type_matches = state
+			.channels
+			.iter()
+			.all(|chan| chan.is_input() && chan.kind() == kind);
+		if !type_matches {
+			return Err(self.fail(
+				DAQmxErrorReadChanTypeMismatch,
+				"Read does not match the channel type.",
+			));
+		}
+
+		if state.removed {
+			return Err(self.fail(DAQmxErrorDeviceRemoved, DEVICE_REMOVED));
+		}
+
+		if state.overwritten {
+			state.overwritten = false;
+			return Err(self.fail(
+				DAQmxErrorSamplesNoLongerAvailable,
+				"Samples are no longer available, they were overwritten before being read.",
+			));
+		}
+
+		// Reads relative to the newest sample skip everything older than the offset
+		if state.read_relative_to == nidaqmx_sys::DAQmx_Val_MostRecentSamp as i32 {
+			let keep = (state.read_offset.min(0).unsigned_abs() as usize) * n_chans;
+			let excess = state.available().saturating_sub(keep);
+			state.buffer.drain(..excess);
+		}
+
+		let n_samps = if n_samps < 0 {
+			state.available() / n_chans
+		} else {
+			n_samps as usize
+		};
+
+		// Tasks without a sample clock read the inputs on demand
+		if state.timing.is_none() {
+			let t = task.created.elapsed().as_secs_f64();
+			let state = &mut *state;
+
+			let mut scans = Vec::with_capacity(n_samps * n_chans);
+			for _ in 0..n_samps {
+				for chan in &state.channels {
+					scans.push(chan.sample(t, &mut state.rng));
+				}
+			}
+			write(&scans, n_chans);
+
+			return Ok(n_samps as i32);
+		}
+
+		let deadline = Instant::now() + Duration::from_secs_f64(timeout.max(0.0));
+		while state.available() < n_samps * n_chans {
+			let now = Instant::now();
+			if now >= deadline {
+				return Err(self.fail(
+					DAQmxErrorSamplesNotYetAvailable,
+					"Some or all of the samples requested have not yet been acquired.",
+				));
+			}
+
+			state = task
+				.data_ready
+				.wait_timeout(state, deadline - now)
+				.unwrap_or_else(|e| e.into_inner())
+				.0;
+		}
+
+		let scans: Vec<f64> = state.buffer.drain(..n_samps * n_chans).collect();
+		write(&scans, n_chans);
+
+		Ok(n_samps as i32)
+	}
+
+	// Analog outputs aren't wired to anything, their writes are only checked against the channel
+	// ranges. Digital outputs drive their lines to the last scan written right away.
+	fn write_scans(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		auto_start: bool,
+		kind: SimKind,
+		buf: &[f64],
+		samps_written: &mut i32,
+	) -> i32 {
+		*samps_written = 0;
+
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		{
+			let state = lock(&sim_task.state);
+			let n_chans = state.channels.len();
+
+			if n_chans == 0 {
+				return self.fail(
+					DAQmxErrorWriteNoOutputChansInTask,
+					"Task contains no output channels.",
+				);
+			}
+
+			let type_matches = state
+				.channels
+				.iter()
+				.all(|chan| !chan.is_input() && chan.kind() == kind);
+			if !type_matches {
+				return self.fail(
+					DAQmxErrorWriteChanTypeMismatch,
+					"Write does not match the channel type.",
+				);
+			}
+			if n_samps < 0 || buf.len() < n_samps as usize * n_chans {
+				return self.fail(
+					DAQmxErrorWriteNumChansMismatch,
+					"Number of samples doesn't match the number of channels in the task.",
+				);
+			}
+
+			let scans = &buf[..n_samps as usize * n_chans];
+			let in_range = scans.chunks(n_chans).all(|scan| {
+				scan.iter().zip(&state.channels).all(|(val, chan)| chan.in_range(*val))
+			});
+			if !in_range {
+				return self.fail(
+					DAQmxErrorInvalidAODataWrite,
+					"Value written is outside the range of the channel.",
+				);
+			}
+
+			if state.timing.is_none() && !auto_start && !sim_task.running.load(Ordering::SeqCst) {
+				return self.fail(
+					DAQmxErrorCannotWriteWhenAutoStartFalseAndTaskNotRunning,
+					"Task is not running and auto start is off.",
+				);
+			}
+
+			if let Some(scan) = scans.chunks(n_chans).last() {
+				for (val, chan) in scan.iter().zip(&state.channels) {
+					if let SimChannel::DigitalOutput { line } = chan {
+						self.lines.drive(*line, *val != 0.0);
+					}
+				}
+			}
+		}
+
+		*samps_written = n_samps;
+
+		if auto_start {
+			unsafe { self.start_task(task) }
+		} else {
+			0
+		}
+	}
+}
+
+impl DaqBackend for SimulatedBackend {
+	fn create_task(&self, _task_name: &CStr, task: &mut TaskPtr) -> i32 {
+		let id = self.next_task_id.fetch_add(1, Ordering::Relaxed);
+		lock(&self.tasks).insert(id, Arc::new(SimTask::new()));
+
+		*task = id as TaskPtr;
+		0
+	}
+
+	unsafe fn start_task(&self, task: TaskPtr) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		if sim_task.running.load(Ordering::SeqCst) {
+			return 0;
+		}
+
+		// DAQmx wants at least two posttrigger samples
+		if let Err(err_code) = self.check_reference_trigger(&sim_task) {
+			return err_code;
+		}
+
+		if sim_task.running.swap(true, Ordering::SeqCst) {
+			return 0;
+		}
+
+		if let Some(terminal) = self.counter_output(&sim_task) {
+			self.triggers.fire(terminal);
+		}
+
+		let generates_samples = {
+			let mut state = lock(&sim_task.state);
+			state.buffer.clear();
+			state.samps_generated = 0;
+			state.acquired = 0;
+			state.overwritten = false;
+			state.removed = false;
+			state.started_at = match state.analog_start {
+				Some(_) => None,
+				None => Some(0),
+			};
+			state.triggered_at = None;
+			if let Some(trigger) = &mut state.analog_start {
+				trigger.last = None;
+			}
+			if let Some((trigger, _)) = &mut state.ref_trigger {
+				trigger.last = None;
+			}
+
+			state.timing.is_some() && state.channels.iter().any(SimChannel::is_input)
+		};
+
+		if generates_samples {
+			let worker_task = Arc::clone(&sim_task);
+			let triggers = Arc::clone(&self.triggers);
+			let removal = self.device.removed_after;
+			let task_id = task as usize;
+
+			let worker = thread::Builder::new()
+				.name("Simulated DAQ Task".to_string())
+				.spawn(move || worker_task.run(task_id, &triggers, removal))
+				.expect("Failed to spawn simulated DAQ thread");
+
+			*lock(&sim_task.worker) = Some(worker);
+		}
+
+		0
+	}
+
+	unsafe fn stop_task(&self, task: TaskPtr) -> i32 {
+		match self.task(task) {
+			Ok(sim_task) => {
+				self.stop(&sim_task);
+				0
+			}
+			Err(err_code) => err_code,
+		}
+	}
+
+	unsafe fn clear_task(&self, task: TaskPtr) -> i32 {
+		let sim_task = lock(&self.tasks).remove(&(task as usize));
+
+		match sim_task {
+			Some(sim_task) => {
+				self.stop(&sim_task);
+				0
+			}
+			None => self.fail(
+				DAQmxErrorInvalidTask,
+				"Task specified is invalid or does not exist.",
+			),
+		}
+	}
+
+	// Nothing to reserve or program, verifying and committing only check there are channels
+	unsafe fn task_control(&self, task: TaskPtr, action: i32) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		match action as u32 {
+			nidaqmx_sys::DAQmx_Val_Task_Start => self.start_task(task),
+			nidaqmx_sys::DAQmx_Val_Task_Stop | nidaqmx_sys::DAQmx_Val_Task_Abort => {
+				self.stop(&sim_task);
+				0
+			}
+			nidaqmx_sys::DAQmx_Val_Task_Verify | nidaqmx_sys::DAQmx_Val_Task_Commit => {
+				if lock(&sim_task.state).channels.is_empty() {
+					self.fail(
+						DAQmxErrorCanNotPerformOpWhenNoChansInTask,
+						"Cannot perform the operation when there are no channels in the task.",
+					)
+				} else {
+					0
+				}
+			}
+			nidaqmx_sys::DAQmx_Val_Task_Reserve | nidaqmx_sys::DAQmx_Val_Task_Unreserve => 0,
+			_ => self.fail(
+				DAQmxErrorInvalidActionInControlTask,
+				"Action specified in the control task is invalid.",
+			),
+		}
+	}
+
+	// Finite tasks are done once they generated all their samples, removed devices stop tasks
+	// early. Tasks that aren't running count as done.
+	unsafe fn is_task_done(&self, task: TaskPtr, is_done: &mut bool) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		let state = lock(&sim_task.state);
+		let finished = state.end_sample().is_some_and(|end| state.samps_generated >= end);
+
+		*is_done = finished || state.removed || !sim_task.running.load(Ordering::SeqCst);
+		0
+	}
+
+	unsafe fn get_task_num_chans(&self, task: TaskPtr, num_chans: &mut u32) -> i32 {
+		match self.task(task) {
+			Ok(sim_task) => {
+				*num_chans = lock(&sim_task.state).channels.len() as u32;
+				0
+			}
+			Err(err_code) => err_code,
+		}
+	}
+
+	unsafe fn create_ai_voltage_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		_terminal_config: i32,
+		min_val: f64,
+		max_val: f64,
+		_units: i32,
+	) -> i32 {
+		self.create_ai_chan(task, physical_channel, name_to_assign, min_val, max_val, SimScale::Volts)
+	}
+
+	unsafe fn create_ai_force_bridge_two_point_lin_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		_units: i32,
+		_bridge_config: i32,
+		_excit_source: i32,
+		_excit_val: f64,
+		_nominal_bridge_resistance: f64,
+		first: (f64, f64),
+		second: (f64, f64),
+		_electrical_units: i32,
+		_physical_units: i32,
+	) -> i32 {
+		if first.0 == second.0 || first.1 == second.1 {
+			return self.fail(
+				DAQmxErrorZeroSlopeLinearScale,
+				"Two point linear scale has a zero or infinite slope.",
+			);
+		}
+
+		let slope = (second.1 - first.1) / (second.0 - first.0);
+		let scale = SimScale::Polynomial(vec![first.1 - slope * first.0, slope]);
+
+		self.create_ai_chan(task, physical_channel, name_to_assign, min_val, max_val, scale)
+	}
+
+	unsafe fn create_ai_force_bridge_polynomial_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		_units: i32,
+		_bridge_config: i32,
+		_excit_source: i32,
+		_excit_val: f64,
+		_nominal_bridge_resistance: f64,
+		forward_coeffs: &[f64],
+		_reverse_coeffs: &[f64],
+		_electrical_units: i32,
+		_physical_units: i32,
+	) -> i32 {
+		if forward_coeffs.is_empty() {
+			return self.fail(
+				DAQmxErrorForwardPolynomialCoefNotSpecd,
+				"Forward polynomial coefficients were not specified.",
+			);
+		}
+
+		let scale = SimScale::Polynomial(forward_coeffs.to_vec());
+
+		self.create_ai_chan(task, physical_channel, name_to_assign, min_val, max_val, scale)
+	}
+
+	unsafe fn create_ai_force_bridge_table_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		_units: i32,
+		_bridge_config: i32,
+		_excit_source: i32,
+		_excit_val: f64,
+		_nominal_bridge_resistance: f64,
+		electrical_vals: &[f64],
+		physical_vals: &[f64],
+		_electrical_units: i32,
+		_physical_units: i32,
+	) -> i32 {
+		if electrical_vals.is_empty() {
+			return self.fail(
+				DAQmxErrorTableScalePreScaledValsNotSpecd,
+				"Table scale values were not specified.",
+			);
+		}
+		if electrical_vals.len() != physical_vals.len() {
+			return self.fail(
+				DAQmxErrorTableScaleNumPreScaledAndScaledValsNotEqual,
+				"Table scale has a different number of electrical and physical values.",
+			);
+		}
+
+		let mut table: Vec<_> =
+			electrical_vals.iter().cloned().zip(physical_vals.iter().cloned()).collect();
+		table.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+		let scale = SimScale::Table(table);
+
+		self.create_ai_chan(task, physical_channel, name_to_assign, min_val, max_val, scale)
+	}
+
+	// The analog signals are in g
+	unsafe fn create_ai_accel_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		name_to_assign: &CStr,
+		_terminal_config: i32,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		_sensitivity: f64,
+		_sensitivity_units: i32,
+		_current_excit_source: i32,
+		_current_excit_val: f64,
+	) -> i32 {
+		const STANDARD_GRAVITY: f64 = 9.80665;	// [m/s^2]
+
+		let scale = if units == DAQmx_Val_MetersPerSecondSquared as i32 {
+			SimScale::Polynomial(vec![0.0, STANDARD_GRAVITY])
+		} else {
+			SimScale::Volts
+		};
+
+		self.create_ai_chan(task, physical_channel, name_to_assign, min_val, max_val, scale)
+	}
+
+	unsafe fn create_co_pulse_chan_freq(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		_name_to_assign: &CStr,
+		_units: i32,
+		_idle_state: i32,
+		_initial_delay: f64,
+		freq: f64,
+		_duty_cycle: f64,
+	) -> i32 {
+		let counter = match self.channel_indices(counter, "ctr") {
+			Ok(indices) => indices[0],
+			Err(err_code) => return err_code,
+		};
+
+		// The simulated clocks don't need a real pulse train, only the frequency the counter
+		// would divide its timebase down to
+		let freq = SIM_TIMEBASE / (SIM_TIMEBASE / freq).round().max(1.0);
+
+		self.add_channels(task, vec![SimChannel::PulseOutput { counter, freq }])
+	}
+
+	unsafe fn create_ci_ang_encoder_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		_name_to_assign: &CStr,
+		decoding_type: i32,
+		z_idx_enable: bool,
+		z_idx_val: f64,
+		_z_idx_phase: i32,
+		_units: i32,
+		pulses_per_rev: u32,
+		_initial_angle: f64,
+	) -> i32 {
+		if let Err(err_code) = self.channel_indices(counter, "ctr") {
+			return err_code;
+		}
+
+		let channel = SimChannel::Encoder {
+			motion: self.device.encoder.clone(),
+			ticks_per_rev: pulses_per_rev as f64 * counts_per_pulse(decoding_type),
+			z_idx: if z_idx_enable { Some(z_idx_val) } else { None },
+		};
+
+		self.add_channels(task, vec![channel])
+	}
+
+	unsafe fn create_ci_lin_encoder_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		_name_to_assign: &CStr,
+		decoding_type: i32,
+		_z_idx_enable: bool,
+		_z_idx_val: f64,
+		_z_idx_phase: i32,
+		units: i32,
+		dist_per_pulse: f64,
+		_initial_pos: f64,
+	) -> i32 {
+		if let Err(err_code) = self.channel_indices(counter, "ctr") {
+			return err_code;
+		}
+
+		const METERS_PER_INCH: f64 = 0.0254;
+
+		// Ticks are counted in meters of travel per pulse
+		let meters_per_unit = match units as u32 {
+			nidaqmx_sys::DAQmx_Val_Inches => Some(METERS_PER_INCH),
+			nidaqmx_sys::DAQmx_Val_Ticks => None,
+			_ => Some(1.0),
+		};
+
+		let channel = SimChannel::LinEncoder {
+			motion: self.device.linear.clone(),
+			dist_per_tick: dist_per_pulse * meters_per_unit.unwrap_or(1.0)
+				/ counts_per_pulse(decoding_type),
+			meters_per_unit,
+		};
+
+		self.add_channels(task, vec![channel])
+	}
+
+	unsafe fn create_ci_ang_velocity_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		_name_to_assign: &CStr,
+		_min_val: f64,
+		_max_val: f64,
+		_decoding_type: i32,
+		units: i32,
+		_pulses_per_rev: u32,
+	) -> i32 {
+		if let Err(err_code) = self.channel_indices(counter, "ctr") {
+			return err_code;
+		}
+
+		let units_per_rev_per_sec = match units as u32 {
+			nidaqmx_sys::DAQmx_Val_RadiansPerSecond => 2.0 * PI,
+			nidaqmx_sys::DAQmx_Val_DegreesPerSecond => 360.0,
+			_ => 60.0,
+		};
+
+		let channel = SimChannel::AngVelocity {
+			motion: self.device.encoder.clone(),
+			units_per_rev_per_sec,
+		};
+
+		self.add_channels(task, vec![channel])
+	}
+
+	unsafe fn create_ci_count_edges_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		_name_to_assign: &CStr,
+		_edge: i32,
+		initial_count: u32,
+		count_direction: i32,
+	) -> i32 {
+		if let Err(err_code) = self.channel_indices(counter, "ctr") {
+			return err_code;
+		}
+
+		let channel = SimChannel::CountEdges {
+			pulses: self.device.pulses.clone(),
+			initial_count: initial_count as f64,
+			down: count_direction as u32 == nidaqmx_sys::DAQmx_Val_CountDown,
+		};
+
+		self.add_channels(task, vec![channel])
+	}
+
+	unsafe fn create_ci_freq_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		_name_to_assign: &CStr,
+		_min_val: f64,
+		_max_val: f64,
+		_units: i32,
+		_edge: i32,
+		_meas_method: i32,
+		_meas_time: f64,
+		_divisor: u32,
+	) -> i32 {
+		if let Err(err_code) = self.channel_indices(counter, "ctr") {
+			return err_code;
+		}
+
+		let channel = SimChannel::PulseTiming {
+			pulses: self.device.pulses.clone(),
+			period: false,
+		};
+
+		self.add_channels(task, vec![channel])
+	}
+
+	unsafe fn create_ci_period_chan(
+		&self,
+		task: TaskPtr,
+		counter: &CStr,
+		_name_to_assign: &CStr,
+		_min_val: f64,
+		_max_val: f64,
+		_units: i32,
+		_edge: i32,
+		_meas_method: i32,
+		_meas_time: f64,
+		_divisor: u32,
+	) -> i32 {
+		if let Err(err_code) = self.channel_indices(counter, "ctr") {
+			return err_code;
+		}
+
+		let channel = SimChannel::PulseTiming {
+			pulses: self.device.pulses.clone(),
+			period: true,
+		};
+
+		self.add_channels(task, vec![channel])
+	}
+
+	unsafe fn create_ao_voltage_chan(
+		&self,
+		task: TaskPtr,
+		physical_channel: &CStr,
+		_name_to_assign: &CStr,
+		min_val: f64,
+		max_val: f64,
+		_units: i32,
+	) -> i32 {
+		let indices = match self.channel_indices(physical_channel, "ao") {
+			Ok(indices) => indices,
+			Err(err_code) => return err_code,
+		};
+
+		let channels = indices
+			.into_iter()
+			.map(|_| SimChannel::AnalogOutput {
+				min: min_val,
+				max: max_val,
+			})
+			.collect();
+
+		self.add_channels(task, channels)
+	}
+
+	unsafe fn create_di_chan(
+		&self,
+		task: TaskPtr,
+		lines: &CStr,
+		_name_to_assign: &CStr,
+		_line_grouping: i32,
+	) -> i32 {
+		let indices = match self.channel_indices(lines, "port0/line") {
+			Ok(indices) => indices,
+			Err(err_code) => return err_code,
+		};
+
+		let digital = &self.device.digital;
+		let channels = indices
+			.into_iter()
+			.map(|line| SimChannel::DigitalInput {
+				line,
+				signal: match digital.len() {
+					0 => Waveform::Constant(0.0),
+					len => digital[line as usize % len].clone(),
+				},
+				lines: Arc::clone(&self.lines),
+			})
+			.collect();
+
+		self.add_channels(task, channels)
+	}
+
+	unsafe fn create_do_chan(
+		&self,
+		task: TaskPtr,
+		lines: &CStr,
+		_name_to_assign: &CStr,
+		_line_grouping: i32,
+	) -> i32 {
+		let indices = match self.channel_indices(lines, "port0/line") {
+			Ok(indices) => indices,
+			Err(err_code) => return err_code,
+		};
+
+		let channels = indices
+			.into_iter()
+			.map(|line| SimChannel::DigitalOutput { line })
+			.collect();
+
+		self.add_channels(task, channels)
+	}
+
+	unsafe fn cfg_samp_clk_timing(
+		&self,
+		task: TaskPtr,
+		source: &CStr,
+		rate: f64,
+		_active_edge: i32,
+		sample_mode: i32,
+		samps_per_chan: u64,
+	) -> i32 {
+		let source = source.to_string_lossy();
+		if !source.is_empty() && !self.is_pfi_terminal(&source) {
+			return self.fail(
+				DAQmxErrorInvalidRoutingSourceTerminalName,
+				format!("Source terminal '{}' is invalid.", source),
+			);
+		}
+
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		// The onboard clock divides its timebase down, so only some rates can be generated
+		let rate = if source.is_empty() {
+			SIM_TIMEBASE / (SIM_TIMEBASE / rate).round().max(1.0)
+		} else {
+			rate
+		};
+
+		lock(&sim_task.state).timing = Some(SimTiming {
+			rate,
+			finite: sample_mode as u32 == nidaqmx_sys::DAQmx_Val_FiniteSamps,
+			samps_per_chan,
+		});
+
+		0
+	}
+
+	unsafe fn cfg_implicit_timing(
+		&self,
+		task: TaskPtr,
+		_sample_mode: i32,
+		_samps_per_chan: u64,
+	) -> i32 {
+		self.task(task)
+			.map(|_| 0)
+			.unwrap_or_else(|err_code| err_code)
+	}
+
+	unsafe fn get_samp_clk_rate(&self, task: TaskPtr, rate: &mut f64) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		let timing_rate = lock(&sim_task.state).timing.as_ref().map(|timing| timing.rate);
+		match timing_rate {
+			Some(timing_rate) => {
+				*rate = timing_rate;
+				0
+			}
+			None => self.fail(DAQmxErrorAttrNotSupported, ATTR_NOT_SUPPORTED),
+		}
+	}
+
+	unsafe fn get_co_pulse_freq(&self, task: TaskPtr, channel: &CStr, freq: &mut f64) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		// Unnamed channels go by their physical channel
+		let channel = channel.to_string_lossy();
+		let pulse_freq = lock(&sim_task.state).channels.iter().find_map(|chan| match chan {
+			SimChannel::PulseOutput { counter, freq }
+				if channel == format!("{}/ctr{}", self.device.name, counter) =>
+			{
+				Some(*freq)
+			}
+			_ => None,
+		});
+
+		match pulse_freq {
+			Some(pulse_freq) => {
+				*freq = pulse_freq;
+				0
+			}
+			None => self.fail(
+				DAQmxErrorChanNotInTask,
+				format!("Channel '{}' is not in the task.", channel),
+			),
+		}
+	}
+
+	unsafe fn cfg_dig_edge_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		_trigger_edge: i32,
+	) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		let source = trigger_source.to_string_lossy().into_owned();
+		if !self.is_pfi_terminal(&source) && !self.is_counter_output_terminal(&source) {
+			return self.fail(
+				DAQmxErrorInvalidRoutingSourceTerminalName,
+				format!("Trigger source '{}' is invalid.", source),
+			);
+		}
+
+		lock(&sim_task.state).start_trigger = Some(source);
+
+		0
+	}
+
+	unsafe fn cfg_anlg_edge_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_slope: i32,
+		trigger_level: f64,
+	) -> i32 {
+		let condition = AnalogCondition::Edge {
+			slope: sim_slope(trigger_slope),
+			level: trigger_level,
+		};
+
+		match self.analog_trigger(task, trigger_source, condition) {
+			Ok((sim_task, trigger)) => {
+				lock(&sim_task.state).analog_start = Some(trigger);
+				0
+			}
+			Err(err_code) => err_code,
+		}
+	}
+
+	unsafe fn cfg_anlg_window_start_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_when: i32,
+		window_top: f64,
+		window_bottom: f64,
+	) -> i32 {
+		let condition = AnalogCondition::Window {
+			when: sim_window_condition(trigger_when),
+			bottom: window_bottom,
+			top: window_top,
+		};
+
+		match self.analog_trigger(task, trigger_source, condition) {
+			Ok((sim_task, trigger)) => {
+				lock(&sim_task.state).analog_start = Some(trigger);
+				0
+			}
+			Err(err_code) => err_code,
+		}
+	}
+
+	unsafe fn cfg_anlg_edge_ref_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_slope: i32,
+		trigger_level: f64,
+		pretrigger_samples: u32,
+	) -> i32 {
+		let condition = AnalogCondition::Edge {
+			slope: sim_slope(trigger_slope),
+			level: trigger_level,
+		};
+
+		match self.analog_trigger(task, trigger_source, condition) {
+			Ok((sim_task, trigger)) => {
+				lock(&sim_task.state).ref_trigger = Some((trigger, pretrigger_samples as u64));
+				0
+			}
+			Err(err_code) => err_code,
+		}
+	}
+
+	unsafe fn cfg_anlg_window_ref_trig(
+		&self,
+		task: TaskPtr,
+		trigger_source: &CStr,
+		trigger_when: i32,
+		window_top: f64,
+		window_bottom: f64,
+		pretrigger_samples: u32,
+	) -> i32 {
+		let condition = AnalogCondition::Window {
+			when: sim_window_condition(trigger_when),
+			bottom: window_bottom,
+			top: window_top,
+		};
+
+		match self.analog_trigger(task, trigger_source, condition) {
+			Ok((sim_task, trigger)) => {
+				lock(&sim_task.state).ref_trigger = Some((trigger, pretrigger_samples as u64));
+				0
+			}
+			Err(err_code) => err_code,
+		}
+	}
+
+	unsafe fn register_every_n_samples_event(
+		&self,
+		task: TaskPtr,
+		_event_type: i32,
+		n_samps: u32,
+		_options: u32,
+		callback: RawReadCallback,
+		callback_data: *mut c_void,
+	) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		lock(&sim_task.state).read_callback =
+			callback.map(|func| (func, n_samps.max(1), callback_data as usize));
+
+		0
+	}
+
+	unsafe fn register_done_event(
+		&self,
+		task: TaskPtr,
+		_options: u32,
+		callback: RawDoneCallback,
+		callback_data: *mut c_void,
+	) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		lock(&sim_task.state).done_callback = callback.map(|func| (func, callback_data as usize));
+
+		0
+	}
+
+	unsafe fn read_analog_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		fill_mode: u32,
+		buf: &mut [f64],
+		samps_read: &mut i32,
+	) -> i32 {
+		let buf_len = buf.len();
+		let mut too_small = false;
+
+		let result = self.read_scans(task, n_samps, timeout, SimKind::Analog, |scans, n_chans| {
+			if scans.len() > buf_len {
+				too_small = true;
+				return;
+			}
+
+			let n_scans = scans.len() / n_chans;
+			for (ind, val) in scans.iter().enumerate() {
+				let (scan, chan) = (ind / n_chans, ind % n_chans);
+
+				match fill_mode {
+					nidaqmx_sys::DAQmx_Val_GroupByScanNumber => buf[ind] = *val,
+					_ => buf[chan * n_scans + scan] = *val,
+				}
+			}
+		});
+
+		finish_read(self, result, too_small, samps_read)
+	}
+
+	unsafe fn read_counter_u32(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [u32],
+		samps_read: &mut i32,
+	) -> i32 {
+		let buf_len = buf.len();
+		let mut too_small = false;
+
+		let result = self.read_scans(task, n_samps, timeout, SimKind::Counter, |scans, n_chans| {
+			if scans.len() / n_chans > buf_len {
+				too_small = true;
+				return;
+			}
+
+			// Counter tasks only read their first channel
+			for (dst, scan) in buf.iter_mut().zip(scans.chunks(n_chans)) {
+				*dst = scan[0] as i64 as i32 as u32;
+			}
+		});
+
+		finish_read(self, result, too_small, samps_read)
+	}
+
+	unsafe fn read_counter_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		buf: &mut [f64],
+		samps_read: &mut i32,
+	) -> i32 {
+		let buf_len = buf.len();
+		let mut too_small = false;
+
+		let result = self.read_scans(task, n_samps, timeout, SimKind::Counter, |scans, n_chans| {
+			if scans.len() / n_chans > buf_len {
+				too_small = true;
+				return;
+			}
+
+			// Counter tasks only read their first channel
+			for (dst, scan) in buf.iter_mut().zip(scans.chunks(n_chans)) {
+				*dst = scan[0];
+			}
+		});
+
+		finish_read(self, result, too_small, samps_read)
+	}
+
+	unsafe fn read_digital_lines(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		timeout: f64,
+		_fill_mode: u32,
+		buf: &mut [u8],
+		samps_read: &mut i32,
+		bytes_per_samp: &mut i32,
+	) -> i32 {
+		let buf_len = buf.len();
+		let mut too_small = false;
+
+		// One channel per line, always grouped by scan
+		let result = self.read_scans(task, n_samps, timeout, SimKind::Digital, |scans, _| {
+			if scans.len() > buf_len {
+				too_small = true;
+				return;
+			}
+
+			for (dst, level) in buf.iter_mut().zip(scans) {
+				*dst = *level as u8;
+			}
+		});
+
+		*bytes_per_samp = 1;
+		finish_read(self, result, too_small, samps_read)
+	}
+
+	unsafe fn write_analog_f64(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		auto_start: bool,
+		_timeout: f64,
+		_data_layout: u32,
+		buf: &[f64],
+		samps_written: &mut i32,
+	) -> i32 {
+		self.write_scans(task, n_samps, auto_start, SimKind::Analog, buf, samps_written)
+	}
+
+	unsafe fn write_digital_lines(
+		&self,
+		task: TaskPtr,
+		n_samps: i32,
+		auto_start: bool,
+		_timeout: f64,
+		_data_layout: u32,
+		buf: &[u8],
+		samps_written: &mut i32,
+	) -> i32 {
+		let buf: Vec<f64> = buf.iter().map(|&level| level as f64).collect();
+
+		self.write_scans(task, n_samps, auto_start, SimKind::Digital, &buf, samps_written)
+	}
+
+	unsafe fn set_write_regen_mode(&self, task: TaskPtr, _regen_mode: i32) -> i32 {
+		self.task(task)
+			.map(|_| 0)
+			.unwrap_or_else(|err_code| err_code)
+	}
+
+	unsafe fn cfg_input_buffer(&self, task: TaskPtr, num_samps_per_chan: u32) -> i32 {
+		self.with_state(task, |state| state.buffer_size = Some(num_samps_per_chan as u64))
+	}
+
+	unsafe fn get_buf_input_buf_size(&self, task: TaskPtr, buf_size: &mut u32) -> i32 {
+		self.with_state(task, |state| *buf_size = state.buffer_size().unwrap_or(0) as u32)
+	}
+
+	unsafe fn set_read_over_write(&self, task: TaskPtr, over_write: i32) -> i32 {
+		self.with_state(task, |state| {
+			state.overwrite = over_write == nidaqmx_sys::DAQmx_Val_OverwriteUnreadSamps as i32
+		})
+	}
+
+	unsafe fn set_read_relative_to(&self, task: TaskPtr, relative_to: i32) -> i32 {
+		self.with_state(task, |state| state.read_relative_to = relative_to)
+	}
+
+	unsafe fn set_read_offset(&self, task: TaskPtr, offset: i32) -> i32 {
+		self.with_state(task, |state| state.read_offset = offset)
+	}
+
+	unsafe fn get_read_avail_samp_per_chan(&self, task: TaskPtr, avail: &mut u32) -> i32 {
+		self.with_state(task, |state| {
+			*avail = (state.available() / state.channels.len().max(1)) as u32
+		})
+	}
+
+	unsafe fn get_read_total_samp_per_chan_acquired(&self, task: TaskPtr, total: &mut u64) -> i32 {
+		self.with_state(task, |state| *total = state.acquired)
+	}
+
+	// AC coupling takes the mean off the analog signal
+	unsafe fn set_ai_coupling(&self, task: TaskPtr, channel: &CStr, coupling: i32) -> i32 {
+		let sim_task = match self.task(task) {
+			Ok(sim_task) => sim_task,
+			Err(err_code) => return err_code,
+		};
+
+		let channel = channel.to_string_lossy();
+		let mut state = lock(&sim_task.state);
+
+		// Unnamed channels go by their physical channels, ranges included
+		let device = &self.device.name;
+		let chan_names = channel.split(',').map(str::trim).flat_map(|entry| {
+			match parse_physical_channels(device, "ai", entry) {
+				Some(indices) => {
+					indices.into_iter().map(|ind| format!("{}/ai{}", device, ind)).collect()
+				}
+				None => vec![entry.to_string()],
+			}
+		});
+
+		for chan_name in chan_names {
+			let mut found = false;
+
+			for chan in state.channels.iter_mut() {
+				if let SimChannel::Analog {
+					name, ac_coupled, ..
+				} = chan
+				{
+					if *name == chan_name {
+						*ac_coupled = coupling == DAQmx_Val_AC as i32;
+						found = true;
+					}
+				}
+			}
+
+			if !found {
+				return self.fail(
+					DAQmxErrorChanNotInTask,
+					format!("Channel '{}' is not in the task.", chan_name),
+				);
+			}
+		}
+
+		0
+	}
+
+	fn get_sys_dev_names(&self, buf: &mut [u8]) -> i32 {
+		copy_string(&self.device.name, buf)
+	}
+
+	fn get_dev_product_type(&self, device: &CStr, buf: &mut [u8]) -> i32 {
+		self.query_device(device, |device| copy_string(&device.product_type, buf))
+	}
+
+	fn get_dev_serial_num(&self, device: &CStr, serial_num: &mut u32) -> i32 {
+		self.query_device(device, |device| {
+			*serial_num = device.serial_number;
+			0
+		})
+	}
+
+	fn get_dev_ai_physical_chans(&self, device: &CStr, buf: &mut [u8]) -> i32 {
+		self.query_device(device, |device| {
+			let chans = name_list(device.ai_channels, |ind| format!("{}/ai{}", device.name, ind));
+			copy_string(&chans, buf)
+		})
+	}
+
+	fn get_dev_ci_physical_chans(&self, device: &CStr, buf: &mut [u8]) -> i32 {
+		self.query_device(device, |device| {
+			let chans = name_list(device.counters, |ind| format!("{}/ctr{}", device.name, ind));
+			copy_string(&chans, buf)
+		})
+	}
+
+	fn get_dev_terminals(&self, device: &CStr, buf: &mut [u8]) -> i32 {
+		self.query_device(device, |device| {
+			let terminals = name_list(device.pfi_lines, |ind| format!("/{}/PFI{}", device.name, ind));
+			copy_string(&terminals, buf)
+		})
+	}
+
+	// Stops every task and lets go of the digital lines, the tasks themselves stay valid
+	fn reset_device(&self, device: &CStr) -> i32 {
+		self.query_device(device, |_| {
+			let sim_tasks: Vec<_> = lock(&self.tasks).values().cloned().collect();
+			for sim_task in sim_tasks {
+				self.stop(&sim_task);
+			}
+
+			lock(&self.lines.0).clear();
+			0
+		})
+	}
+
+	fn self_test_device(&self, device: &CStr) -> i32 {
+		self.query_device(device, |device| {
+			if device.fails_self_test {
+				self.fail(DAQmxErrorSelfTestFailed, "Self test of the device has failed.")
+			} else {
+				0
+			}
+		})
+	}
+
+	fn get_self_cal_last_date_and_time(
+		&self,
+		device: &CStr,
+		year: &mut u32,
+		month: &mut u32,
+		day: &mut u32,
+		hour: &mut u32,
+		minute: &mut u32,
+	) -> i32 {
+		self.query_device(device, |device| match device.calibration.self_cal {
+			Some(self_cal) => {
+				let date = self_cal.date;
+				*year = date.year;
+				*month = date.month;
+				*day = date.day;
+				*hour = date.hour;
+				*minute = date.minute;
+				0
+			}
+			None => self.fail(DAQmxErrorAttrNotSupported, ATTR_NOT_SUPPORTED),
+		})
+	}
+
+	fn get_ext_cal_last_date_and_time(
+		&self,
+		device: &CStr,
+		year: &mut u32,
+		month: &mut u32,
+		day: &mut u32,
+		hour: &mut u32,
+		minute: &mut u32,
+	) -> i32 {
+		self.query_device(device, |device| {
+			let date = device.calibration.external.date;
+			*year = date.year;
+			*month = date.month;
+			*day = date.day;
+			*hour = date.hour;
+			*minute = date.minute;
+			0
+		})
+	}
+
+	fn get_self_cal_last_temp(&self, device: &CStr, temp: &mut f64) -> i32 {
+		self.query_device(device, |device| match device.calibration.self_cal {
+			Some(self_cal) => {
+				*temp = self_cal.temperature;
+				0
+			}
+			None => self.fail(DAQmxErrorAttrNotSupported, ATTR_NOT_SUPPORTED),
+		})
+	}
+
+	fn get_ext_cal_last_temp(&self, device: &CStr, temp: &mut f64) -> i32 {
+		self.query_device(device, |device| {
+			*temp = device.calibration.external.temperature;
+			0
+		})
+	}
+
+	fn get_cal_dev_temp(&self, device: &CStr, temp: &mut f64) -> i32 {
+		self.query_device(device, |device| match device.calibration.temperature {
+			Some(temperature) => {
+				*temp = temperature;
+				0
+			}
+			None => self.fail(DAQmxErrorAttrNotSupported, ATTR_NOT_SUPPORTED),
+		})
+	}
+
+	fn extended_error_info(&self) -> String {
+		LAST_ERROR.with(|last_error| last_error.borrow().clone())
+	}
+}
+
+// Follows the DAQmx string query convention, see `DaqBackend`
+fn copy_string(string: &str, buf: &mut [u8]) -> i32 {
+	let bytes = string.as_bytes();
+
+	if buf.is_empty() {
+		return bytes.len() as i32 + 1;
+	}
+
+	let len = bytes.len().min(buf.len() - 1);
+	buf[..len].copy_from_slice(&bytes[..len]);
+	buf[len] = b'\0';
+
+	0
+}
+
+fn name_list<F: Fn(u32) -> String>(count: u32, name: F) -> String {
+	(0..count).map(name).collect::<Vec<_>>().join(", ")
+}
+
+fn sim_slope(slope: i32) -> Slope {
+	match slope as u32 {
+		nidaqmx_sys::DAQmx_Val_FallingSlope => Slope::Falling,
+		_ => Slope::Rising,
+	}
+}
+
+fn sim_window_condition(when: i32) -> WindowCondition {
+	match when as u32 {
+		nidaqmx_sys::DAQmx_Val_LeavingWin => WindowCondition::Leaving,
+		_ => WindowCondition::Entering,
+	}
+}
+
+// Ticks per encoder pulse for a DAQmx decoding type
+fn counts_per_pulse(decoding_type: i32) -> f64 {
+	match decoding_type as u32 {
+		nidaqmx_sys::DAQmx_Val_X4 => 4.0,
+		nidaqmx_sys::DAQmx_Val_X2 => 2.0,
+		_ => 1.0,
+	}
+}
+
+fn finish_read(
+	backend: &SimulatedBackend,
+	result: Result<i32, i32>,
+	too_small: bool,
+	samps_read: &mut i32,
+) -> i32 {
+	match result {
+		Ok(_) if too_small => backend.fail(
+			DAQmxErrorReadBufferTooSmall,
+			"Buffer is too small to fit the read data.",
+		),
+		Ok(n_read) => {
+			*samps_read = n_read;
+			0
+		}
+		Err(err_code) => {
+			*samps_read = 0;
+			err_code
+		}
+	}
+}
+
+struct SimTiming {
+	rate: f64,
+	finite: bool,
+	samps_per_chan: u64,
+}
+
+// Maps an analog signal to the units of the channel
+#[derive(Debug, Clone)]
+enum SimScale {
+	Volts,
+	// Coefficients from the constant term up
+	Polynomial(Vec<f64>),
+	// (electrical, physical) points sorted by the electrical value, interpolated linearly and
+	// held past the ends
+	Table(Vec<(f64, f64)>),
+}
+
+impl SimScale {
+	fn apply(&self, val: f64) -> f64 {
+		match self {
+			SimScale::Volts => val,
+			SimScale::Polynomial(coeffs) => coeffs.iter().rev().fold(0.0, |acc, c| acc * val + c),
+			SimScale::Table(table) => {
+				let upper = table.iter().position(|&(electrical, _)| electrical >= val);
+
+				match upper {
+					Some(0) => table[0].1,
+					Some(ind) => {
+						let (x0, y0) = table[ind - 1];
+						let (x1, y1) = table[ind];
+						y0 + (y1 - y0) * (val - x0) / (x1 - x0)
+					}
+					None => table[table.len() - 1].1,
+				}
+			}
+		}
+	}
+}
+
+enum SimChannel {
+	Analog {
+		name: String,
+		waveform: Waveform,
+		scale: SimScale,
+		ac_coupled: bool,
+		min: f64,
+		max: f64,
+	},
+	LinEncoder {
+		motion: Waveform,
+		dist_per_tick: f64,				// [m]
+		meters_per_unit: Option<f64>,	// None when read in ticks
+	},
+	AngVelocity {
+		motion: Waveform,
+		units_per_rev_per_sec: f64,
+	},
+	CountEdges {
+		pulses: Waveform,
+		initial_count: f64,
+		down: bool,
+	},
+	// Frequency [Hz] or period [s] of the pulses, ticks aren't simulated
+	PulseTiming {
+		pulses: Waveform,
+		period: bool,
+	},
+	Encoder {
+		motion: Waveform,
+		ticks_per_rev: f64,
+		z_idx: Option<f64>,
+	},
+	AnalogOutput {
+		min: f64,
+		max: f64,
+	},
+	DigitalInput {
+		line: u32,
+		signal: Waveform,
+		lines: Arc<SimLines>,
+	},
+	DigitalOutput {
+		line: u32,
+	},
+	PulseOutput {
+		counter: u32,
+		freq: f64,	// Coerced [Hz]
+	},
+}
+
+// What a channel is read or written as
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SimKind {
+	Analog,
+	Counter,
+	Digital,
+}
+
+impl SimChannel {
+	fn is_input(&self) -> bool {
+		match self {
+			SimChannel::AnalogOutput { .. }
+			| SimChannel::DigitalOutput { .. }
+			| SimChannel::PulseOutput { .. } => false,
+			_ => true,
+		}
+	}
+
+	fn kind(&self) -> SimKind {
+		match self {
+			SimChannel::Analog { .. } | SimChannel::AnalogOutput { .. } => SimKind::Analog,
+			SimChannel::Encoder { .. }
+			| SimChannel::LinEncoder { .. }
+			| SimChannel::AngVelocity { .. }
+			| SimChannel::CountEdges { .. }
+			| SimChannel::PulseTiming { .. }
+			| SimChannel::PulseOutput { .. } => SimKind::Counter,
+			SimChannel::DigitalInput { .. } | SimChannel::DigitalOutput { .. } => SimKind::Digital,
+		}
+	}
+
+	// Whether an output can be set to `val`
+	fn in_range(&self, val: f64) -> bool {
+		match self {
+			SimChannel::AnalogOutput { min, max } => val >= *min && val <= *max,
+			_ => true,
+		}
+	}
+
+	fn sample(&self, t: f64, rng: &mut XorShift) -> f64 {
+		match self {
+			SimChannel::Analog {
+				waveform,
+				scale,
+				ac_coupled,
+				min,
+				max,
+				..
+			} => {
+				let mut val = waveform.sample(t, rng);
+				if *ac_coupled {
+					val -= waveform.mean();
+				}
+
+				scale.apply(val).max(*min).min(*max)
+			}
+			SimChannel::Encoder {
+				motion,
+				ticks_per_rev,
+				z_idx,
+			} => {
+				let ticks = (motion.sample(t, rng) * ticks_per_rev).round();
+
+				// The index pulse resets the count once per revolution
+				match z_idx {
+					Some(z_idx_val) => ticks.rem_euclid(*ticks_per_rev) + z_idx_val,
+					None => ticks,
+				}
+			}
+			SimChannel::LinEncoder {
+				motion,
+				dist_per_tick,
+				meters_per_unit,
+			} => {
+				let ticks = (motion.sample(t, rng) / dist_per_tick).round();
+
+				match meters_per_unit {
+					Some(meters_per_unit) => ticks * dist_per_tick / meters_per_unit,
+					None => ticks,
+				}
+			}
+			SimChannel::AngVelocity {
+				motion,
+				units_per_rev_per_sec,
+			} => motion.derivative(t) * units_per_rev_per_sec,
+			SimChannel::CountEdges {
+				pulses,
+				initial_count,
+				down,
+			} => {
+				let count = pulses.sample(t, rng).floor();
+				if *down {
+					initial_count - count
+				} else {
+					initial_count + count
+				}
+			}
+			SimChannel::PulseTiming { pulses, period } => {
+				let freq = pulses.derivative(t).abs();
+				if *period {
+					1.0 / freq	// Infinite without any pulses
+				} else {
+					freq
+				}
+			}
+			SimChannel::DigitalInput {
+				line,
+				signal,
+				lines,
+			} => {
+				let high = lines.level(*line).unwrap_or_else(|| signal.sample(t, rng) > 0.5);
+				if high {
+					1.0
+				} else {
+					0.0
+				}
+			}
+			SimChannel::AnalogOutput { .. }
+			| SimChannel::DigitalOutput { .. }
+			| SimChannel::PulseOutput { .. } => 0.0,
+		}
+	}
+}
+
+#[derive(Default)]
+struct SimTaskState {
+	channels: Vec<SimChannel>,
+	timing: Option<SimTiming>,
+	start_trigger: Option<String>,
+	analog_start: Option<SimAnalogTrigger>,
+	// With the number of pretrigger samples
+	ref_trigger: Option<(SimAnalogTrigger, u64)>,
+	// Samples the analog start and reference triggers occured on
+	started_at: Option<u64>,
+	triggered_at: Option<u64>,
+	read_callback: Option<(
+		unsafe extern "C" fn(*mut c_void, i32, u32, *mut c_void) -> i32,
+		u32,
+		usize,
+	)>,
+	done_callback: Option<(
+		unsafe extern "C" fn(*mut c_void, i32, *mut c_void) -> i32,
+		usize,
+	)>,
+	// Scans waiting to be read, interleaved by channel
+	buffer: VecDeque<f64>,
+	// Set with DAQmxCfgInputBuffer, the sample clock's samples per channel otherwise
+	buffer_size: Option<u64>,
+	// Overflows drop the oldest scans silently instead of failing the next read
+	overwrite: bool,
+	read_relative_to: i32,
+	read_offset: i32,
+	samps_generated: u64,
+	// Scans that made it into the buffer since the task started
+	acquired: u64,
+	overwritten: bool,
+	removed: bool,
+	rng: XorShift,
+}
+
+impl SimTaskState {
+	// Sample a finite acquisition ends at, unknown until its triggers occured
+	fn end_sample(&self) -> Option<u64> {
+		match self.timing {
+			Some(SimTiming {
+				finite: true,
+				samps_per_chan,
+				..
+			}) => match &self.ref_trigger {
+				Some((_, pretrig)) => self.triggered_at.map(|ind| ind + samps_per_chan - pretrig),
+				None => self.started_at.map(|ind| ind + samps_per_chan),
+			},
+			_ => None,
+		}
+	}
+
+	fn buffer_size(&self) -> Option<u64> {
+		self.buffer_size
+			.or_else(|| self.timing.as_ref().map(|timing| timing.samps_per_chan))
+	}
+
+	// Pretrigger samples can't be read before the reference trigger occured
+	fn available(&self) -> usize {
+		match (&self.ref_trigger, self.triggered_at) {
+			(Some(_), None) => 0,
+			_ => self.buffer.len(),
+		}
+	}
+}
+
+// Analog trigger on one of the task's channels
+struct SimAnalogTrigger {
+	channel: usize,
+	condition: AnalogCondition,
+	last: Option<f64>,	// Previous sample of the channel
+}
+
+impl SimAnalogTrigger {
+	// Whether the condition is met between the last sample and this one
+	fn check(&mut self, scan: &[f64]) -> bool {
+		let val = scan[self.channel];
+		let last = self.last.replace(val);
+
+		let last = match last {
+			Some(last) => last,
+			None => return false,
+		};
+
+		match self.condition {
+			AnalogCondition::Edge {
+				slope: Slope::Rising,
+				level,
+			} => last < level && val >= level,
+			AnalogCondition::Edge {
+				slope: Slope::Falling,
+				level,
+			} => last > level && val <= level,
+			AnalogCondition::Window { when, bottom, top } => {
+				let inside = |val| val >= bottom && val <= top;
+				match when {
+					WindowCondition::Entering => !inside(last) && inside(val),
+					WindowCondition::Leaving => inside(last) && !inside(val),
+				}
+			}
+		}
+	}
+}
+
+struct SimTask {
+	created: Instant,
+	state: Mutex<SimTaskState>,
+	data_ready: Condvar,
+	running: AtomicBool,
+	worker: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl SimTask {
+	fn new() -> Self {
+		SimTask {
+			created: Instant::now(),
+			state: Mutex::new(SimTaskState::default()),
+			data_ready: Condvar::new(),
+			running: AtomicBool::new(false),
+			worker: Mutex::new(None),
+		}
+	}
+
+	fn stop(&self) {
+		self.running.store(false, Ordering::SeqCst);
+		self.data_ready.notify_all();
+
+		let worker = lock(&self.worker).take();
+
+		// A task stopped from inside one of its own callbacks can't wait on itself
+		if let Some(worker) = worker {
+			if worker.thread().id() != thread::current().id() {
+				let _ = worker.join();
+			}
+		}
+	}
+
+	// `removal` is when the device gets unplugged [s], reported to the done callback on this
+	// thread
+	fn run(
+		&self,
+		task_id: usize,
+		triggers: &SimTriggers,
+		removal: Option<f64>,
+	) {
+		let start_trigger = lock(&self.state).start_trigger.clone();
+
+		let start = match start_trigger {
+			Some(terminal) => match triggers.wait(&terminal, &self.running) {
+				Some(start) => start,
+				None => return,
+			},
+			None => Instant::now(),
+		};
+
+		let (rate, batch_size) = {
+			let state = lock(&self.state);
+			let rate = state
+				.timing
+				.as_ref()
+				.map(|timing| timing.rate)
+				.unwrap_or(1.0);
+			let batch_size = match state.read_callback {
+				Some((_, n_samps, _)) => n_samps as u64,
+				None => ((rate / 100.0) as u64).max(1),
+			};
+
+			(rate, batch_size)
+		};
+
+		let removed_at = removal.map(|secs| (secs * rate) as u64);
+
+		while self.running.load(Ordering::SeqCst) {
+			let (read_callback, done_callback, finished, removed) = {
+				let mut state = lock(&self.state);
+
+				let generated = state.samps_generated;
+				let target = generated + batch_size;
+				let target = match state.end_sample() {
+					Some(end) => target.min(end),
+					None => target,
+				};
+				let target = match removed_at {
+					Some(removed_at) => target.min(removed_at),
+					None => target,
+				};
+
+				// Wait for the sample clock to catch up
+				let deadline = start + Duration::from_secs_f64(target as f64 / rate);
+				drop(state);
+				while self.running.load(Ordering::SeqCst) {
+					let now = Instant::now();
+					if now >= deadline {
+						break;
+					}
+					thread::sleep(MAX_SLEEP.min(deadline - now));
+				}
+				state = lock(&self.state);
+
+				self.generate(&mut state, target, rate);
+
+				let finished = state.end_sample().is_some_and(|end| state.samps_generated >= end);
+
+				let removed = match removed_at {
+					Some(removed_at) => state.samps_generated >= removed_at,
+					None => false,
+				};
+
+				// Like the driver, only whole batches of N samples fire the read callback, and none
+				// fire while waiting for a reference trigger
+				let read_callback = match target - generated {
+					n_new if n_new == batch_size && state.available() > 0 => state.read_callback,
+					_ => None,
+				};
+
+				(read_callback, state.done_callback, finished, removed)
+			};
+
+			self.data_ready.notify_all();
+
+			if !self.running.load(Ordering::SeqCst) {
+				break;
+			}
+
+			let task_ptr = task_id as *mut c_void;
+
+			if let Some((func, n_samps, data)) = read_callback {
+				let event_type = nidaqmx_sys::DAQmx_Val_Acquired_Into_Buffer as i32;
+				unsafe { func(task_ptr, event_type, n_samps, data as *mut c_void) };
+			}
+
+			if finished || removed {
+				self.running.store(false, Ordering::SeqCst);
+
+				let status = if removed {
+					lock(&self.state).removed = true;
+					set_last_error(DEVICE_REMOVED);
+					DAQmxErrorDeviceRemoved
+				} else {
+					0
+				};
+
+				if let Some((func, data)) = done_callback {
+					unsafe { func(task_ptr, status, data as *mut c_void) };
+				}
+			}
+		}
+	}
+
+	fn generate(&self, state: &mut SimTaskState, target: u64, rate: f64) {
+		let capacity = match state.buffer_size() {
+			Some(size) => (size.max(1) as usize) * state.channels.len(),
+			None => usize::MAX,
+		};
+
+		let n_chans = state.channels.len();
+
+		for ind in state.samps_generated..target {
+			// A trigger in this batch can end the acquisition early
+			if state.end_sample().is_some_and(|end| ind >= end) {
+				break;
+			}
+
+			let t = ind as f64 / rate;
+
+			let mut scan = Vec::with_capacity(n_chans);
+			for chan in 0..n_chans {
+				let val = state.channels[chan].sample(t, &mut state.rng);
+				scan.push(val);
+			}
+
+			// Nothing is acquired before the analog start trigger
+			if state.started_at.is_none() {
+				let started = match &mut state.analog_start {
+					Some(trigger) => trigger.check(&scan),
+					None => true,
+				};
+
+				if !started {
+					continue;
+				}
+				state.started_at = Some(ind);
+			}
+			let started_at = state.started_at.unwrap_or(0);
+
+			state.buffer.extend(scan.iter());
+			state.acquired += 1;
+
+			// Only the last pretrigger samples are kept until the reference trigger occurs
+			if state.triggered_at.is_none() {
+				if let Some((trigger, pretrig)) = &mut state.ref_trigger {
+					let pretrig = *pretrig;
+					if trigger.check(&scan) && ind - started_at >= pretrig {
+						state.triggered_at = Some(ind);
+					} else {
+						let excess = state.buffer.len().saturating_sub(pretrig as usize * n_chans);
+						state.buffer.drain(..excess);
+					}
+				}
+			}
+		}
+
+		state.samps_generated = state.samps_generated.max(target);
+
+		// Continuous tasks overwrite the oldest unread samples once the buffer is full
+		if state.buffer.len() > capacity {
+			let excess = state.buffer.len() - capacity;
+			state.buffer.drain(..excess);
+			state.overwritten = !state.overwrite;
+		}
+	}
+}
+
+// Levels of the digital lines driven by an output
+#[derive(Default)]
+struct SimLines(Mutex<HashMap<u32, bool>>);
+
+impl SimLines {
+	fn drive(&self, line: u32, high: bool) {
+		lock(&self.0).insert(line, high);
+	}
+
+	fn level(&self, line: u32) -> Option<bool> {
+		lock(&self.0).get(&line).cloned()
+	}
+}
+
+// Edges seen on the trigger terminals. Counter outputs fire when their task starts, external
+// PFI signals are assumed to be toggling all the time.
+#[derive(Default)]
+struct SimTriggers {
+	fired: Mutex<HashMap<String, Instant>>,
+	changed: Condvar,
+}
+
+impl SimTriggers {
+	fn fire(&self, terminal: String) {
+		lock(&self.fired).insert(terminal, Instant::now());
+		self.changed.notify_all();
+	}
+
+	fn reset(&self, terminal: &str) {
+		lock(&self.fired).remove(terminal);
+	}
+
+	// Time the task actually starts, or None if it was stopped while waiting
+	fn wait(&self, terminal: &str, running: &AtomicBool) -> Option<Instant> {
+		let armed = Instant::now();
+
+		if terminal.rsplit('/').next().is_some_and(|name| name.starts_with("PFI")) {
+			return Some(armed);
+		}
+
+		let mut fired = lock(&self.fired);
+		loop {
+			if !running.load(Ordering::SeqCst) {
+				return None;
+			}
+
+			// A trigger that fired before the task was armed starts it on the next edge
+			if let Some(&time) = fired.get(terminal) {
+				return Some(time.max(armed));
+			}
+
+			fired = self
+				.changed
+				.wait_timeout(fired, MAX_SLEEP)
+				.unwrap_or_else(|err| err.into_inner())
+				.0;
+		}
+	}
+}
+
+// Parses "Dev1/ai0:1, Dev1/ai3" into the channel indices [0, 1, 3], channels of another
+// device or kind are rejected
+// What DAQmx calls the channel at `pos` of the `num_chans` created with `name_to_assign`. A
+// list of names is used one by one, a single name for several channels gets numbered, e.g.
+// name0, name1. None if the channel goes by its physical channel.
+fn assigned_name(name_to_assign: &str, pos: usize, num_chans: usize) -> Option<String> {
+	let names: Vec<_> = name_to_assign.split(',').map(str::trim).collect();
+
+	match names[..] {
+		[""] => None,
+		[name] if num_chans > 1 => Some(format!("{}{}", name, pos)),
+		_ => names.get(pos).map(|name| name.to_string()),
+	}
+}
+
+fn parse_physical_channels(device: &str, kind: &str, physical_channel: &str) -> Option<Vec<u32>> {
+	let index = |name: &str, qualified: bool| -> Option<u32> {
+		let name = name.trim();
+		let name = match name.find('/') {
+			Some(ind) if &name[..ind] == device => &name[ind + 1..],
+			Some(_) => return None,
+			None if qualified => return None,
+			None => name,
+		};
+		let digits = name.trim_start_matches(kind);
+		if digits.len() == name.len() && qualified {
+			return None;
+		}
+		digits.parse().ok()
+	};
+
+	let mut indices = Vec::new();
+
+	for part in physical_channel.split(',') {
+		let mut range = part.splitn(2, ':');
+		let first = index(range.next()?, true)?;
+		let last = match range.next() {
+			Some(last) => index(last, false)?,
+			None => first,
+		};
+
+		if first <= last {
+			indices.extend(first..=last);
+		} else {
+			indices.extend((last..=first).rev());
+		}
+	}
+
+	if indices.is_empty() {
+		None
+	} else {
+		Some(indices)
+	}
+}
+
+// Tiny PRNG so the simulated noise doesn't need an extra dependency
+struct XorShift(u64);
+
+impl Default for XorShift {
+	fn default() -> Self {
+		XorShift(0x2545_F491_4F6C_DD1D)
+	}
+}
+
+impl XorShift {
+	fn next_f64(&mut self) -> f64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+
+		(self.0 >> 11) as f64 / (1u64 << 53) as f64
+	}
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
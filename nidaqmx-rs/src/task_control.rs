@@ -0,0 +1,49 @@
+use super::{
+	buffer::{BufferStatus, OverwriteMode, ReadPosition},
+	error::DaqError,
+	task_handle::TaskHandle,
+};
+
+// Lifecycle and input buffer of a channel's DAQmx task, the same for every kind of input
+pub trait TaskControl {
+	fn task_handle(&mut self) -> &mut TaskHandle;
+
+	// Ends the acquisition, the task keeps its configuration and can be launched again
+	fn stop_task(&mut self) -> Result<(), DaqError> {
+		self.task_handle().stop()
+	}
+
+	// Checks the channels, timing and triggers against the device without reserving it, so a
+	// bad setup shows up before launching
+	fn verify_task(&mut self) -> Result<(), DaqError> {
+		self.task_handle().verify()
+	}
+
+	// Reserves the hardware ahead of time, so launching and relaunching after a stop are quick
+	fn commit_task(&mut self) -> Result<(), DaqError> {
+		self.task_handle().commit()
+	}
+
+	fn is_task_done(&mut self) -> Result<bool, DaqError> {
+		self.task_handle().is_done()
+	}
+
+	// Samples waiting in and acquired into the DAQmx buffer, per channel
+	fn buffer_status(&mut self) -> Result<BufferStatus, DaqError> {
+		self.task_handle().buffer_status()
+	}
+
+	// Resizes the buffer picked at creation, in samples per channel. Set before launching.
+	fn set_buffer_size(&mut self, samps_per_chan: u32) -> Result<(), DaqError> {
+		self.task_handle().configure_input_buffer(samps_per_chan)
+	}
+
+	fn set_overwrite_mode(&mut self, mode: OverwriteMode) -> Result<(), DaqError> {
+		self.task_handle().set_overwrite_mode(mode.as_daqmx())
+	}
+
+	// Applies to every read after this one, reads start at the current position by default
+	fn set_read_position(&mut self, position: ReadPosition) -> Result<(), DaqError> {
+		self.task_handle().set_read_position(position.relative_to.as_daqmx(), position.offset)
+	}
+}
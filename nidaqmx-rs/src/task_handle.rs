@@ -1,138 +1,546 @@
 use super::{
+	ai_accel_channel::AccelChannel,
+	ai_force_channel::{ForceBridgeChannel, ForceBridgeScale},
+	backend::{Backend, DaqBackend},
+	buffer::BufferStatus,
+	ci_channel::CounterSample,
 	callback_utils::{
 		self, raw_done_callback_impl, raw_read_callback_impl, CallbackWrapper, DoneCallback,
-		RawDoneCallback,
+		DoneCallbackWrapper, RawDoneCallback, RawReadCallback, ReadCallback, ReadCallbackWrapper,
 	},
+	error::{self, DaqError},
+	trigger::{AnalogCondition, AnalogTrigger},
 	EMPTY_CSTRING,
 };
 
-use std::{
-	ffi::{CStr, CString},
-	os::raw::c_void,
-	ptr,
-};
+use std::{convert::TryFrom, os::raw::c_void, ptr, sync::Arc};
 
 type NonNullVoidPtr = ptr::NonNull<c_void>;
 
-type RawReadCallback = Option<unsafe extern "C" fn(*mut c_void, i32, u32, *mut c_void) -> i32>;
-type ReadCallback<T> = fn(&mut T, &mut RawTaskHandle, u32) -> Result<(), ()>;
-
-pub struct RawTaskHandle(NonNullVoidPtr);
+pub struct RawTaskHandle(NonNullVoidPtr, Backend);
 
 impl RawTaskHandle {
-	// Caller must ensure pointer is a valid task handle
-	pub unsafe fn from_raw(ptr: *mut c_void) -> Option<Self> {
-		NonNullVoidPtr::new(ptr).map(RawTaskHandle)
-	}
-
-	// Caller must ensure pointer is set to a valid task handle
-	pub unsafe fn new() -> Self {
-		RawTaskHandle(NonNullVoidPtr::dangling())
+	// Caller must ensure pointer is a valid task handle of the given backend
+	pub unsafe fn from_raw(ptr: *mut c_void, backend: Backend) -> Option<Self> {
+		NonNullVoidPtr::new(ptr).map(|ptr| RawTaskHandle(ptr, backend))
 	}
 
 	pub fn get(&self) -> NonNullVoidPtr {
 		self.0
 	}
 
-	// Caller must ensure pointer is a valid task handle
-	unsafe fn set(&mut self, ptr: NonNullVoidPtr) {
-		self.0 = ptr;
+	pub fn backend(&self) -> &dyn DaqBackend {
+		&*self.1
 	}
 
-	pub fn chk_err_code(&mut self, err_code: i32) {
-		let failed = err_code < 0;
+	pub fn chk_err_code(&mut self, err_code: i32, call: &'static str) -> Result<(), DaqError> {
+		error::chk_err_code(self.backend(), err_code, call)
+	}
 
-		if failed {
-			self.handle_err();
-		}
+	// Samples per channel acquired but not read yet, safe to call from the DAQ callbacks
+	pub fn available_samples(&mut self) -> Result<u32, DaqError> {
+		let mut avail = 0u32;
+
+		let err_code =
+			unsafe { self.backend().get_read_avail_samp_per_chan(self.get().as_ptr(), &mut avail) };
+
+		self.chk_err_code(err_code, "DAQmxGetReadAvailSampPerChan")?;
+
+		Ok(avail)
 	}
 
-	// Caller must ensure
-	pub unsafe fn clear_task(&mut self) {
-		nidaqmx_sys::DAQmxStopTask(self.get().as_ptr());
-		nidaqmx_sys::DAQmxClearTask(self.get().as_ptr());
+	// Scans are interleaved into `buf`, returns the number of samples read per channel
+	pub fn read_analog_f64(
+		&mut self,
+		n_samps: usize,
+		timeout: f64,
+		buf: &mut [f64],
+	) -> Result<usize, DaqError> {
+		let mut samps_read = 0i32;
+
+		// Should be safe if the task handle is valid
+		let err_code = unsafe {
+			self.backend().read_analog_f64(
+				self.get().as_ptr(),
+				n_samps as i32,
+				timeout,
+				nidaqmx_sys::DAQmx_Val_GroupByScanNumber,
+				buf,
+				&mut samps_read,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxReadAnalogF64")?;
+
+		Ok(samps_read as usize)
 	}
 
-	fn handle_err(&mut self) {
-		const ERROR_CODE_MAX_LEN: usize = 2048;
+	// Reads raw ticks (u32) or scaled values (f64) depending on the sample type
+	pub fn read_counter<S: CounterSample>(
+		&mut self,
+		n_samps: usize,
+		timeout: f64,
+		buf: &mut [S],
+	) -> Result<usize, DaqError> {
+		let mut samps_read = 0i32;
+
+		// Should be safe if the task handle is valid
+		let err_code = unsafe {
+			S::read_counter(
+				self.backend(),
+				self.get().as_ptr(),
+				n_samps as i32,
+				timeout,
+				buf,
+				&mut samps_read,
+			)
+		};
+
+		self.chk_err_code(err_code, S::READ_CALL)?;
+
+		Ok(samps_read as usize)
+	}
 
-		let mut buf = [0u8; ERROR_CODE_MAX_LEN];
-		let buf_ptr = buf.as_ptr() as *mut i8;
+	// Scans of one byte per line are interleaved into `buf`, returns the number of samples read
+	// per line
+	pub fn read_digital_lines(
+		&mut self,
+		n_samps: usize,
+		timeout: f64,
+		buf: &mut [u8],
+	) -> Result<usize, DaqError> {
+		let mut samps_read = 0i32;
+		let mut bytes_per_samp = 0i32;
 
-		// This is safe as long as nidaqmx respects the length of this buffer
-		unsafe { nidaqmx_sys::DAQmxGetExtendedErrorInfo(buf_ptr, buf.len() as u32) };
+		// Should be safe if the task handle is valid
+		let err_code = unsafe {
+			self.backend().read_digital_lines(
+				self.get().as_ptr(),
+				n_samps as i32,
+				timeout,
+				nidaqmx_sys::DAQmx_Val_GroupByScanNumber,
+				buf,
+				&mut samps_read,
+				&mut bytes_per_samp,
+			)
+		};
 
-		let last_elem = buf[..].last_mut().unwrap();
-		*last_elem = b'\0'; // Write out null terminator
+		self.chk_err_code(err_code, "DAQmxReadDigitalLines")?;
 
-		// This is safe since we wrote a null terminator at the end of our buffer
-		let err_msg = unsafe { CStr::from_bytes_with_nul_unchecked(&buf) };
-		let err_msg = err_msg.to_string_lossy();
+		Ok(samps_read as usize)
+	}
 
-		// This is safe since we're immediately panicing
-		unsafe { self.clear_task() };
-		panic!("{}", err_msg);
+	// Caller must ensure the task wasn't cleared
+	pub unsafe fn stop_task(&mut self) {
+		self.backend().stop_task(self.get().as_ptr());
+	}
+
+	// Caller must ensure
+	pub unsafe fn clear_task(&mut self) {
+		self.backend().stop_task(self.get().as_ptr());
+		self.backend().clear_task(self.get().as_ptr());
 	}
 }
 
+// Callback wrapper the backend holds a pointer to, with the function that frees it
+type RegisteredCallback = (*mut c_void, unsafe fn(*mut c_void));
+
 pub struct TaskHandle {
 	raw_handle: RawTaskHandle,
+	// Freed after the task is cleared, the backend may call them until then
+	callbacks: Vec<RegisteredCallback>,
 }
 
 impl TaskHandle {
-	pub fn new() -> Self {
-		// This is safe since we're immediately populating it with a valid handle
-		let mut task_handle = TaskHandle {
-			raw_handle: unsafe { RawTaskHandle::new() },
-		};
-		create_task(&mut task_handle);
+	pub fn with_backend(backend: Backend) -> Result<Self, DaqError> {
+		let raw_handle = create_task(backend)?;
 
-		task_handle
+		Ok(TaskHandle {
+			raw_handle,
+			callbacks: Vec::new(),
+		})
 	}
 
 	pub fn get(&mut self) -> *mut c_void {
 		self.raw_handle.get().as_ptr()
 	}
 
-	pub fn chk_err_code(&mut self, err_code: i32) {
-		self.raw_handle.chk_err_code(err_code);
+	pub fn backend(&self) -> &dyn DaqBackend {
+		self.raw_handle.backend()
+	}
+
+	pub fn chk_err_code(&mut self, err_code: i32, call: &'static str) -> Result<(), DaqError> {
+		self.raw_handle.chk_err_code(err_code, call)
+	}
+
+	pub fn num_channels(&mut self) -> Result<usize, DaqError> {
+		let mut num_chans = 0u32;
+
+		let task = self.get();
+		let err_code = unsafe { self.backend().get_task_num_chans(task, &mut num_chans) };
+
+		self.chk_err_code(err_code, "DAQmxGetTaskNumChans")?;
+
+		Ok(num_chans as usize)
+	}
+
+	// Also restarts a stopped task, its channels, timing and callbacks stay configured
+	pub fn start(&mut self) -> Result<(), DaqError> {
+		let err_code = unsafe { self.backend().start_task(self.raw_handle.get().as_ptr()) };
+
+		self.chk_err_code(err_code, "DAQmxStartTask")
 	}
 
-	pub fn launch(&mut self) {
-		unsafe { nidaqmx_sys::DAQmxStartTask(self.raw_handle.get().as_ptr()) };
+	// Returns the task to the state it was in before it was started, a committed task stays
+	// committed so it can be started again quickly
+	pub fn stop(&mut self) -> Result<(), DaqError> {
+		let err_code = unsafe { self.backend().stop_task(self.raw_handle.get().as_ptr()) };
+
+		self.chk_err_code(err_code, "DAQmxStopTask")
+	}
+
+	// Checks the channels, timing and triggers for errors without reserving any hardware
+	pub fn verify(&mut self) -> Result<(), DaqError> {
+		self.control(nidaqmx_sys::DAQmx_Val_Task_Verify)
 	}
 
-	pub fn create_ai_volt_chan(&mut self, chan_desc: &str, input_span: f64) {
-		let chan_name = EMPTY_CSTRING;
-		let chan_desc = CString::new(chan_desc).unwrap();
+	// Reserves and programs the hardware, so starting the task later takes as little as possible
+	pub fn commit(&mut self) -> Result<(), DaqError> {
+		self.control(nidaqmx_sys::DAQmx_Val_Task_Commit)
+	}
+
+	// Whether a finite task acquired all its samples, or any task stopped because of an error
+	pub fn is_done(&mut self) -> Result<bool, DaqError> {
+		let mut is_done = false;
+
+		let err_code =
+			unsafe { self.backend().is_task_done(self.raw_handle.get().as_ptr(), &mut is_done) };
+
+		self.chk_err_code(err_code, "DAQmxIsTaskDone")?;
+
+		Ok(is_done)
+	}
+
+	pub fn buffer_status(&mut self) -> Result<BufferStatus, DaqError> {
+		let available = self.raw_handle.available_samples()?;
+
+		let mut total_acquired = 0u64;
+		let err_code = unsafe {
+			self.backend()
+				.get_read_total_samp_per_chan_acquired(self.raw_handle.get().as_ptr(), &mut total_acquired)
+		};
+		self.chk_err_code(err_code, "DAQmxGetReadTotalSampPerChanAcquired")?;
+
+		let mut size = 0u32;
+		let err_code =
+			unsafe { self.backend().get_buf_input_buf_size(self.raw_handle.get().as_ptr(), &mut size) };
+		self.chk_err_code(err_code, "DAQmxGetBufInputBufSize")?;
+
+		Ok(BufferStatus {
+			available,
+			total_acquired,
+			size,
+		})
+	}
+
+	// Overrides the buffer size DAQmx picked from the sample clock, in samples per channel
+	pub fn configure_input_buffer(&mut self, samps_per_chan: u32) -> Result<(), DaqError> {
+		let err_code =
+			unsafe { self.backend().cfg_input_buffer(self.raw_handle.get().as_ptr(), samps_per_chan) };
+
+		self.chk_err_code(err_code, "DAQmxCfgInputBuffer")
+	}
+
+	pub fn set_overwrite_mode(&mut self, over_write: i32) -> Result<(), DaqError> {
+		let err_code =
+			unsafe { self.backend().set_read_over_write(self.raw_handle.get().as_ptr(), over_write) };
+
+		self.chk_err_code(err_code, "DAQmxSetReadOverWrite")
+	}
+
+	pub fn set_read_position(&mut self, relative_to: i32, offset: i32) -> Result<(), DaqError> {
+		let task = self.raw_handle.get().as_ptr();
+
+		let err_code = unsafe { self.backend().set_read_relative_to(task, relative_to) };
+		self.chk_err_code(err_code, "DAQmxSetReadRelativeTo")?;
+
+		let err_code = unsafe { self.backend().set_read_offset(task, offset) };
+		self.chk_err_code(err_code, "DAQmxSetReadOffset")
+	}
+
+	// DAQmx reads `n_samps` scans from the buffer it's given, make sure they're all there
+	fn checked_write_len(
+		&mut self,
+		n_samps: usize,
+		buf_len: usize,
+		call: &'static str,
+	) -> Result<i32, DaqError> {
+		let num_channels = self.num_channels()?;
+
+		match (n_samps.checked_mul(num_channels), i32::try_from(n_samps)) {
+			(Some(len), Ok(n_samps)) if len <= buf_len => Ok(n_samps),
+			_ => Err(DaqError::new(
+				nidaqmx_sys::DAQmxErrorWriteBufferTooSmall,
+				format!(
+					"{} samples per channel don't fit in a buffer of {} samples.",
+					n_samps, buf_len
+				),
+				call,
+			)),
+		}
+	}
+
+	fn control(&mut self, action: u32) -> Result<(), DaqError> {
+		let err_code =
+			unsafe { self.backend().task_control(self.raw_handle.get().as_ptr(), action as i32) };
+
+		self.chk_err_code(err_code, "DAQmxTaskControl")
+	}
+
+	pub fn create_ai_volt_chan(
+		&mut self,
+		chan_desc: &str,
+		chan_name: &str,
+		terminal_config: i32,
+		min_val: f64,
+		max_val: f64,
+	) -> Result<(), DaqError> {
+		let chan_name = error::c_string(chan_name, "DAQmxCreateAIVoltageChan")?;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateAIVoltageChan")?;
+
+		// Should be safe if the task handle is valid
+		let error_code = unsafe {
+			self.backend().create_ai_voltage_chan(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
+				&chan_name,
+				terminal_config,
+				min_val,
+				max_val,
+				nidaqmx_sys::DAQmx_Val_Volts as i32,
+			)
+		};
+
+		self.chk_err_code(error_code, "DAQmxCreateAIVoltageChan")
+	}
+
+	// Creates the channel and sets its coupling, through its name if it has one
+	pub fn create_ai_accel_chan(
+		&mut self,
+		chan_desc: &str,
+		chan: &AccelChannel,
+	) -> Result<(), DaqError> {
+		let chan_name = chan.name.as_ref().map_or("", String::as_str);
+		let chan_name = error::c_string(chan_name, "DAQmxCreateAIAccelChan")?;
+		let physical_chan = error::c_string(chan_desc, "DAQmxCreateAIAccelChan")?;
+		let num_chans_before = self.num_channels()?;
+
+		let (excit_source, excit_val) = match chan.excitation_current {
+			Some(amps) => (nidaqmx_sys::DAQmx_Val_Internal as i32, amps),
+			None => (nidaqmx_sys::DAQmx_Val_None as i32, 0.0),
+		};
+
+		// Should be safe if the task handle is valid
+		let error_code = unsafe {
+			self.backend().create_ai_accel_chan(
+				self.raw_handle.get().as_ptr(),
+				&physical_chan,
+				&chan_name,
+				chan.terminal_config.as_daqmx(),
+				chan.min_val,
+				chan.max_val,
+				chan.units.as_daqmx(),
+				chan.sensitivity,
+				nidaqmx_sys::DAQmx_Val_mVoltsPerG as i32,
+				excit_source,
+				excit_val,
+			)
+		};
+		self.chk_err_code(error_code, "DAQmxCreateAIAccelChan")?;
+
+		// DAQmx numbers a single name given to several physical channels, e.g. "blade" over
+		// ai0:2 names them blade0 to blade2
+		let num_created = self.num_channels()? - num_chans_before;
+		let channel = match &chan.name {
+			Some(name) if num_created > 1 && !name.contains(',') => (0..num_created)
+				.map(|ind| format!("{}{}", name, ind))
+				.collect::<Vec<_>>()
+				.join(","),
+			Some(name) => name.clone(),
+			None => chan_desc.to_string(),
+		};
+		let channel = error::c_string(&channel, "DAQmxSetAICoupling")?;
+
+		let error_code = unsafe {
+			self.backend().set_ai_coupling(
+				self.raw_handle.get().as_ptr(),
+				&channel,
+				chan.coupling.as_daqmx(),
+			)
+		};
+
+		self.chk_err_code(error_code, "DAQmxSetAICoupling")
+	}
+
+	// Bridge outputs in the calibration are always given in mV/V
+	pub fn create_ai_force_bridge_chan(
+		&mut self,
+		chan_desc: &str,
+		chan: &ForceBridgeChannel,
+	) -> Result<(), DaqError> {
+		let chan_name = chan.name.as_ref().map_or("", String::as_str);
+		let chan_name = error::c_string(chan_name, "DAQmxCreateAIForceBridgeTwoPointLinChan")?;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateAIForceBridgeTwoPointLinChan")?;
+		let task = self.raw_handle.get().as_ptr();
+		let backend = self.backend();
+
+		let units = chan.units.as_daqmx();
+		let electrical_units = nidaqmx_sys::DAQmx_Val_mVoltsPerVolt as i32;
+
+		// Should be safe if the task handle is valid
+		let (error_code, call) = unsafe {
+			match &chan.scale {
+				ForceBridgeScale::TwoPointLinear { first, second } => (
+					backend.create_ai_force_bridge_two_point_lin_chan(
+						task,
+						&chan_desc,
+						&chan_name,
+						chan.min_val,
+						chan.max_val,
+						units,
+						chan.bridge_config.as_daqmx(),
+						chan.excitation_source.as_daqmx(),
+						chan.excitation_val,
+						chan.nominal_resistance,
+						*first,
+						*second,
+						electrical_units,
+						units,
+					),
+					"DAQmxCreateAIForceBridgeTwoPointLinChan",
+				),
+				ForceBridgeScale::Polynomial { forward, reverse } => (
+					backend.create_ai_force_bridge_polynomial_chan(
+						task,
+						&chan_desc,
+						&chan_name,
+						chan.min_val,
+						chan.max_val,
+						units,
+						chan.bridge_config.as_daqmx(),
+						chan.excitation_source.as_daqmx(),
+						chan.excitation_val,
+						chan.nominal_resistance,
+						forward,
+						reverse,
+						electrical_units,
+						units,
+					),
+					"DAQmxCreateAIForceBridgePolynomialChan",
+				),
+				ForceBridgeScale::Table {
+					electrical,
+					physical,
+				} => (
+					backend.create_ai_force_bridge_table_chan(
+						task,
+						&chan_desc,
+						&chan_name,
+						chan.min_val,
+						chan.max_val,
+						units,
+						chan.bridge_config.as_daqmx(),
+						chan.excitation_source.as_daqmx(),
+						chan.excitation_val,
+						chan.nominal_resistance,
+						electrical,
+						physical,
+						electrical_units,
+						units,
+					),
+					"DAQmxCreateAIForceBridgeTableChan",
+				),
+			}
+		};
+
+		self.chk_err_code(error_code, call)
+	}
+
+	pub fn create_ao_volt_chan(
+		&mut self,
+		chan_desc: &str,
+		chan_name: &str,
+		min_val: f64,
+		max_val: f64,
+	) -> Result<(), DaqError> {
+		let chan_name = error::c_string(chan_name, "DAQmxCreateAOVoltageChan")?;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateAOVoltageChan")?;
 
 		// Should be safe if the task handle is valid
 		let error_code = unsafe {
-			nidaqmx_sys::DAQmxCreateAIVoltageChan(
-				self.get(),
-				chan_desc.as_ptr(),
-				chan_name,
-				nidaqmx_sys::DAQmx_Val_Cfg_Default,
-				-input_span,
-				input_span,
+			self.backend().create_ao_voltage_chan(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
+				&chan_name,
+				min_val,
+				max_val,
 				nidaqmx_sys::DAQmx_Val_Volts as i32,
-				ptr::null(),
 			)
 		};
 
-		self.chk_err_code(error_code);
+		self.chk_err_code(error_code, "DAQmxCreateAOVoltageChan")
 	}
 
-	pub fn create_co_freq_chan(&mut self, chan_desc: &str, freq: f64, duty_cycle: f64) {
+	// One channel per line, so every line is a column of the scans
+	pub fn create_di_chan(&mut self, lines: &str, chan_name: &str) -> Result<(), DaqError> {
+		let chan_name = error::c_string(chan_name, "DAQmxCreateDIChan")?;
+		let lines = error::c_string(lines, "DAQmxCreateDIChan")?;
+
+		// Should be safe if the task handle is valid
+		let error_code = unsafe {
+			self.backend().create_di_chan(
+				self.raw_handle.get().as_ptr(),
+				&lines,
+				&chan_name,
+				nidaqmx_sys::DAQmx_Val_ChanPerLine as i32,
+			)
+		};
+
+		self.chk_err_code(error_code, "DAQmxCreateDIChan")
+	}
+
+	pub fn create_do_chan(&mut self, lines: &str, chan_name: &str) -> Result<(), DaqError> {
+		let chan_name = error::c_string(chan_name, "DAQmxCreateDOChan")?;
+		let lines = error::c_string(lines, "DAQmxCreateDOChan")?;
+
+		// Should be safe if the task handle is valid
+		let error_code = unsafe {
+			self.backend().create_do_chan(
+				self.raw_handle.get().as_ptr(),
+				&lines,
+				&chan_name,
+				nidaqmx_sys::DAQmx_Val_ChanPerLine as i32,
+			)
+		};
+
+		self.chk_err_code(error_code, "DAQmxCreateDOChan")
+	}
+
+	pub fn create_co_freq_chan(
+		&mut self,
+		chan_desc: &str,
+		freq: f64,
+		duty_cycle: f64,
+	) -> Result<(), DaqError> {
 		const INITIAL_DELAY: f64 = 0.0;
 
 		let name_of_channel = EMPTY_CSTRING;
-		let chan_desc = CString::new(chan_desc).unwrap();
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateCOPulseChanFreq")?;
 
 		let err_code = unsafe {
-			nidaqmx_sys::DAQmxCreateCOPulseChanFreq(
-				self.get(),
-				chan_desc.as_ptr(),
+			self.backend().create_co_pulse_chan_freq(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
 				name_of_channel,
 				nidaqmx_sys::DAQmx_Val_Hz as i32,
 				nidaqmx_sys::DAQmx_Val_Low as i32,
@@ -142,25 +550,427 @@ impl TaskHandle {
 			)
 		};
 
-		self.chk_err_code(err_code);
+		self.chk_err_code(err_code, "DAQmxCreateCOPulseChanFreq")
 	}
 
-	pub fn configure_sample_clock(&mut self, clk_src: &str, rate: f64, samples_per_batch: u64) {
-		let clk_src = CString::new(clk_src).unwrap();
+	pub fn create_ci_ang_encoder_chan(
+		&mut self,
+		chan_desc: &str,
+		decoding_type: i32,
+		z_idx_enable: bool,
+		z_idx_val: f64,
+		z_idx_phase: i32,
+		units: i32,
+		pulses_per_rev: u32,
+		initial_angle: f64,
+	) -> Result<(), DaqError> {
+		let name_of_channel = EMPTY_CSTRING;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateCIAngEncoderChan")?;
+
+		let err_code = unsafe {
+			self.backend().create_ci_ang_encoder_chan(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
+				name_of_channel,
+				decoding_type,
+				z_idx_enable,
+				z_idx_val,
+				z_idx_phase,
+				units,
+				pulses_per_rev,
+				initial_angle,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCreateCIAngEncoderChan")
+	}
+
+	pub fn create_ci_lin_encoder_chan(
+		&mut self,
+		chan_desc: &str,
+		decoding_type: i32,
+		z_idx_enable: bool,
+		z_idx_val: f64,
+		z_idx_phase: i32,
+		units: i32,
+		dist_per_pulse: f64,
+		initial_pos: f64,
+	) -> Result<(), DaqError> {
+		let name_of_channel = EMPTY_CSTRING;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateCILinEncoderChan")?;
+
+		let err_code = unsafe {
+			self.backend().create_ci_lin_encoder_chan(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
+				name_of_channel,
+				decoding_type,
+				z_idx_enable,
+				z_idx_val,
+				z_idx_phase,
+				units,
+				dist_per_pulse,
+				initial_pos,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCreateCILinEncoderChan")
+	}
+
+	pub fn create_ci_ang_velocity_chan(
+		&mut self,
+		chan_desc: &str,
+		min_val: f64,
+		max_val: f64,
+		decoding_type: i32,
+		units: i32,
+		pulses_per_rev: u32,
+	) -> Result<(), DaqError> {
+		let name_of_channel = EMPTY_CSTRING;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateCIAngVelocityChan")?;
+
+		let err_code = unsafe {
+			self.backend().create_ci_ang_velocity_chan(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
+				name_of_channel,
+				min_val,
+				max_val,
+				decoding_type,
+				units,
+				pulses_per_rev,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCreateCIAngVelocityChan")
+	}
+
+	pub fn create_ci_count_edges_chan(
+		&mut self,
+		chan_desc: &str,
+		edge: i32,
+		initial_count: u32,
+		count_direction: i32,
+	) -> Result<(), DaqError> {
+		let name_of_channel = EMPTY_CSTRING;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateCICountEdgesChan")?;
+
+		let err_code = unsafe {
+			self.backend().create_ci_count_edges_chan(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
+				name_of_channel,
+				edge,
+				initial_count,
+				count_direction,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCreateCICountEdgesChan")
+	}
+
+	pub fn create_ci_freq_chan(
+		&mut self,
+		chan_desc: &str,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		edge: i32,
+		meas_method: i32,
+		meas_time: f64,
+		divisor: u32,
+	) -> Result<(), DaqError> {
+		let name_of_channel = EMPTY_CSTRING;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateCIFreqChan")?;
+
+		let err_code = unsafe {
+			self.backend().create_ci_freq_chan(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
+				name_of_channel,
+				min_val,
+				max_val,
+				units,
+				edge,
+				meas_method,
+				meas_time,
+				divisor,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCreateCIFreqChan")
+	}
+
+	pub fn create_ci_period_chan(
+		&mut self,
+		chan_desc: &str,
+		min_val: f64,
+		max_val: f64,
+		units: i32,
+		edge: i32,
+		meas_method: i32,
+		meas_time: f64,
+		divisor: u32,
+	) -> Result<(), DaqError> {
+		let name_of_channel = EMPTY_CSTRING;
+		let chan_desc = error::c_string(chan_desc, "DAQmxCreateCIPeriodChan")?;
+
+		let err_code = unsafe {
+			self.backend().create_ci_period_chan(
+				self.raw_handle.get().as_ptr(),
+				&chan_desc,
+				name_of_channel,
+				min_val,
+				max_val,
+				units,
+				edge,
+				meas_method,
+				meas_time,
+				divisor,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCreateCIPeriodChan")
+	}
+
+	// `samps_per_chan` is the buffer size in continuous mode and the acquisition length in finite
+	// mode
+	pub fn configure_sample_clock(
+		&mut self,
+		clk_src: &str,
+		rate: f64,
+		sample_mode: i32,
+		samps_per_chan: u64,
+	) -> Result<(), DaqError> {
+		let clk_src = error::c_string(clk_src, "DAQmxCfgSampClkTiming")?;
 
 		// Should be safe if the task handle is valid
 		let err_code = unsafe {
-			nidaqmx_sys::DAQmxCfgSampClkTiming(
-				self.get(),
-				clk_src.as_ptr(),
+			self.backend().cfg_samp_clk_timing(
+				self.raw_handle.get().as_ptr(),
+				&clk_src,
 				rate,
 				nidaqmx_sys::DAQmx_Val_Rising as i32,
+				sample_mode,
+				samps_per_chan,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCfgSampClkTiming")
+	}
+
+	pub fn sample_clock_rate(&mut self) -> Result<f64, DaqError> {
+		let mut rate = 0.0;
+
+		let err_code =
+			unsafe { self.backend().get_samp_clk_rate(self.raw_handle.get().as_ptr(), &mut rate) };
+
+		self.chk_err_code(err_code, "DAQmxGetSampClkRate")?;
+
+		Ok(rate)
+	}
+
+	// Frequency the counter output really generates [Hz]
+	pub fn co_pulse_freq(&mut self, channel: &str) -> Result<f64, DaqError> {
+		let channel = error::c_string(channel, "DAQmxGetCOPulseFreq")?;
+		let mut freq = 0.0;
+
+		let err_code = unsafe {
+			self.backend()
+				.get_co_pulse_freq(self.raw_handle.get().as_ptr(), &channel, &mut freq)
+		};
+
+		self.chk_err_code(err_code, "DAQmxGetCOPulseFreq")?;
+
+		Ok(freq)
+	}
+
+	pub fn configure_implicit_timing(&mut self, buf_size: u64) -> Result<(), DaqError> {
+		let err_code = unsafe {
+			self.backend().cfg_implicit_timing(
+				self.raw_handle.get().as_ptr(),
 				nidaqmx_sys::DAQmx_Val_ContSamps as i32,
-				samples_per_batch,
+				buf_size,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCfgImplicitTiming")
+	}
+
+	pub fn configure_start_trigger(&mut self, source: &str, edge: i32) -> Result<(), DaqError> {
+		let source = error::c_string(source, "DAQmxCfgDigEdgeStartTrig")?;
+
+		// Should be safe if the task handle is valid
+		let err_code = unsafe {
+			self.backend()
+				.cfg_dig_edge_start_trig(self.raw_handle.get().as_ptr(), &source, edge)
+		};
+
+		self.chk_err_code(err_code, "DAQmxCfgDigEdgeStartTrig")
+	}
+
+	pub fn configure_analog_start_trigger(&mut self, trigger: &AnalogTrigger) -> Result<(), DaqError> {
+		let task = self.raw_handle.get().as_ptr();
+
+		// Should be safe if the task handle is valid
+		match trigger.condition {
+			AnalogCondition::Edge { slope, level } => {
+				let source = error::c_string(&trigger.source, "DAQmxCfgAnlgEdgeStartTrig")?;
+				let err_code = unsafe {
+					self.backend()
+						.cfg_anlg_edge_start_trig(task, &source, slope.as_daqmx(), level)
+				};
+				self.chk_err_code(err_code, "DAQmxCfgAnlgEdgeStartTrig")
+			}
+			AnalogCondition::Window { when, bottom, top } => {
+				let source = error::c_string(&trigger.source, "DAQmxCfgAnlgWindowStartTrig")?;
+				let err_code = unsafe {
+					self.backend()
+						.cfg_anlg_window_start_trig(task, &source, when.as_daqmx(), top, bottom)
+				};
+				self.chk_err_code(err_code, "DAQmxCfgAnlgWindowStartTrig")
+			}
+		}
+	}
+
+	pub fn configure_reference_trigger(
+		&mut self,
+		trigger: &AnalogTrigger,
+		pretrigger_samples: u32,
+	) -> Result<(), DaqError> {
+		let task = self.raw_handle.get().as_ptr();
+
+		// Should be safe if the task handle is valid
+		match trigger.condition {
+			AnalogCondition::Edge { slope, level } => {
+				let source = error::c_string(&trigger.source, "DAQmxCfgAnlgEdgeRefTrig")?;
+				let err_code = unsafe {
+					self.backend().cfg_anlg_edge_ref_trig(
+						task,
+						&source,
+						slope.as_daqmx(),
+						level,
+						pretrigger_samples,
+					)
+				};
+				self.chk_err_code(err_code, "DAQmxCfgAnlgEdgeRefTrig")
+			}
+			AnalogCondition::Window { when, bottom, top } => {
+				let source = error::c_string(&trigger.source, "DAQmxCfgAnlgWindowRefTrig")?;
+				let err_code = unsafe {
+					self.backend().cfg_anlg_window_ref_trig(
+						task,
+						&source,
+						when.as_daqmx(),
+						top,
+						bottom,
+						pretrigger_samples,
+					)
+				};
+				self.chk_err_code(err_code, "DAQmxCfgAnlgWindowRefTrig")
+			}
+		}
+	}
+
+	pub fn read_analog_f64(
+		&mut self,
+		n_samps: usize,
+		timeout: f64,
+		buf: &mut [f64],
+	) -> Result<usize, DaqError> {
+		self.raw_handle.read_analog_f64(n_samps, timeout, buf)
+	}
+
+	pub fn read_counter<S: CounterSample>(
+		&mut self,
+		n_samps: usize,
+		timeout: f64,
+		buf: &mut [S],
+	) -> Result<usize, DaqError> {
+		self.raw_handle.read_counter(n_samps, timeout, buf)
+	}
+
+	pub fn read_digital_lines(
+		&mut self,
+		n_samps: usize,
+		timeout: f64,
+		buf: &mut [u8],
+	) -> Result<usize, DaqError> {
+		self.raw_handle.read_digital_lines(n_samps, timeout, buf)
+	}
+
+	// `buf` holds a byte per line for every scan, returns the number of samples written per line
+	pub fn write_digital_lines(
+		&mut self,
+		n_samps: usize,
+		auto_start: bool,
+		timeout: f64,
+		buf: &[u8],
+	) -> Result<usize, DaqError> {
+		let n_samps = self.checked_write_len(n_samps, buf.len(), "DAQmxWriteDigitalLines")?;
+		let mut samps_written = 0i32;
+
+		// Should be safe if the task handle is valid
+		let err_code = unsafe {
+			self.backend().write_digital_lines(
+				self.raw_handle.get().as_ptr(),
+				n_samps,
+				auto_start,
+				timeout,
+				nidaqmx_sys::DAQmx_Val_GroupByScanNumber,
+				buf,
+				&mut samps_written,
+			)
+		};
+
+		self.chk_err_code(err_code, "DAQmxWriteDigitalLines")?;
+
+		Ok(samps_written as usize)
+	}
+
+	// `buf` holds interleaved scans, returns the number of samples written per channel
+	pub fn write_analog_f64(
+		&mut self,
+		n_samps: usize,
+		auto_start: bool,
+		timeout: f64,
+		buf: &[f64],
+	) -> Result<usize, DaqError> {
+		let n_samps = self.checked_write_len(n_samps, buf.len(), "DAQmxWriteAnalogF64")?;
+		let mut samps_written = 0i32;
+
+		// Should be safe if the task handle is valid
+		let err_code = unsafe {
+			self.backend().write_analog_f64(
+				self.raw_handle.get().as_ptr(),
+				n_samps,
+				auto_start,
+				timeout,
+				nidaqmx_sys::DAQmx_Val_GroupByScanNumber,
+				buf,
+				&mut samps_written,
 			)
 		};
 
-		self.chk_err_code(err_code);
+		self.chk_err_code(err_code, "DAQmxWriteAnalogF64")?;
+
+		Ok(samps_written as usize)
+	}
+
+	// Whether the output buffer is generated over and over or has to be refilled continuously
+	pub fn set_regeneration(&mut self, allow: bool) -> Result<(), DaqError> {
+		let regen_mode = if allow {
+			nidaqmx_sys::DAQmx_Val_AllowRegen
+		} else {
+			nidaqmx_sys::DAQmx_Val_DoNotAllowRegen
+		};
+
+		let err_code = unsafe {
+			self.backend()
+				.set_write_regen_mode(self.raw_handle.get().as_ptr(), regen_mode as i32)
+		};
+
+		self.chk_err_code(err_code, "DAQmxSetWriteRegenMode")
 	}
 
 	pub unsafe fn register_read_callback<T>(
@@ -168,18 +978,20 @@ impl TaskHandle {
 		n_samps: u32,
 		callback: ReadCallback<T>,
 		callback_data: T,
-	) {
+	) -> Result<(), DaqError> {
 		const SAMPLES_EVENT_TYPE: i32 = nidaqmx_sys::DAQmx_Val_Acquired_Into_Buffer as i32;
 
 		// This raw callback bootstraps the execution of the actual callback
 		let raw_callback: RawReadCallback = Some(raw_read_callback_impl::<T>);
 
 		// The actual user defined callback
-		let callback_wrapper = CallbackWrapper::new(callback_data, callback);
+		let backend = Arc::clone(&self.raw_handle.1);
+		let callback_wrapper = CallbackWrapper::new(callback_data, callback, backend);
 		let callback_wrapper_ptr = callback_wrapper.into_raw();
 
-		let err_code = nidaqmx_sys::DAQmxRegisterEveryNSamplesEvent(
-			self.get(),
+		let task = self.get();
+		let err_code = self.backend().register_every_n_samples_event(
+			task,
 			SAMPLES_EVENT_TYPE,
 			n_samps,
 			callback_utils::CALLBACK_OPTIONS,
@@ -187,59 +999,85 @@ impl TaskHandle {
 			callback_wrapper_ptr,
 		);
 
-		self.chk_err_code(err_code);
+		let result = self.chk_err_code(err_code, "DAQmxRegisterEveryNSamplesEvent");
+
+		// The backend never took the callback if registering failed
+		match result {
+			Ok(()) => self
+				.callbacks
+				.push((callback_wrapper_ptr, ReadCallbackWrapper::<T>::free_raw)),
+			Err(_) => ReadCallbackWrapper::<T>::free_raw(callback_wrapper_ptr),
+		}
+
+		result
 	}
 
 	pub unsafe fn register_done_callback<T>(
 		&mut self,
 		callback: DoneCallback<T>,
 		callback_data: T,
-	) {
+	) -> Result<(), DaqError> {
 		// This raw callback bootstraps the execution of the actual callback
 		let raw_callback: RawDoneCallback = Some(raw_done_callback_impl::<T>);
 
 		// The actual user defined callback
-		let callback_wrapper = CallbackWrapper::new(callback_data, callback);
+		let backend = Arc::clone(&self.raw_handle.1);
+		let callback_wrapper = CallbackWrapper::new(callback_data, callback, backend);
 		let callback_wrapper_ptr = callback_wrapper.into_raw();
 
-		let err_code = nidaqmx_sys::DAQmxRegisterDoneEvent(
-			self.get(),
+		let task = self.get();
+		let err_code = self.backend().register_done_event(
+			task,
 			callback_utils::CALLBACK_OPTIONS,
 			raw_callback,
 			callback_wrapper_ptr,
 		);
 
-		self.chk_err_code(err_code);
+		let result = self.chk_err_code(err_code, "DAQmxRegisterDoneEvent");
+
+		// The backend never took the callback if registering failed
+		match result {
+			Ok(()) => self
+				.callbacks
+				.push((callback_wrapper_ptr, DoneCallbackWrapper::<T>::free_raw)),
+			Err(_) => DoneCallbackWrapper::<T>::free_raw(callback_wrapper_ptr),
+		}
+
+		result
 	}
 }
 
 impl Drop for TaskHandle {
 	// This is safe becuase the task handle is going out of scope, and will no longer be used
 	fn drop(&mut self) {
-		unsafe { self.raw_handle.clear_task() };
+		unsafe {
+			self.raw_handle.clear_task();
+
+			// Clearing the task unregistered the callbacks, nothing can call them anymore
+			for (ptr, free) in self.callbacks.drain(..) {
+				free(ptr);
+			}
+		}
 	}
 }
 
 unsafe impl Send for TaskHandle {}
 
-fn create_task(th: &mut TaskHandle) {
+fn create_task(backend: Backend) -> Result<RawTaskHandle, DaqError> {
 	let task_name = EMPTY_CSTRING;
 
 	let mut tmp_handle: *mut c_void = ptr::null_mut();
-	let tmp_handle_ptr = &mut tmp_handle as *mut _;
 
-	// This is safe as long as NI isn't dumb
-	let error_code = unsafe { nidaqmx_sys::DAQmxCreateTask(task_name, tmp_handle_ptr) };
+	let error_code = backend.create_task(task_name, &mut tmp_handle);
 
-	assert!(
-		!tmp_handle.is_null(),
-		"DAQmxCreateTask seems to have returned a null pointer."
-	);
+	error::chk_err_code(&*backend, error_code, "DAQmxCreateTask")?;
 
 	// Safe since tmp_handle should be a valid task handle
-	unsafe {
-		th.raw_handle.set(NonNullVoidPtr::new_unchecked(tmp_handle));
-	}
-
-	th.raw_handle.chk_err_code(error_code);
+	unsafe { RawTaskHandle::from_raw(tmp_handle, backend) }.ok_or_else(|| {
+		DaqError::new(
+			error_code,
+			"DAQmxCreateTask seems to have returned a null pointer.",
+			"DAQmxCreateTask",
+		)
+	})
 }
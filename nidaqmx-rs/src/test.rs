@@ -0,0 +1,1083 @@
+pub use super::*;
+
+use futures::{
+	executor::{block_on, block_on_stream},
+	StreamExt, TryStreamExt,
+};
+
+const SAMPLE_RATE: usize = 1000;
+
+#[test]
+fn simulated_ai_stream() {
+	let backend = simulated(SimulatedDevice {
+		analog: vec![Waveform::Constant(1.5), Waveform::Constant(-0.5)],
+		..Default::default()
+	});
+
+	let ai_chan =
+		AiChannel::with_backend(backend, "Dev1", "PFI13", "ai0:1", SAMPLE_RATE).unwrap();
+	let mut ai_stream = ai_chan.make_async().unwrap();
+	ai_stream.launch_task().unwrap();
+
+	let scans = block_on(ai_stream.take(25).try_collect::<Vec<_>>())
+		.unwrap_or_else(|_| panic!("stream failed"));
+
+	assert_eq!(scans.len(), 25);
+	for (ind, scan) in scans.iter().enumerate() {
+		assert_eq!(scan.sample, ind as u64 + 1);
+		assert_eq!(*scan.data, [1.5, -0.5]);
+	}
+}
+
+#[test]
+fn simulated_encoder_stream() {
+	let backend = simulated(SimulatedDevice {
+		encoder: Waveform::Ramp {
+			start: 0.0,
+			end: 1.0,
+			period: 1.0,
+		},
+		..Default::default()
+	});
+
+	let encoder_chan =
+		CiEncoderChannel::with_backend(backend, "Dev1", SAMPLE_RATE, EncoderConfig::default())
+			.unwrap();
+	let mut encoder_stream = encoder_chan.make_async().unwrap();
+	encoder_stream.launch_task().unwrap();
+
+	let readings = block_on(encoder_stream.take(20).try_collect::<Vec<_>>())
+		.unwrap_or_else(|_| panic!("stream failed"));
+
+	// 500 pulses per rev with X4 decoding, one rev per second, sampled at 1 kHz
+	for (ind, reading) in readings.iter().enumerate() {
+		assert_eq!(reading.pos, ind as i32 * 2);
+	}
+}
+
+#[test]
+fn ai_channel_count_from_physical_channels() {
+	let backend = simulated(SimulatedDevice::default());
+
+	let ai_chan = AiChannel::with_backend(backend, "Dev1", "", "ai0:3, ai6", SAMPLE_RATE).unwrap();
+	assert_eq!(ai_chan.num_channels(), 5);
+
+	let mut ai_stream = ai_chan.make_async().unwrap();
+	ai_stream.launch_task().unwrap();
+
+	let first = block_on_stream(ai_stream).next().unwrap().unwrap();
+	assert_eq!(first.data.len(), 5);
+	assert_eq!(first.to_string().split(',').count(), 7);
+}
+
+#[test]
+fn ai_builder_per_channel_range() {
+	let backend = simulated(SimulatedDevice {
+		analog: vec![Waveform::Constant(1.5)],
+		..Default::default()
+	});
+
+	let ai_chan = AiChannel::builder("Dev1", "", SAMPLE_RATE)
+		.backend(backend)
+		.channel(AiVoltageChannel::new("ai0").name("wide"))
+		.channel(
+			AiVoltageChannel::new("ai1")
+				.name("narrow")
+				.range(-0.5, 0.5)
+				.terminal_config(TerminalConfig::Differential),
+		)
+		.build()
+		.unwrap();
+
+	let mut ai_stream = ai_chan.make_async().unwrap();
+	ai_stream.launch_task().unwrap();
+
+	let first = block_on_stream(ai_stream).next().unwrap().unwrap();
+	assert_eq!(*first.data, [1.5, 0.5]);
+}
+
+#[test]
+fn simulated_device_discovery() {
+	let backend = simulated(SimulatedDevice {
+		name: "Dev2".to_string(),
+		serial_number: 0x1234,
+		ai_channels: 2,
+		counters: 2,
+		pfi_lines: 2,
+		..Default::default()
+	});
+
+	let devices = list_devices_with_backend(&*backend).unwrap();
+	assert_eq!(
+		devices,
+		[DeviceInfo {
+			name: "Dev2".to_string(),
+			product_type: "PCIe-6321".to_string(),
+			serial_number: 0x1234,
+			ai_channels: vec!["Dev2/ai0".to_string(), "Dev2/ai1".to_string()],
+			ci_channels: vec!["Dev2/ctr0".to_string(), "Dev2/ctr1".to_string()],
+			pfi_terminals: vec!["/Dev2/PFI0".to_string(), "/Dev2/PFI1".to_string()],
+		}]
+	);
+
+	// Channels have to live on the device they're created for
+	let ai_chan = |device, clk_src, physical_channels| {
+		AiChannel::with_backend(
+			Backend::clone(&backend),
+			device,
+			clk_src,
+			physical_channels,
+			SAMPLE_RATE,
+		)
+	};
+	assert!(ai_chan("Dev1", "", "ai0").is_err());
+	assert!(ai_chan("Dev2", "", "ai2").is_err());
+	assert!(ai_chan("Dev2", "PFI2", "ai1").is_err());
+	assert!(ai_chan("Dev2", "PFI1", "ai0:1").is_ok());
+}
+
+#[test]
+fn concurrent_streams_count_samples_independently() {
+	let backend = simulated(SimulatedDevice::default());
+
+	let first = AiChannel::with_backend(Backend::clone(&backend), "Dev1", "", "ai0", SAMPLE_RATE)
+		.unwrap()
+		.make_async()
+		.unwrap();
+	let second = AiChannel::with_backend(backend, "Dev1", "", "ai1:2", SAMPLE_RATE)
+		.unwrap()
+		.make_async()
+		.unwrap();
+
+	let mut first = block_on_stream(first);
+	first.launch_task().unwrap();
+	let _ = first.by_ref().take(30).count();
+
+	// The second stream starts well after the first and numbers its samples from scratch
+	let mut second = block_on_stream(second);
+	second.launch_task().unwrap();
+
+	let second_numbers: Vec<_> = second.take(10).map(|scan| scan.unwrap().sample).collect();
+	assert_eq!(second_numbers, (1..=10).collect::<Vec<_>>());
+
+	let first_number = first.next().unwrap().unwrap().sample;
+	assert!(first_number > 30);
+}
+
+#[test]
+fn start_trigger_waits_for_counter_output() {
+	let backend = simulated(SimulatedDevice::default());
+	let trigger = StartTrigger {
+		source: TriggerSource::CounterOutput(1),
+		edge: Edge::Rising,
+	};
+
+	let mut ai_stream = AiChannel::builder("Dev1", "PFI13", SAMPLE_RATE)
+		.backend(Backend::clone(&backend))
+		.channel(AiVoltageChannel::new("ai0"))
+		.start_trigger(trigger)
+		.build()
+		.unwrap()
+		.make_async()
+		.unwrap();
+	let config = EncoderConfig {
+		setup: CiSetup {
+			start_trigger: Some(trigger),
+			..Default::default()
+		},
+		..Default::default()
+	};
+	let mut encoder_stream = CiEncoderChannel::with_backend(backend, "Dev1", SAMPLE_RATE, config)
+		.unwrap()
+		.make_async()
+		.unwrap();
+
+	// Armed, but the encoder's sample clock counter isn't running yet
+	ai_stream.arm().unwrap();
+	std::thread::sleep(std::time::Duration::from_millis(100));
+
+	let fired = std::time::Instant::now();
+	encoder_stream.launch_task().unwrap();
+
+	let mut ai_stream = block_on_stream(ai_stream);
+	let first = ai_stream.next().unwrap().unwrap();
+	assert_eq!(first.sample, 1);
+	assert!(fired.elapsed() >= std::time::Duration::from_millis(10));
+
+	let first = block_on_stream(encoder_stream).next().unwrap().unwrap();
+	assert_eq!(first.sample, 1);
+}
+
+#[test]
+fn start_trigger_source_must_exist() {
+	let backend = simulated(SimulatedDevice::default());
+
+	let ai_chan = AiChannel::builder("Dev1", "", SAMPLE_RATE)
+		.backend(backend)
+		.channel(AiVoltageChannel::new("ai0"))
+		.start_trigger(StartTrigger {
+			source: TriggerSource::CounterOutput(7),
+			edge: Edge::Rising,
+		})
+		.build();
+
+	assert!(ai_chan.is_err());
+}
+
+#[test]
+fn synchronized_launch_rolls_back() {
+	let backend = simulated(SimulatedDevice::default());
+	let builder = || {
+		AiChannel::builder("Dev1", "", SAMPLE_RATE)
+			.backend(Backend::clone(&backend))
+			.channel(AiVoltageChannel::new("ai0"))
+	};
+
+	let mut first = builder().build().unwrap();
+	// Fails to start, DAQmx wants at least two posttrigger samples
+	let mut second = builder()
+		.reference_trigger(ReferenceTrigger {
+			trigger: AnalogTrigger::edge("Dev1/ai0", Slope::Rising, 0.0),
+			pretrigger_samples: 10,
+			posttrigger_samples: 1,
+		})
+		.build()
+		.unwrap();
+
+	let err = launch_synchronized(&mut [&mut first, &mut second]).unwrap_err();
+	assert_eq!(err.code, nidaqmx_sys::DAQmxErrorTooManyPretrigPlusMinPostTrigSamps);
+
+	// The first task was armed, but doesn't keep acquiring on its own
+	assert!(first.is_task_done().unwrap());
+	assert!(second.is_task_done().unwrap());
+}
+
+// Sample numbers read from a bounded stream that wasn't polled until it overflowed
+fn stalled_ai_stream(policy: OverflowPolicy) -> Vec<Result<u64, i32>> {
+	const CAPACITY: usize = 20;
+
+	let backend = simulated(SimulatedDevice::default());
+	let ai_chan = AiChannel::with_backend(backend, "Dev1", "", "ai0", SAMPLE_RATE).unwrap();
+
+	let mut ai_stream = ai_chan.make_async_bounded(CAPACITY, policy).unwrap();
+	let stats = ai_stream.stats();
+	ai_stream.launch_task().unwrap();
+
+	// Nobody reads for a while, so the queue overflows
+	std::thread::sleep(std::time::Duration::from_millis(100));
+
+	let mut numbers = Vec::new();
+	for scan in block_on_stream(ai_stream).take(CAPACITY + 1) {
+		let failed = scan.is_err();
+		numbers.push(scan.map(|scan| scan.sample).map_err(|err| err.code));
+		if failed {
+			break;
+		}
+	}
+
+	assert_eq!(stats.high_water(), CAPACITY);
+	assert!(stats.dropped() > 0);
+
+	numbers
+}
+
+#[test]
+fn bounded_stream_drops_oldest() {
+	let numbers = stalled_ai_stream(OverflowPolicy::DropOldest);
+
+	// The newest samples are kept, in order
+	assert!(*numbers[0].as_ref().unwrap() > 1);
+	for pair in numbers.windows(2) {
+		assert_eq!(pair[1], pair[0].map(|number| number + 1));
+	}
+}
+
+#[test]
+fn bounded_stream_drops_newest() {
+	let numbers = stalled_ai_stream(OverflowPolicy::DropNewest);
+
+	// The first samples are kept, everything after them is lost until there's room again
+	let expected: Vec<_> = (1..=20).map(Ok).collect();
+	assert_eq!(numbers[..20], expected[..]);
+	assert!(numbers[20].unwrap() > 21);
+}
+
+#[test]
+fn bounded_stream_errors_on_overflow() {
+	let numbers = stalled_ai_stream(OverflowPolicy::Error);
+
+	assert_eq!(numbers, [Err(nidaqmx_sys::DAQmxErrorSamplesNoLongerAvailable)]);
+}
+
+#[test]
+fn blocking_read_continues_sample_numbers() {
+	let backend = simulated(SimulatedDevice {
+		analog: vec![Waveform::Constant(0.25)],
+		..Default::default()
+	});
+
+	let mut ai_chan = AiChannel::with_backend(backend, "Dev1", "", "ai0", SAMPLE_RATE).unwrap();
+	ai_chan.launch_task().unwrap();
+
+	let timeout = std::time::Duration::from_secs(1);
+	let first = ai_chan.read(25, timeout).unwrap();
+	assert_eq!(first.len(), 25);
+	assert_eq!(first[24].sample, 25);
+	assert_eq!(*first[0].data, [0.25]);
+
+	let numbers: Vec<_> = ai_chan
+		.batches(10, timeout)
+		.take(2)
+		.flat_map(|batch| batch.unwrap())
+		.map(|scan| scan.sample)
+		.collect();
+	assert_eq!(numbers, (26..=45).collect::<Vec<_>>());
+}
+
+#[test]
+fn blocking_read_times_out() {
+	let backend = simulated(SimulatedDevice::default());
+
+	// Never launched, so nothing is ever acquired
+	let mut encoder_chan =
+		CiEncoderChannel::with_backend(backend, "Dev1", SAMPLE_RATE, EncoderConfig::default())
+			.unwrap();
+
+	let timeout = std::time::Duration::from_millis(20);
+	let err = encoder_chan.read(10, timeout).err().unwrap();
+	assert_eq!(err.call, "DAQmxReadCounterU32");
+
+	let mut batches = encoder_chan.batches(10, timeout);
+	assert!(batches.next().unwrap().is_err());
+	assert!(batches.next().is_none());
+}
+
+#[test]
+fn finite_stream_ends_when_done() {
+	let backend = simulated(SimulatedDevice::default());
+
+	// Not a multiple of the callback batch size, the last few samples come with the done event
+	let mut ai_stream = AiChannel::builder("Dev1", "", SAMPLE_RATE)
+		.backend(backend)
+		.channel(AiVoltageChannel::new("ai0"))
+		.sample_mode(SampleMode::Finite(25))
+		.build()
+		.unwrap()
+		.make_async()
+		.unwrap();
+	ai_stream.launch_task().unwrap();
+
+	let numbers: Vec<_> = block_on_stream(ai_stream).map(|scan| scan.unwrap().sample).collect();
+	assert_eq!(numbers, (1..=25).collect::<Vec<_>>());
+}
+
+#[test]
+fn finite_stream_relaunches() {
+	let backend = simulated(SimulatedDevice::default());
+
+	let mut ai_stream = AiChannel::builder("Dev1", "", SAMPLE_RATE)
+		.backend(backend)
+		.channel(AiVoltageChannel::new("ai0"))
+		.sample_mode(SampleMode::Finite(5))
+		.build()
+		.unwrap()
+		.make_async()
+		.unwrap();
+
+	// The callbacks outlive the first run, and every run gets a fresh queue
+	for _ in 0..3 {
+		ai_stream.launch_task().unwrap();
+
+		let numbers: Vec<_> = block_on_stream(&mut ai_stream)
+			.map(|scan| scan.unwrap().sample)
+			.collect();
+		assert_eq!(numbers, (1..=5).collect::<Vec<_>>());
+	}
+
+	// Relaunched before the last run was drained, its leftovers are dropped
+	ai_stream.launch_task().unwrap();
+	let first = block_on(ai_stream.next()).unwrap().unwrap();
+	assert_eq!(first.sample, 1);
+	ai_stream.launch_task().unwrap();
+
+	let numbers: Vec<_> = block_on_stream(&mut ai_stream)
+		.map(|scan| scan.unwrap().sample)
+		.collect();
+	assert_eq!(numbers, (1..=5).collect::<Vec<_>>());
+}
+
+#[test]
+fn continuous_stream_stops() {
+	let backend = simulated(SimulatedDevice::default());
+
+	let mut ai_chan = AiChannel::with_backend(backend, "Dev1", "", "ai0", SAMPLE_RATE).unwrap();
+	ai_chan.verify_task().unwrap();
+
+	let mut ai_stream = ai_chan.make_async().unwrap();
+	ai_stream.commit_task().unwrap();
+
+	for _ in 0..2 {
+		ai_stream.launch_task().unwrap();
+		let first = block_on(ai_stream.next()).unwrap().unwrap();
+		assert_eq!(first.sample, 1);
+
+		// Ends after whatever was queued before the stop, without an error
+		ai_stream.stop_task().unwrap();
+		let rest: Vec<_> = block_on_stream(&mut ai_stream).collect();
+		assert!(rest.iter().all(|scan| scan.is_ok()));
+	}
+}
+
+#[test]
+fn finite_acquire_returns_whole_capture() {
+	let backend = simulated(SimulatedDevice::default());
+	let config = EncoderConfig {
+		setup: CiSetup {
+			sample_mode: SampleMode::Finite(37),
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	let mut encoder_chan =
+		CiEncoderChannel::with_backend(backend, "Dev1", SAMPLE_RATE, config).unwrap();
+
+	let readings = encoder_chan.acquire().unwrap();
+	assert_eq!(readings.len(), 37);
+	assert_eq!(readings[36].sample, 37);
+
+	// Nothing left to read
+	let timeout = std::time::Duration::from_millis(20);
+	assert!(encoder_chan.read_to_end(timeout).unwrap().is_empty());
+}
+
+#[test]
+fn ao_static_voltages() {
+	let backend = simulated(SimulatedDevice::default());
+
+	let mut ao_chan = AoChannel::with_backend(Backend::clone(&backend), "Dev1", "ao0:1").unwrap();
+	assert_eq!(ao_chan.num_channels(), 2);
+	ao_chan.write_static(&[1.0, -2.0]).unwrap();
+	assert!(ao_chan.write_static(&[1.0]).is_err());
+
+	let mut excitation = AoChannel::builder("Dev1")
+		.backend(Backend::clone(&backend))
+		.channel(AoVoltageChannel::new("ao0").range(0.0, 5.0))
+		.build()
+		.unwrap();
+	excitation.write_static(&[5.0]).unwrap();
+	let err = excitation.write_static(&[6.0]).err().unwrap();
+	assert_eq!(err.code, nidaqmx_sys::DAQmxErrorInvalidAODataWrite);
+
+	assert!(AoChannel::with_backend(backend, "Dev1", "ao2").is_err());
+}
+
+#[test]
+fn ao_waveforms() {
+	let sine = AoWaveform::Sine {
+		amplitude: 2.0,
+		frequency: 100.0,
+		offset: 1.0,
+	};
+	let square = AoWaveform::Square {
+		amplitude: 1.0,
+		frequency: 250.0,
+		offset: 0.0,
+		duty_cycle: 0.5,
+	};
+
+	// Whole periods at 1 kHz
+	assert_eq!(sine.period_len(SAMPLE_RATE).unwrap(), 10);
+	assert!((sine.sample(10, SAMPLE_RATE) - 1.0).abs() < 1e-9);
+	assert!((sine.sample(2, SAMPLE_RATE) - sine.sample(12, SAMPLE_RATE)).abs() < 1e-9);
+	let square_period: Vec<_> = (0..4).map(|ind| square.sample(ind, SAMPLE_RATE)).collect();
+	assert_eq!(square_period, [1.0, 1.0, -1.0, -1.0]);
+
+	let backend = simulated(SimulatedDevice::default());
+
+	let mut shaker = AoChannel::builder("Dev1")
+		.backend(Backend::clone(&backend))
+		.channel(AoVoltageChannel::new("ao0:1"))
+		.sample_clock("", SAMPLE_RATE)
+		.build()
+		.unwrap();
+	assert!(shaker.generate(std::slice::from_ref(&sine)).is_err());
+	shaker.generate(&[sine.clone(), AoWaveform::Arbitrary(vec![0.0, 0.5, 1.0])]).unwrap();
+
+	// Frequencies that can't be generated, and periods whose common multiple is too long
+	let still = AoWaveform::Sine {
+		amplitude: 1.0,
+		frequency: 0.0,
+		offset: 0.0,
+	};
+	assert!(still.period_len(SAMPLE_RATE).is_err());
+	assert!(shaker.generate(&[still.clone(), sine.clone()]).is_err());
+	let coprime = [AoWaveform::Arbitrary(vec![0.0; 4999]), AoWaveform::Arbitrary(vec![0.0; 4993])];
+	assert!(shaker.generate(&coprime).is_err());
+
+	let mut streamed = AoChannel::builder("Dev1")
+		.backend(Backend::clone(&backend))
+		.channel(AoVoltageChannel::new("ao0"))
+		.sample_clock("", SAMPLE_RATE)
+		.build()
+		.unwrap();
+	let timeout = std::time::Duration::from_secs(1);
+	assert_eq!(streamed.write_waveforms(&[square], 100, timeout).unwrap(), 100);
+	assert!(streamed.write_waveforms(&[still], 100, timeout).is_err());
+
+	// Waveforms need a sample clock
+	let mut on_demand = AoChannel::with_backend(backend, "Dev1", "ao0").unwrap();
+	assert!(on_demand.generate(&[sine]).is_err());
+}
+
+#[test]
+fn digital_loopback() {
+	let backend = simulated(SimulatedDevice {
+		digital: vec![Waveform::Constant(0.0)],
+		..Default::default()
+	});
+
+	let mut outputs =
+		DoChannel::with_backend(Backend::clone(&backend), "Dev1", "port0/line0:1").unwrap();
+	let mut inputs =
+		DiChannel::with_backend(Backend::clone(&backend), "Dev1", "port0/line0:2").unwrap();
+	assert_eq!(inputs.num_channels(), 3);
+	assert_eq!(inputs.read_lines().unwrap(), [false, false, false]);
+
+	outputs.write_lines(&[true, false]).unwrap();
+	assert_eq!(inputs.read_lines().unwrap(), [true, false, false]);
+	assert!(outputs.write_lines(&[true]).is_err());
+
+	// Streaming needs a sample clock
+	assert!(outputs.write(&[true, true], std::time::Duration::from_secs(1)).is_err());
+	assert!(inputs.make_async().is_err());
+	assert!(DiChannel::with_backend(backend, "Dev1", "port0/line8").is_err());
+}
+
+#[test]
+fn digital_stream() {
+	let backend = simulated(SimulatedDevice {
+		digital: vec![Waveform::Constant(1.0), Waveform::Constant(0.0)],
+		..Default::default()
+	});
+
+	let di_chan = DiChannel::builder("Dev1", "port0/line0:2")
+		.backend(backend)
+		.sample_clock("", SAMPLE_RATE)
+		.sample_mode(SampleMode::Finite(25))
+		.build()
+		.unwrap();
+	let mut di_stream = di_chan.make_async().unwrap();
+	di_stream.launch_task().unwrap();
+
+	let scans: Vec<_> = block_on_stream(di_stream).map(Result::unwrap).collect();
+
+	assert_eq!(scans.len(), 25);
+	assert_eq!(scans[24].sample, 25);
+	assert_eq!(*scans[0].data, [true, false, true]);
+	assert_eq!(scans[0].to_string(), "1,0,1,0,1");
+}
+
+#[test]
+fn force_bridge_scales() {
+	// Bridge outputs of 1 and -0.5 mV/V
+	let backend = simulated(SimulatedDevice {
+		analog: vec![Waveform::Constant(1.0), Waveform::Constant(-0.5)],
+		..Default::default()
+	});
+
+	let mut ai_chan = AiChannel::builder("Dev1", "", SAMPLE_RATE)
+		.backend(Backend::clone(&backend))
+		.channel(ForceBridgeChannel::two_point_lin("ai0", (0.0, 0.0), (2.0, 10.0)))
+		.channel(
+			ForceBridgeChannel::polynomial("ai1", vec![1.0, 2.0, 4.0], vec![])
+				.units(ForceUnits::KilogramForce),
+		)
+		.channel(ForceBridgeChannel::table("ai0", vec![0.0, 0.5, 2.0], vec![0.0, 1.0, 2.0]))
+		.channel(AiVoltageChannel::new("ai1"))
+		.build()
+		.unwrap();
+	ai_chan.launch_task().unwrap();
+
+	let scans = ai_chan.read(1, std::time::Duration::from_secs(1)).unwrap();
+	assert_eq!(*scans[0].data, [5.0, 1.0, 4.0 / 3.0, -0.5]);
+
+	let table_mismatch = AiChannel::builder("Dev1", "", SAMPLE_RATE)
+		.backend(backend)
+		.channel(ForceBridgeChannel::table("ai0", vec![0.0, 1.0], vec![0.0]))
+		.build();
+	assert!(table_mismatch.is_err());
+}
+
+#[test]
+fn accel_coupling_and_units() {
+	// 2 g of vibration on top of a 1 g bias
+	let backend = simulated(SimulatedDevice {
+		analog: vec![Waveform::Sine {
+			amplitude: 2.0,
+			frequency: 50.0,
+			offset: 1.0,
+		}],
+		..Default::default()
+	});
+
+	let ai_chan = AiChannel::builder("Dev1", "", SAMPLE_RATE)
+		.backend(backend)
+		.channel(AccelChannel::new("ai0").name("blade"))
+		.channel(AccelChannel::new("ai1").coupling(Coupling::Dc))
+		.channel(
+			AccelChannel::new("ai2")
+				.units(AccelUnits::MetersPerSecondSquared)
+				.range(-100.0, 100.0),
+		)
+		// Named ranges are numbered like DAQmx does, wing0 and wing1
+		.channel(AccelChannel::new("ai3:4").name("wing"))
+		.channel(AccelChannel::new("ai5:6").coupling(Coupling::Dc))
+		.build()
+		.unwrap();
+	let mut accel_stream = ai_chan.make_async().unwrap();
+	accel_stream.launch_task().unwrap();
+
+	let scans = block_on(accel_stream.take(100).try_collect::<Vec<_>>())
+		.unwrap_or_else(|_| panic!("stream failed"));
+
+	let mean = |chan: usize| scans.iter().map(|scan| scan.data[chan]).sum::<f64>() / 100.0;
+	assert!(mean(0).abs() < 1e-6);
+	assert!((mean(1) - 1.0).abs() < 1e-6);
+	assert!(mean(2).abs() < 1e-6);
+	assert!(mean(3).abs() < 1e-6 && mean(4).abs() < 1e-6);
+	assert!((mean(5) - 1.0).abs() < 1e-6 && (mean(6) - 1.0).abs() < 1e-6);
+	let peak = scans.iter().map(|scan| scan.data[2]).fold(0.0, f64::max);
+	assert!((peak - 2.0 * 9.80665).abs() < 1e-6);
+}
+
+#[test]
+fn counter_input_family() {
+	let backend = simulated(SimulatedDevice {
+		encoder: Waveform::Ramp {
+			start: 0.0,
+			end: 2.0,
+			period: 1.0,
+		},
+		linear: Waveform::Constant(0.123456),
+		..Default::default()
+	});
+	let finite = |counter_id, clk_src_counter_id| CiSetup {
+		counter_id,
+		clk_src_counter_id,
+		sample_mode: SampleMode::Finite(10),
+		..Default::default()
+	};
+
+	let velocity_config = AngVelocityConfig {
+		setup: finite(0, 1),
+		..Default::default()
+	};
+	let mut velocity_chan = CiAngVelocityChannel::with_backend(
+		Backend::clone(&backend),
+		"Dev1",
+		SAMPLE_RATE,
+		velocity_config,
+	)
+	.unwrap();
+	let velocities = velocity_chan.acquire().unwrap();
+	assert_eq!(velocities.len(), 10);
+	assert!(velocities.iter().all(|reading| (reading.velocity - 120.0).abs() < 1e-9));
+
+	// 2.5 um per tick with X4 decoding
+	let slider_config = LinEncoderConfig {
+		setup: finite(2, 3),
+		..Default::default()
+	};
+	let mut slider_stream =
+		CiLinEncoderChannel::with_backend(backend, "Dev1", SAMPLE_RATE, slider_config)
+			.unwrap()
+			.make_async()
+			.unwrap();
+	slider_stream.launch_task().unwrap();
+
+	let positions: Vec<_> = block_on_stream(slider_stream).map(Result::unwrap).collect();
+	assert_eq!(positions.len(), 10);
+	assert_eq!(positions[9].sample, 10);
+	assert!((positions[0].pos - 0.123455).abs() < 1e-9);
+}
+
+#[test]
+fn pulse_counter_inputs() {
+	// 100 Hz pulse train
+	let backend = simulated(SimulatedDevice {
+		pulses: Waveform::Ramp {
+			start: 0.0,
+			end: 1000.0,
+			period: 10.0,
+		},
+		..Default::default()
+	});
+	let finite = |counter_id, clk_src_counter_id| CiSetup {
+		counter_id,
+		clk_src_counter_id,
+		sample_mode: SampleMode::Finite(20),
+		..Default::default()
+	};
+
+	let flow_config = CountEdgesConfig {
+		direction: CountDirection::Down,
+		setup: finite(0, 1),
+		..Default::default()
+	};
+	let mut flow_chan = CiCountEdgesChannel::with_backend(
+		Backend::clone(&backend),
+		"Dev1",
+		SAMPLE_RATE,
+		flow_config,
+	)
+	.unwrap();
+	let counts = flow_chan.acquire().unwrap();
+	assert_eq!(counts.len(), 20);
+	assert_eq!(counts[0].count, 0);
+	assert_eq!(counts[19].count, u32::MAX);	// Counted down past zero
+
+	let tach_config = FreqConfig {
+		setup: finite(0, 1),
+		..Default::default()
+	};
+	let mut tach_stream =
+		CiFreqChannel::with_backend(Backend::clone(&backend), "Dev1", SAMPLE_RATE, tach_config)
+			.unwrap()
+			.make_async()
+			.unwrap();
+	tach_stream.launch_task().unwrap();
+
+	let freqs: Vec<_> = block_on_stream(tach_stream).map(Result::unwrap).collect();
+	assert_eq!(freqs.len(), 20);
+	assert!(freqs.iter().all(|reading| (reading.freq - 100.0).abs() < 1e-9));
+
+	let period_config = PeriodConfig {
+		setup: finite(2, 3),
+		..Default::default()
+	};
+	let mut period_chan =
+		CiPeriodChannel::with_backend(backend, "Dev1", SAMPLE_RATE, period_config).unwrap();
+	let periods = period_chan.acquire().unwrap();
+	assert_eq!(periods.len(), 20);
+	assert!((periods[19].period - 0.01).abs() < 1e-12);
+}
+
+#[test]
+fn device_removal_ends_streams() {
+	let backend = simulated(SimulatedDevice {
+		removed_after: Some(0.1),
+		..Default::default()
+	});
+
+	let ai_chan =
+		AiChannel::with_backend(Backend::clone(&backend), "Dev1", "", "ai0", SAMPLE_RATE).unwrap();
+	let mut ai_stream = ai_chan.make_async().unwrap();
+	ai_stream.launch_task().unwrap();
+
+	let encoder_chan =
+		CiEncoderChannel::with_backend(backend, "Dev1", SAMPLE_RATE, EncoderConfig::default())
+			.unwrap();
+	let mut encoder_stream = encoder_chan.make_async().unwrap();
+	encoder_stream.launch_task().unwrap();
+
+	// The samples acquired before the device went away come first, then the reason it stopped
+	let scans: Vec<_> = block_on_stream(ai_stream).collect();
+	assert_eq!(scans.len(), 101);
+	assert!(scans[..100].iter().all(Result::is_ok));
+	let err = scans[100].as_ref().unwrap_err();
+	assert_eq!(err.code, nidaqmx_sys::DAQmxErrorDeviceRemoved);
+	assert_eq!(err.call, "DAQmxDoneEvent");
+
+	let readings: Vec<_> = block_on_stream(encoder_stream).collect();
+	assert_eq!(readings.len(), 101);
+	assert_eq!(readings[100].as_ref().err().map(|err| err.code), Some(err.code));
+}
+
+#[test]
+fn back_to_back_trials() {
+	let backend = simulated(SimulatedDevice::default());
+	let config = EncoderConfig {
+		setup: CiSetup {
+			sample_mode: SampleMode::Finite(20),
+			..Default::default()
+		},
+		..Default::default()
+	};
+
+	let mut encoder_chan =
+		CiEncoderChannel::with_backend(Backend::clone(&backend), "Dev1", SAMPLE_RATE, config)
+			.unwrap();
+	encoder_chan.verify_task().unwrap();
+	encoder_chan.commit_task().unwrap();
+
+	// The same task is relaunched without setting it up again
+	for _ in 0..2 {
+		let readings = encoder_chan.acquire().unwrap();
+		assert_eq!(readings.len(), 20);
+		assert_eq!(readings[19].sample, 20);
+		assert!(encoder_chan.is_task_done().unwrap());
+
+		encoder_chan.stop_task().unwrap();
+	}
+
+	// Nothing to check or commit without channels
+	let mut task_handle = task_handle::TaskHandle::with_backend(backend).unwrap();
+	assert!(task_handle.verify().is_err());
+	assert!(task_handle.commit().is_err());
+	assert!(task_handle.is_done().unwrap());
+}
+
+#[test]
+fn error_info_per_thread() {
+	let backend = simulated(SimulatedDevice::default());
+
+	assert!(reset_device(&*backend, "Dev2").is_err());
+
+	let err = reset_device(&*backend, "Dev1\0").unwrap_err();
+	assert_eq!(err.code, nidaqmx_sys::DAQmxErrorInvalidCharInString);
+
+	// A call failing on another thread meanwhile doesn't replace this thread's error
+	let other = Backend::clone(&backend);
+	std::thread::spawn(move || assert!(reset_device(&*other, "Dev3").is_err()))
+		.join()
+		.unwrap();
+
+	assert!(backend.extended_error_info().contains("'Dev2'"));
+}
+
+#[test]
+fn hardware_check() {
+	let backend = simulated(SimulatedDevice::default());
+
+	// Resetting aborts whatever is still running on the device
+	let mut encoder_chan = CiEncoderChannel::with_backend(
+		Backend::clone(&backend),
+		"Dev1",
+		SAMPLE_RATE,
+		EncoderConfig::default(),
+	)
+	.unwrap();
+	encoder_chan.launch_task().unwrap();
+	assert!(!encoder_chan.is_task_done().unwrap());
+	reset_device(&*backend, "Dev1").unwrap();
+	assert!(encoder_chan.is_task_done().unwrap());
+
+	self_test_device(&*backend, "Dev1").unwrap();
+	assert!(reset_device(&*backend, "Dev2").is_err());
+
+	let err = reset_device(&*backend, "Dev1\0").unwrap_err();
+	assert_eq!(err.code, nidaqmx_sys::DAQmxErrorInvalidCharInString);
+
+	let calibration = CalibrationInfo::query(&*backend, "Dev1").unwrap();
+	assert_eq!(calibration, SimulatedDevice::default().calibration);
+	assert_eq!(calibration.external.date.to_string(), "2019-03-14 09:30");
+
+	// A bare bones device without self-calibration or a temperature sensor
+	let backend = simulated(SimulatedDevice {
+		calibration: CalibrationInfo {
+			self_cal: None,
+			temperature: None,
+			..SimulatedDevice::default().calibration
+		},
+		fails_self_test: true,
+		..Default::default()
+	});
+
+	let err = self_test_device(&*backend, "Dev1").unwrap_err();
+	assert_eq!(err.code, nidaqmx_sys::DAQmxErrorSelfTestFailed);
+
+	let calibration = CalibrationInfo::query(&*backend, "Dev1").unwrap();
+	assert_eq!(calibration.self_cal, None);
+	assert_eq!(calibration.temperature, None);
+}
+
+#[test]
+fn analog_triggers() {
+	let backend = simulated(SimulatedDevice {
+		analog: vec![Waveform::Sine {
+			amplitude: 1.0,
+			frequency: 5.0,
+			offset: 0.0,
+		}],
+		..Default::default()
+	});
+	let timeout = std::time::Duration::from_secs(2);
+	let builder = || {
+		AiChannel::builder("Dev1", "", SAMPLE_RATE)
+			.backend(Backend::clone(&backend))
+			.channel(AiVoltageChannel::new("ai0").name("jaw"))
+	};
+
+	let mut ai_chan = builder()
+		.reference_trigger(ReferenceTrigger {
+			trigger: AnalogTrigger::edge("jaw", Slope::Rising, 0.5),
+			pretrigger_samples: 50,
+			posttrigger_samples: 100,
+		})
+		.build()
+		.unwrap();
+
+	let capture = ai_chan.capture(timeout).unwrap();
+	assert_eq!(capture.scans.len(), 150);
+	assert_eq!(capture.pretrigger().len(), 50);
+	assert_eq!(capture.posttrigger()[0].sample, 51);
+	assert!(capture.pretrigger()[49].data[0] < 0.5);
+	assert!(capture.posttrigger()[0].data[0] >= 0.5);
+
+	let mut ai_chan = builder()
+		.reference_trigger(ReferenceTrigger {
+			trigger: AnalogTrigger::window("jaw", WindowCondition::Leaving, -0.5, 0.5),
+			pretrigger_samples: 10,
+			posttrigger_samples: 10,
+		})
+		.build()
+		.unwrap();
+
+	let capture = ai_chan.capture(timeout).unwrap();
+	assert_eq!(capture.trigger_index, 10);
+	assert!(capture.scans[9].data[0].abs() <= 0.5);
+	assert!(capture.scans[10].data[0].abs() > 0.5);
+
+	// Acquisition starts on the trigger sample
+	let mut ai_chan = builder()
+		.analog_start_trigger(AnalogTrigger::edge("jaw", Slope::Falling, -0.5))
+		.sample_mode(SampleMode::Finite(20))
+		.build()
+		.unwrap();
+
+	let scans = ai_chan.acquire().unwrap();
+	assert!(scans[0].data[0] <= -0.5);
+	assert!(scans[1].data[0] < scans[0].data[0]);
+
+	assert!(builder()
+		.analog_start_trigger(AnalogTrigger::edge("Dev1/ai1", Slope::Rising, 0.0))
+		.build()
+		.is_err());
+
+	// The channels of a named range go by numbered names only
+	let range = |source| {
+		AiChannel::builder("Dev1", "", SAMPLE_RATE)
+			.backend(Backend::clone(&backend))
+			.channel(AiVoltageChannel::new("ai0:1").name("jaws"))
+			.analog_start_trigger(AnalogTrigger::edge(source, Slope::Falling, -0.5))
+			.sample_mode(SampleMode::Finite(20))
+			.build()
+	};
+	assert!(range("jaws").is_err());
+	let scans = range("jaws1").unwrap().acquire().unwrap();
+	assert!(scans[0].data[1] <= -0.5);
+	let err = builder().build().unwrap().capture(timeout).unwrap_err();
+	assert_eq!(err.code, nidaqmx_sys::DAQmxErrorNoRefTrigConfigured);
+}
+
+#[test]
+fn input_buffer_overwrite_and_backlog() {
+	// Reads back the acquisition time [s], so sample numbers are easy to check
+	let backend = simulated(SimulatedDevice {
+		analog: vec![Waveform::Ramp {
+			start: 0.0,
+			end: 100.0,
+			period: 100.0,
+		}],
+		..Default::default()
+	});
+	let timeout = std::time::Duration::from_secs(1);
+	let new_chan = || {
+		let mut ai_chan =
+			AiChannel::with_backend(Backend::clone(&backend), "Dev1", "", "ai0", SAMPLE_RATE)
+				.unwrap();
+		ai_chan.set_buffer_size(50).unwrap();
+		ai_chan
+	};
+
+	let mut ai_chan = new_chan();
+	ai_chan.set_overwrite_mode(OverwriteMode::Overwrite).unwrap();
+	ai_chan.launch_task().unwrap();
+	std::thread::sleep(std::time::Duration::from_millis(150));
+
+	let status = ai_chan.buffer_status().unwrap();
+	assert_eq!(status.size, 50);
+	assert_eq!(status.available, 50);
+	assert!(status.total_acquired >= 100);
+	assert_eq!(status.fill(), 1.0);
+
+	ai_chan.set_read_position(ReadPosition::most_recent(10)).unwrap();
+	let scans = ai_chan.read(10, timeout).unwrap();
+	let first = (scans[0].data[0] * SAMPLE_RATE as f64).round() as u64;
+	let last = (scans[9].data[0] * SAMPLE_RATE as f64).round() as u64;
+	assert_eq!(last - first, 9);
+	assert!(last + 1 >= status.total_acquired);
+
+	// Without overwriting, the overflow fails the next read
+	let mut ai_chan = new_chan();
+	ai_chan.launch_task().unwrap();
+	std::thread::sleep(std::time::Duration::from_millis(150));
+
+	let err = ai_chan.read(10, timeout).unwrap_err();
+	assert_eq!(err.code, nidaqmx_sys::DAQmxErrorSamplesNoLongerAvailable);
+
+	// Nobody polls the stream, so it falls behind
+	let mut ai_stream = AiChannel::with_backend(backend, "Dev1", "", "ai0", SAMPLE_RATE)
+		.unwrap()
+		.make_async()
+		.unwrap();
+	let stats = ai_stream.stats();
+	ai_stream.launch_task().unwrap();
+	std::thread::sleep(std::time::Duration::from_millis(100));
+
+	assert!(!stats.is_empty());
+	assert!(ai_stream.backlog() >= 50);
+}
+
+#[test]
+fn samples_timed_by_coerced_clock() {
+	let backend = simulated(SimulatedDevice::default());
+
+	// The 100 MHz timebase can't be divided down to exactly 3 kHz
+	let mut ai_chan = AiChannel::with_backend(backend, "Dev1", "", "ai0", 3000).unwrap();
+	let time_axis = ai_chan.time_axis();
+	assert_eq!(time_axis.rate, 100e6 / 33333.0);
+	assert_eq!(time_axis.start, None);
+
+	ai_chan.launch_task().unwrap();
+	let scans = ai_chan.read(30, std::time::Duration::from_secs(1)).unwrap();
+	let time_axis = ai_chan.time_axis();
+	let start = time_axis.start.unwrap();
+
+	assert_eq!(scans[0].sample, 1);
+	assert_eq!(scans[0].time, 0.0);
+	assert_eq!(scans[29].time, 29.0 / time_axis.rate);
+	assert_eq!(time_axis.host_time(1), Some(start));
+	assert_eq!(time_axis.host_time(time_axis.rate as u64 + 1).unwrap() - start, 999_990_000);
+
+	// Counter inputs are clocked by the counter output routed to PFI13, which is coerced the same
+	let encoder_chan = CiEncoderChannel::with_backend(
+		simulated(SimulatedDevice::default()),
+		"Dev1",
+		3000,
+		EncoderConfig::default(),
+	)
+	.unwrap();
+	assert_eq!(encoder_chan.time_axis().rate, 100e6 / 33333.0);
+}
+
+#[test]
+fn host_clocks() {
+	let first = monotonic_ns();
+	std::thread::sleep(std::time::Duration::from_millis(5));
+	assert!(monotonic_ns() - first >= 5_000_000);
+
+	let system = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap()
+		.as_nanos() as u64;
+	let utc = utc_ns();
+	assert!(utc.max(system) - utc.min(system) < 1_000_000_000);
+
+	// The TIMEPOINT counts 100 ns ticks from 1601, 11644473600 s before the Unix epoch
+	let timepoint_ns = (TIMEPOINT / 10_000_000 - 11_644_473_600) * 1_000_000_000;
+	let since_timepoint = get_steady_time_nanoseconds();
+	let utc = utc_ns();
+	assert!(utc - since_timepoint - timepoint_ns < 1_000_000_000);
+}
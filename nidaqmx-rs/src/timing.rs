@@ -0,0 +1,59 @@
+use super::error::DaqError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleMode {
+	// Acquire until the task is dropped
+	Continuous,
+	// Acquire this many samples per channel, then stop
+	Finite(u64),
+}
+
+impl SampleMode {
+	// DAQmx sample mode and samples per channel, which is the buffer size for continuous tasks
+	pub fn as_daqmx(self, buf_size: u64) -> (i32, u64) {
+		match self {
+			SampleMode::Continuous => (nidaqmx_sys::DAQmx_Val_ContSamps as i32, buf_size),
+			SampleMode::Finite(n_samps) => (nidaqmx_sys::DAQmx_Val_FiniteSamps as i32, n_samps),
+		}
+	}
+
+	// Length of a finite acquisition, continuous ones have no end to wait for
+	pub fn finite_samps(self) -> Result<u64, DaqError> {
+		match self {
+			SampleMode::Finite(n_samps) => Ok(n_samps),
+			SampleMode::Continuous => Err(DaqError::new(
+				nidaqmx_sys::DAQmxErrorInvalidTimingType,
+				"Only finite acquisitions have an end to read to.",
+				"read_to_end",
+			)),
+		}
+	}
+}
+
+// Turns sample numbers into time, using the rate the sample clock really runs at rather than the
+// one asked for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeAxis {
+	// DAQmx coerces the requested rate to one its timebase can divide down to [Hz]
+	pub rate: f64,
+	// Host time the task was launched at [ns since the TIMEPOINT], None until it is. Tasks
+	// waiting on a start trigger take their first sample later than this.
+	pub start: Option<u64>,
+}
+
+impl TimeAxis {
+	pub fn new(rate: f64) -> Self {
+		TimeAxis { rate, start: None }
+	}
+
+	// Time of `sample` relative to the first one (sample 1) [s]
+	pub fn seconds(&self, sample: u64) -> f64 {
+		sample.saturating_sub(1) as f64 / self.rate
+	}
+
+	// Host time of `sample` [ns since the TIMEPOINT], extrapolated from the launch
+	pub fn host_time(&self, sample: u64) -> Option<u64> {
+		self.start
+			.map(|start| start + (self.seconds(sample) * 1e9).round() as u64)
+	}
+}
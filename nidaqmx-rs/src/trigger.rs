@@ -0,0 +1,159 @@
+use super::{error::DaqError, task_control::TaskControl, terminal_desc};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+	Rising,
+	Falling,
+}
+
+impl Edge {
+	pub fn as_daqmx(self) -> i32 {
+		match self {
+			Edge::Rising => nidaqmx_sys::DAQmx_Val_Rising as i32,
+			Edge::Falling => nidaqmx_sys::DAQmx_Val_Falling as i32,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerSource {
+	// External signal on a PFI line
+	Pfi(u8),
+	// Internal output of a counter, e.g. the one generating a shared sample clock
+	CounterOutput(u8),
+}
+
+impl TriggerSource {
+	pub fn terminal_desc(self, device: &str) -> String {
+		match self {
+			TriggerSource::Pfi(pfi_id) => terminal_desc(device, &format!("PFI{}", pfi_id)),
+			TriggerSource::CounterOutput(counter_id) => {
+				terminal_desc(device, &format!("Ctr{}InternalOutput", counter_id))
+			}
+		}
+	}
+}
+
+// Tasks with a start trigger wait for its edge after being launched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StartTrigger {
+	pub source: TriggerSource,
+	pub edge: Edge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slope {
+	Rising,
+	Falling,
+}
+
+impl Slope {
+	pub fn as_daqmx(self) -> i32 {
+		match self {
+			Slope::Rising => nidaqmx_sys::DAQmx_Val_RisingSlope as i32,
+			Slope::Falling => nidaqmx_sys::DAQmx_Val_FallingSlope as i32,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowCondition {
+	Entering,
+	Leaving,
+}
+
+impl WindowCondition {
+	pub fn as_daqmx(self) -> i32 {
+		match self {
+			WindowCondition::Entering => nidaqmx_sys::DAQmx_Val_EnteringWin as i32,
+			WindowCondition::Leaving => nidaqmx_sys::DAQmx_Val_LeavingWin as i32,
+		}
+	}
+}
+
+// Levels are in the units of the source channel, e.g. newtons for a force bridge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnalogCondition {
+	// The signal crosses `level` in the direction of `slope`
+	Edge { slope: Slope, level: f64 },
+	// The signal enters or leaves the range [bottom, top]
+	Window {
+		when: WindowCondition,
+		bottom: f64,
+		top: f64,
+	},
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalogTrigger {
+	// A single channel of the task by its name, e.g. "grasperLoadCell1" or "Dev1/ai0" if it has
+	// none, or an analog trigger terminal like "/Dev1/APFI0". DAQmx numbers the channels of a
+	// named range, so "jaw" over ai0:1 is triggered on as "jaw0" or "jaw1".
+	pub source: String,
+	pub condition: AnalogCondition,
+}
+
+impl AnalogTrigger {
+	pub fn edge<S: Into<String>>(source: S, slope: Slope, level: f64) -> Self {
+		AnalogTrigger {
+			source: source.into(),
+			condition: AnalogCondition::Edge { slope, level },
+		}
+	}
+
+	pub fn window<S: Into<String>>(source: S, when: WindowCondition, bottom: f64, top: f64) -> Self {
+		AnalogTrigger {
+			source: source.into(),
+			condition: AnalogCondition::Window { when, bottom, top },
+		}
+	}
+}
+
+// A finite acquisition that keeps the last `pretrigger_samples` before the trigger and stops
+// `posttrigger_samples` after it, counting the sample the trigger occured on
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceTrigger {
+	pub trigger: AnalogTrigger,
+	pub pretrigger_samples: u64,
+	pub posttrigger_samples: u64,
+}
+
+// A task that can be launched ahead of a start trigger it shares with other tasks
+pub trait Arm: TaskControl {
+	// Starts the task, it then waits for its start trigger (if any) before acquiring
+	fn arm(&mut self) -> Result<(), DaqError>;
+
+	// Starts whatever this task owns that generates the trigger edge, e.g. its sample clock
+	fn fire(&mut self) -> Result<(), DaqError> {
+		Ok(())
+	}
+}
+
+// Arms every task before firing any of them, so tasks sharing a start trigger all start on the
+// same hardware edge. If one fails, the tasks armed so far are stopped again, so none of them is
+// left waiting on an edge or acquiring on its own.
+pub fn launch_synchronized(tasks: &mut [&mut dyn Arm]) -> Result<(), DaqError> {
+	for ind in 0..tasks.len() {
+		if let Err(err) = tasks[ind].arm() {
+			// The failed task may have started before it failed
+			stop_all(&mut tasks[..=ind]);
+			return Err(err);
+		}
+	}
+
+	for ind in 0..tasks.len() {
+		if let Err(err) = tasks[ind].fire() {
+			stop_all(tasks);
+			return Err(err);
+		}
+	}
+
+	Ok(())
+}
+
+// Rolls back a failed launch, the error that made it fail is the one worth reporting
+fn stop_all(tasks: &mut [&mut dyn Arm]) {
+	for task in tasks.iter_mut().rev() {
+		let _ = task.stop_task();
+	}
+}
@@ -1,124 +1,413 @@
 use futures::{
 	future::{self, Future},
-	stream::Stream,
-	sync::oneshot,
-	Poll,
+	stream::{Stream, TryStream, TryStreamExt},
 };
 
-use nidaqmx::{AiChannel, CiEncoderChannel, 
-	// get_steady_time_nanoseconds,
+use nidaqmx::{
+	AiChannel, AiVoltageChannel, AngleUnits, Backend, CalibrationInfo, CiEncoderChannel, CiSetup,
+	DaqBackend, DaqError, DecodingType, DeviceInfo, Edge, EncoderConfig, OverflowPolicy,
+	QueueStats, SampleMode, SimulatedDevice, StartTrigger, StreamChannel, TerminalConfig, TimeAxis,
+	TriggerSource, ZIndex, ZIndexPhase,
 };
 
 use std::{
+	cell::Cell,
+	fmt,
 	fs::{self, File, OpenOptions},
 	io::{BufWriter, Write},
 	marker::Unpin,
 	path::PathBuf,
+	pin::Pin,
 	sync::Arc,
+	task::{Context, Poll},
 	thread,
-	time::Instant,
 };
 
 use atomic::Atomic;
-use tokio_current_thread::CurrentThread;
+use tokio_util::sync::CancellationToken;
 
 use crate::ui;
 
 const SAMPLING_RATE: usize = 1000;
 const DATA_SEND_RATE: usize = 10; // hz
 const UPDATE_UI_SAMP_COUNT: usize = SAMPLING_RATE / DATA_SEND_RATE;
+// Samples each stream may fall behind the DAQ before the oldest ones are dropped
+const QUEUE_CAPACITY: usize = 10 * SAMPLING_RATE;
+
+// Set this environment variable to run the console without a DAQ attached
+const SIMULATE_ENV_VAR: &str = "SCISSORS_SIMULATE";
+// Serial number (hex, as printed on the device) of the DAQ to use when several are installed
+const DAQ_SERIAL_ENV_VAR: &str = "SCISSORS_DAQ_SERIAL";
+
+struct AnalogSensor {
+	physical_channel: &'static str,
+	name: &'static str,
+	min_val: f64, // [V]
+	max_val: f64, // [V]
+	terminal_config: TerminalConfig,
+}
+
+// Recorded in this order, the grasper load cell amplifiers output +-2 V
+const ANALOG_SENSORS: [AnalogSensor; 2] = [
+	AnalogSensor {
+		physical_channel: "ai0",
+		name: "grasperLoadCell1",
+		min_val: -2.0,
+		max_val: 2.0,
+		terminal_config: TerminalConfig::Default,
+	},
+	AnalogSensor {
+		physical_channel: "ai1",
+		name: "grasperLoadCell2",
+		min_val: -2.0,
+		max_val: 2.0,
+		terminal_config: TerminalConfig::Default,
+	},
+];
+
+// AMT102-V CUI encoder on the grasper, DIP switches set to 2048 PPR
+const ENCODER_CONFIG: EncoderConfig = EncoderConfig {
+	decoding: DecodingType::X4,
+	pulses_per_rev: 2048,
+	z_index: Some(ZIndex {
+		value: 0.0,
+		phase: ZIndexPhase::ALowBLow,
+	}),
+	units: AngleUnits::Ticks,
+	initial_angle: 0.0,
+	setup: CiSetup {
+		counter_id: 0,
+		clk_src_counter_id: 1,
+		clk_src_output_pfi_id: 13,
+		start_trigger: Some(START_TRIGGER),
+		sample_mode: SampleMode::Continuous,
+	},
+};
+
+// Both tasks are armed on the encoder's sample clock counter, so they start on its first pulse
+const START_TRIGGER: StartTrigger = StartTrigger {
+	source: TriggerSource::CounterOutput(1),
+	edge: Edge::Rising,
+};
+
+pub enum CollectionError {
+	FileExists(PathBuf),
+	Daq(DaqError),
+	// Serial number that was asked for, None if no device was found at all
+	DeviceNotFound(Option<u32>),
+	AmbiguousDevice(Vec<DeviceInfo>),
+	InvalidSerial(String),
+}
+
+impl fmt::Display for CollectionError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			CollectionError::FileExists(fpath) => {
+				write!(f, "File '{}' already exists", fpath.display())
+			}
+			CollectionError::Daq(err) => write!(f, "DAQ error: {}", err),
+			CollectionError::DeviceNotFound(None) => write!(f, "No DAQ device found"),
+			CollectionError::DeviceNotFound(Some(serial)) => {
+				write!(f, "No DAQ device with serial number {:X} found", serial)
+			}
+			CollectionError::AmbiguousDevice(devices) => {
+				let serials: Vec<_> = devices
+					.iter()
+					.map(|device| format!("{:X}", device.serial_number))
+					.collect();
+				write!(
+					f,
+					"Several DAQ devices found ({}), set {} to pick one",
+					serials.join(", "),
+					DAQ_SERIAL_ENV_VAR
+				)
+			}
+			CollectionError::InvalidSerial(serial) => {
+				write!(
+					f,
+					"{} must be a hex serial number, got '{}'",
+					DAQ_SERIAL_ENV_VAR, serial
+				)
+			}
+		}
+	}
+}
+
+impl From<DaqError> for CollectionError {
+	fn from(err: DaqError) -> Self {
+		CollectionError::Daq(err)
+	}
+}
+
+// Outcome of the last "check hardware" action, noted in the header of the recordings after it
+pub struct HardwareCheck {
+	device: DeviceInfo,
+	time: time::Tm,
+	calibration: CalibrationInfo,
+}
+
+// Resets and self-tests the DAQ, then reads its calibration records. Resetting frees tasks a
+// crashed session left reserved, so it mustn't run during a recording.
+pub fn check_hardware() -> Result<HardwareCheck, CollectionError> {
+	let backend = daq_backend();
+	let device = select_device(&*backend)?;
+
+	nidaqmx::reset_device(&*backend, &device.name)?;
+	log::info!("Reset {}", device.name);
+
+	nidaqmx::self_test_device(&*backend, &device.name)?;
+	log::info!("{} passed its self-test", device.name);
 
-pub fn start(fpath: &mut PathBuf) -> Option<DataCollectionHandle> {
-	let (mut adc_file, mut enc_file) = prepare_files(fpath)?;
+	let calibration = CalibrationInfo::query(&*backend, &device.name)?;
+	log::info!("{}: {}", device.name, calibration);
 
-	let encoder_chan = CiEncoderChannel::new(SAMPLING_RATE);
-	let ai_chan = AiChannel::new("/Dev1/PFI13", "Dev1/ai0:1", SAMPLING_RATE);
+	Ok(HardwareCheck {
+		device,
+		time: time::now(),
+		calibration,
+	})
+}
+
+pub fn start(
+	fpath: &mut PathBuf,
+	hardware_check: Option<&HardwareCheck>,
+) -> Result<DataCollectionHandle, CollectionError> {
+	let backend = daq_backend();
+	let device = select_device(&*backend)?;
+
+	// A check of another DAQ says nothing about this one
+	let hardware_check =
+		hardware_check.filter(|check| check.device.serial_number == device.serial_number);
+
+	let (mut adc_file, mut enc_file) = prepare_files(fpath, &device, hardware_check)
+		.ok_or_else(|| CollectionError::FileExists(fpath.clone()))?;
+
+	let encoder_chan = CiEncoderChannel::with_backend(
+		Backend::clone(&backend),
+		&device.name,
+		SAMPLING_RATE,
+		ENCODER_CONFIG,
+	)?;
+	// The analog inputs are clocked by the encoder's sample clock
+	let ai_clk_src = format!("PFI{}", ENCODER_CONFIG.setup.clk_src_output_pfi_id);
+	let ai_builder = AiChannel::builder(device.name.as_str(), ai_clk_src, SAMPLING_RATE)
+		.backend(backend)
+		.start_trigger(START_TRIGGER);
+	let ai_chan = ANALOG_SENSORS
+		.iter()
+		.fold(ai_builder, |builder, sensor| {
+			builder.channel(
+				AiVoltageChannel::new(sensor.physical_channel)
+					.name(sensor.name)
+					.range(sensor.min_val, sensor.max_val)
+					.terminal_config(sensor.terminal_config),
+			)
+		})
+		.build()?;
 
 	let enc_plot_data = Arc::new(LatestSensorData::new());
 	let adc_plot_data = Arc::clone(&enc_plot_data);
 
 	// Set up the NI tasks
-	let mut encoder_stream = encoder_chan.make_async();
-	let mut ai_stream = ai_chan.make_async();
-	
-	// Launch the tasks, record the time difference
-	encoder_stream.launch_task();
-	let delay = std::time::Instant::now();
-
-	ai_stream.launch_task();
-	let delay = delay.elapsed();
+	let mut encoder_stream =
+		encoder_chan.make_async_bounded(QUEUE_CAPACITY, OverflowPolicy::DropOldest)?;
+	let mut ai_stream = ai_chan.make_async_bounded(QUEUE_CAPACITY, OverflowPolicy::DropOldest)?;
+
+	let queue_stats = QueueMonitor {
+		adc: ai_stream.stats(),
+		enc: encoder_stream.stats(),
+		last_dropped: Cell::new(0),
+	};
+
+	// Arm both tasks, they start together on the first edge of the shared sample clock
+	nidaqmx::launch_synchronized(&mut [&mut ai_stream, &mut encoder_stream])?;
+
+	// Note the alignment in the encoder file
+	writeln!(
+		enc_file,
+		"%adc and encoder streams started on the same sample clock edge (sample numbers match)"
+	)
+	.expect("Failed to write alignment to encoder file");
+
+	// Both streams run on the encoder's sample clock, so they share its time axis
+	let time_axis = encoder_stream.channel().time_axis();
+	write_time_axis(&mut adc_file, &time_axis);
+	write_time_axis(&mut enc_file, &time_axis);
 
-	let delay_sample_count = ((delay.as_micros() as f32)/1000_f32).round();
-
-	// Write the recorded time difference to the encoder file
-	writeln!(enc_file, "%adc stream late by: {} ns ({} samples)", delay.as_nanos(), delay_sample_count).expect("Failed to write time difference to encoder file");
-	
 	// Start the streams
-	let encoder_stream = encoder_stream
-		.bifurcate(UPDATE_UI_SAMP_COUNT, move |data| {
+	let encoder_task = async move {
+		let mut readings = encoder_stream.bifurcate(UPDATE_UI_SAMP_COUNT, move |data| {
 			enc_plot_data.pos.store(data.pos, atomic::Ordering::Relaxed);
-		})
-		.map(move |data| writeln!(enc_file, "{}", data).expect("Failed to write data"))
-		.for_each(|_| future::ok(()));
-	
-	let ai_stream = ai_stream
-		.bifurcate(UPDATE_UI_SAMP_COUNT, move |data| {
+		});
+
+		while let Some(data) = readings.try_next().await? {
+			writeln!(enc_file, "{}", data).expect("Failed to write data");
+		}
+
+		Ok(())
+	};
+
+	let ai_task = async move {
+		let mut scans = ai_stream.bifurcate(UPDATE_UI_SAMP_COUNT, move |data| {
 			let pos = adc_plot_data.pos.load(atomic::Ordering::Relaxed);
-			let tstamp = adc_plot_data.start_t.elapsed().as_millis() as f64 / 1e3;
-			ui::WindowHandle::append_to_chart(tstamp, data.data[0], data.data[1], pos);
-		})
-		.map(move |mut data| 
-		{
-			data.timestamp += delay_sample_count as u64;
-			writeln!(adc_file, "{}", data).expect("Failed to write data")
-		})
-		.for_each(|_| future::ok(()));
+			ui::WindowHandle::append_to_chart(data.time, data.data[0], data.data[1], pos);
+			queue_stats.update_ui();
+		});
+
+		while let Some(data) = scans.try_next().await? {
+			writeln!(adc_file, "{}", data).expect("Failed to write data");
+		}
+
+		Ok(())
+	};
+
+	// Either stream failing, e.g. because the DAQ was unplugged, ends the whole recording
+	let data_task = async move { future::try_join(ai_task, encoder_task).await.map(|_| ()) };
+	Ok(DataCollectionHandle::start(data_task))
+}
+
+fn daq_backend() -> Backend {
+	if std::env::var_os(SIMULATE_ENV_VAR).is_some() {
+		log::warn!("{} is set, using a simulated DAQ", SIMULATE_ENV_VAR);
+		nidaqmx::simulated(SimulatedDevice::default())
+	} else {
+		nidaqmx::ni_daqmx()
+	}
+}
+
+// Picks the DAQ by the serial number in DAQ_SERIAL_ENV_VAR, or the only one installed
+fn select_device(backend: &dyn DaqBackend) -> Result<DeviceInfo, CollectionError> {
+	let mut devices = nidaqmx::list_devices_with_backend(backend)?;
+
+	for device in &devices {
+		log::info!(
+			"Found {} ({}, serial number {:X})",
+			device.name,
+			device.product_type,
+			device.serial_number
+		);
+	}
+
+	let serial = match std::env::var(DAQ_SERIAL_ENV_VAR) {
+		Ok(serial) => Some(
+			u32::from_str_radix(serial.trim(), 16)
+				.map_err(|_| CollectionError::InvalidSerial(serial))?,
+		),
+		Err(_) => None,
+	};
+
+	let device = match serial {
+		Some(serial) => devices
+			.into_iter()
+			.find(|device| device.serial_number == serial)
+			.ok_or(CollectionError::DeviceNotFound(Some(serial)))?,
+		None if devices.len() > 1 => return Err(CollectionError::AmbiguousDevice(devices)),
+		None => devices.pop().ok_or(CollectionError::DeviceNotFound(None))?,
+	};
+
+	log::info!("Using {}", device.name);
+
+	Ok(device)
+}
+
+// Shows how far the file writers lag behind the DAQ, and warns when samples get dropped
+struct QueueMonitor {
+	adc: Arc<QueueStats>,
+	enc: Arc<QueueStats>,
+	last_dropped: Cell<u64>,
+}
 
-	let data_stream = ai_stream.join(encoder_stream).map(|_| ()).map_err(|_| ());
-	Some(DataCollectionHandle::start(data_stream))
+impl QueueMonitor {
+	fn update_ui(&self) {
+		let dropped = self.adc.dropped() + self.enc.dropped();
+		if dropped > self.last_dropped.get() {
+			log::warn!(
+				"Data is not being written fast enough, {} samples dropped so far",
+				dropped
+			);
+			self.last_dropped.set(dropped);
+		}
+
+		// The backlog also counts what's still waiting in the DAQmx buffers
+		let status = format!(
+			"Backlog: adc {} (queue peak {}, {} dropped), enc {} (queue peak {}, {} dropped)",
+			self.adc.backlog(),
+			self.adc.high_water(),
+			self.adc.dropped(),
+			self.enc.backlog(),
+			self.enc.high_water(),
+			self.enc.dropped()
+		);
+		ui::WindowHandle::update_buffer_status(&status);
+	}
 }
 
 pub struct DataCollectionHandle {
-	stop_handle: oneshot::Sender<()>,
+	cancel: CancellationToken,
 	thread_handle: thread::JoinHandle<bool>,
 }
 
 impl DataCollectionHandle {
 	fn start<F>(fut: F) -> Self
 	where
-		F: Future<Item = (), Error = ()> + Send + 'static,
+		F: Future<Output = Result<(), DaqError>> + Send + 'static,
 	{
-		let (snd, recv) = oneshot::channel::<()>();
-		let new_fut = fut.select(recv.map_err(|_| ()));
+		let cancel = CancellationToken::new();
+		let cancelled = cancel.clone();
 
 		let thrd = thread::Builder::new().name("Data Collection Driver".to_string());
 
 		let thread_handle = thrd
-			.spawn(move || CurrentThread::new().block_on(new_fut).is_err())
+			.spawn(move || {
+				let runtime = tokio::runtime::Builder::new_current_thread()
+					.build()
+					.expect("Failed to build data collection runtime");
+
+				// Dropping the pipeline on cancellation drops the streams, which stops the tasks
+				let result = runtime.block_on(async move {
+					tokio::select! {
+						result = fut => result,
+						_ = cancelled.cancelled() => Ok(()),
+					}
+				});
+
+				// Nobody pressed stop, so tell the operator why the recording ended
+				if let Err(err) = &result {
+					log::error!("Data collection stopped, {}", err);
+					ui::WindowHandle::collection_failed();
+				}
+
+				result.is_err()
+			})
 			.expect("Failed to spawn data collection thread");
 
 		log::info!("Started data collection");
 		Self {
-			stop_handle: snd,
+			cancel,
 			thread_handle,
 		}
 	}
 
 	pub fn stop(self) {
 		log::debug!("Sent abort signal");
-		let _ = self.stop_handle.send(());
+		self.cancel.cancel();
 
 		let thread_status = self.thread_handle.join();
 
 		match thread_status {
 			Ok(false) => log::info!("Data collection stopped"),
 			Ok(true) => log::info!("Data collection stopped with error"),
-			Err(error) => log::error!("{:#?}",error)
+			Err(error) => log::error!("{:#?}", error),
 		};
 	}
 }
 
-fn open_buffered_file(fpath: &mut PathBuf, name: &str) -> Option<BufWriter<File>> {
+fn open_buffered_file(
+	fpath: &mut PathBuf,
+	name: &str,
+	device: &DeviceInfo,
+	hardware_check: Option<&HardwareCheck>,
+) -> Option<BufWriter<File>> {
 	const BUF_CAPACITY: usize = 1024 * 1024; // 1 Mb
 
 	let tm = time::now();
@@ -141,26 +430,56 @@ fn open_buffered_file(fpath: &mut PathBuf, name: &str) -> Option<BufWriter<File>
 		.create_new(true)
 		.open(&fpath)
 		.ok()?;
-		
+
 	let mut file = BufWriter::with_capacity(BUF_CAPACITY, file);
 
 	let _ = writeln!(&mut file, "%{}", tm.rfc822());
-	// let _ = writeln!(&mut file, "%Initial Timestamp: {} ns", get_steady_time_nanoseconds());
+	let _ = writeln!(
+		&mut file,
+		"%Device: {} ({}, serial number {:X})",
+		device.name, device.product_type, device.serial_number
+	);
+	match hardware_check {
+		Some(check) => {
+			let _ = writeln!(
+				&mut file,
+				"%Hardware check: reset and passed self-test, {}",
+				check.time.rfc822()
+			);
+			let _ = writeln!(&mut file, "%Calibration: {}", check.calibration);
+		}
+		None => {
+			let _ = writeln!(&mut file, "%Hardware check: not run");
+		}
+	}
 
 	// Add data information to top of files
-	if name == "adc"
-	{
+	if name == "adc" {
 		let _ = writeln!(&mut file, "%Target Sample Rate: {} hz", SAMPLING_RATE);
-		let _ = writeln!(&mut file, "%sampleNumber, grasperLoadCell1, grasperLoadCell2");
-		let _ = writeln!(&mut file, "%[count], [V], [V]");
-		let _ = writeln!(&mut file, "%[V]olts resolve to approximately 0.5 kg/V (see calibration data for today's date)");
-	}
-	else if name == "enc"
-	{
+		let names: Vec<_> = ANALOG_SENSORS.iter().map(|sensor| sensor.name).collect();
+		let units: Vec<_> = ANALOG_SENSORS.iter().map(|_| "[V]").collect();
+		let ranges: Vec<_> = ANALOG_SENSORS
+			.iter()
+			.map(|sensor| format!("{} to {} V", sensor.min_val, sensor.max_val))
+			.collect();
+
+		let _ = writeln!(&mut file, "%sampleNumber, time, {}", names.join(", "));
+		let _ = writeln!(&mut file, "%[count], [s], {}", units.join(", "));
+		let _ = writeln!(&mut file, "%Input ranges: {}", ranges.join(", "));
+		let _ = writeln!(
+			&mut file,
+			"%[V]olts resolve to approximately 0.5 kg/V (see calibration data for today's date)"
+		);
+	} else if name == "enc" {
 		let _ = writeln!(&mut file, "%Target Sample Rate: {} hz", SAMPLING_RATE);
-		let _ = writeln!(&mut file, "%sampleNumber, encoderCount");
-		let _ = writeln!(&mut file, "%[count], [count]");
-		let _ = writeln!(&mut file, "%AMT102-V CUI Encoder used. 8192 ticks per 360 degrees (x4 factor included).");
+		let _ = writeln!(&mut file, "%sampleNumber, time, encoderCount");
+		let _ = writeln!(&mut file, "%[count], [s], [count]");
+		let _ = writeln!(
+			&mut file,
+			"%AMT102-V CUI Encoder used. {} ticks per 360 degrees ({:?} decoding included).",
+			ENCODER_CONFIG.ticks_per_rev(),
+			ENCODER_CONFIG.decoding
+		);
 	}
 
 	log::debug!("File created: {}", fpath.display());
@@ -168,7 +487,11 @@ fn open_buffered_file(fpath: &mut PathBuf, name: &str) -> Option<BufWriter<File>
 	Some(file)
 }
 
-fn prepare_files(fpath: &mut PathBuf) -> Option<(BufWriter<File>, BufWriter<File>)> {
+fn prepare_files(
+	fpath: &mut PathBuf,
+	device: &DeviceInfo,
+	hardware_check: Option<&HardwareCheck>,
+) -> Option<(BufWriter<File>, BufWriter<File>)> {
 	if fpath.exists() {
 		return None;
 	}
@@ -177,18 +500,28 @@ fn prepare_files(fpath: &mut PathBuf) -> Option<(BufWriter<File>, BufWriter<File
 
 	fpath.push("gibberish/");
 
-	let adc_file = open_buffered_file(fpath, "adc")?;
-	let enc_file = open_buffered_file(fpath, "enc")?;
+	let adc_file = open_buffered_file(fpath, "adc", device, hardware_check)?;
+	let enc_file = open_buffered_file(fpath, "enc", device, hardware_check)?;
 
 	log::info!("Created files");
 
 	Some((adc_file, enc_file))
 }
 
+// The time column comes from the coerced rate, the start time ties it to the host clock
+fn write_time_axis(file: &mut BufWriter<File>, time_axis: &TimeAxis) {
+	writeln!(file, "%Actual Sample Rate: {} hz", time_axis.rate)
+		.expect("Failed to write sample rate");
+	if let Some(start) = time_axis.start {
+		writeln!(file, "%Start Time: {} ns", start).expect("Failed to write start time");
+	}
+}
+
+// Passes every n-th item that made it through the stream to `f` on the way by
 struct Bifurcate<S, F>
 where
-	S: Stream,
-	F: Fn(&S::Item),
+	S: TryStream,
+	F: Fn(&S::Ok),
 {
 	inner: S,
 	state: usize,
@@ -198,30 +531,31 @@ where
 
 impl<S, F> Stream for Bifurcate<S, F>
 where
-	S: Stream + Unpin,
-	F: Fn(&S::Item) + Unpin,
+	S: TryStream + Unpin,
+	F: Fn(&S::Ok) + Unpin,
 {
-	type Item = S::Item;
-	type Error = S::Error;
+	type Item = Result<S::Ok, S::Error>;
 
-	fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-		let item = futures::try_ready!(self.inner.poll());
+	fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let item = futures::ready!(self.inner.try_poll_next_unpin(cx));
 
-		if self.state % self.n == 0 {
-			item.as_ref().map(&self.f);
-		}
+		if let Some(Ok(data)) = &item {
+			if self.state % self.n == 0 {
+				(self.f)(data);
+			}
 
-		self.state += 1;
+			self.state += 1;
+		}
 
-		Ok(futures::Async::Ready(item))
+		Poll::Ready(item)
 	}
 }
 
-trait StreamBifurcate: Stream {
+trait StreamBifurcate: TryStream {
 	fn bifurcate<F>(self, n: usize, f: F) -> Bifurcate<Self, F>
 	where
 		Self: Sized,
-		F: Fn(&Self::Item),
+		F: Fn(&Self::Ok),
 	{
 		Bifurcate {
 			inner: self,
@@ -232,17 +566,15 @@ trait StreamBifurcate: Stream {
 	}
 }
 
-impl<T: Stream> StreamBifurcate for T {}
+impl<T: TryStream> StreamBifurcate for T {}
 
 struct LatestSensorData {
-	start_t: Instant,
 	pos: Atomic<i32>,
 }
 
 impl LatestSensorData {
 	fn new() -> Self {
 		Self {
-			start_t: Instant::now(),
 			pos: Default::default(),
 		}
 	}
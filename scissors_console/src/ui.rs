@@ -6,7 +6,7 @@ use std::sync::{Mutex, Arc};
 use std::thread;
 use std::time as std_time;
 
-use crate::data_collection::{self, DataCollectionHandle};
+use crate::data_collection::{self, DataCollectionHandle, HardwareCheck};
 use nativefiledialog_rs as nfd;
 
 pub struct WindowHandle;
@@ -22,6 +22,8 @@ pub struct App {
 	folder_path: PathBuf,
 	data_collection_handle: Option<DataCollectionHandle>,
 	beeper_stop_flag: Arc<Mutex<bool>>,
+	// Last successful hardware check, written into the headers of the following recordings
+	hardware_check: Option<HardwareCheck>,
 }
 
 enum UiEventVariant<'a> {
@@ -30,6 +32,7 @@ enum UiEventVariant<'a> {
 	Stop,
 	ClearLog,
 	ChooseDir,
+	CheckHardware,
 	Unknown(&'a str),
 }
 
@@ -61,6 +64,15 @@ impl WindowHandle {
 		let js = format!("append_to_chart({},{},{},{})", time, force1, force2, pos);
 		Self::eval(js)
 	}
+
+	pub fn update_buffer_status(status: &str) {
+		Self::eval(format!("update_buffer_status({})", js_string(status)))
+	}
+
+	// Goes through the stop button, so the beeper stops and a new recording can be started
+	pub fn collection_failed() {
+		Self::eval("collection_failed()".to_string())
+	}
 }
 
 impl WindowLogger {
@@ -92,14 +104,38 @@ impl log::Log for WindowLogger {
 
 		// Only sometimes log to the ui
 		if self.enabled(record.metadata()) {
-			let js = format!(r#"append_to_log("{}\t{}\t{}\n")"#, level, time_fmt, args);
-			WindowHandle::eval(js);
+			let line = format!("{}\t{}\t{}\n", level, time_fmt, args);
+			WindowHandle::eval(format!("append_to_log({})", js_string(&line)));
 		}
 	}
 
 	fn flush(&self) {}
 }
 
+// Quotes `text` as a JS string literal, DAQmx error messages come with quotes and line breaks
+fn js_string(text: &str) -> String {
+	let mut quoted = String::with_capacity(text.len() + 2);
+	quoted.push('"');
+
+	for c in text.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			// Other control characters, and the line separators JS doesn't allow in strings
+			c if c.is_control() || c == '\u{2028}' || c == '\u{2029}' => {
+				quoted.push_str(&format!("\\u{:04x}", c as u32))
+			}
+			c => quoted.push(c),
+		}
+	}
+
+	quoted.push('"');
+	quoted
+}
+
 impl App {
 	pub fn new() -> Self {
 		let tmp_dir = std::env::temp_dir();
@@ -108,6 +144,7 @@ impl App {
 			folder_path: tmp_dir,
 			data_collection_handle: None,
 			beeper_stop_flag,
+			hardware_check: None,
 		}
 	}
 
@@ -148,6 +185,7 @@ impl<'a> From<&'a str> for UiEventVariant<'a> {
 			"stop" => UiEventVariant::Stop,
 			"clear_log" => UiEventVariant::ClearLog,
 			"choose_dir" => UiEventVariant::ChooseDir,
+			"check_hardware" => UiEventVariant::CheckHardware,
 			msg => UiEventVariant::Unknown(msg),
 		}
 	}
@@ -174,6 +212,7 @@ impl UiEvent<'_> {
 			UiEventVariant::Stop => self.stop(),
 			UiEventVariant::ClearLog => self.clear_log(),
 			UiEventVariant::ChooseDir => self.choose_dir(),
+			UiEventVariant::CheckHardware => self.check_hardware(),
 			UiEventVariant::Unknown(msg) => self.unknown(msg),
 		};
 	}
@@ -213,9 +252,15 @@ impl UiEvent<'_> {
 		fpath.push(file);
 
 		if col_handle.is_none() {
-			*col_handle = data_collection::start(&mut fpath);
+			match data_collection::start(&mut fpath, self.app.hardware_check.as_ref()) {
+				Ok(handle) => *col_handle = Some(handle),
+				Err(err) => {
+					// Nothing was started, so don't start the beeper either
+					log::error!("{}", err);
+					return;
+				}
+			}
 
-			
             // Create a new Arc
             let c_stop_flag = Arc::clone(&self.app.beeper_stop_flag);
 
@@ -292,11 +337,6 @@ impl UiEvent<'_> {
                     if App::read_flag(&c_stop_flag) {break;};	// check for stop button
                 }
             });
-
-			// If its still none, that means there was a file open error
-			if col_handle.is_none() {
-				log::error!("File '{}' already exists", fpath.display());
-			}
 		}
 	}
 
@@ -340,6 +380,27 @@ impl UiEvent<'_> {
 		self.app.update_folder_path(&self.window, folder);
 	}
 
+	fn check_hardware(self) {
+		log::debug!("Check hardware button pressed");
+
+		// Resetting the DAQ would abort the recording
+		if self.app.data_collection_handle.is_some() {
+			log::warn!("Stop the recording before checking the hardware");
+			return;
+		}
+
+		match data_collection::check_hardware() {
+			Ok(check) => {
+				log::info!("Hardware check passed");
+				self.app.hardware_check = Some(check);
+			}
+			Err(err) => {
+				log::error!("Hardware check failed, {}", err);
+				self.app.hardware_check = None;
+			}
+		}
+	}
+
 	fn unknown(self, msg: &str) {
 		log::error!("Unrecognized message: {}", msg);
 	}
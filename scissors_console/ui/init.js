@@ -80,6 +80,14 @@ function clear_log() {
 	LOG_DATA.value = "";
 }
 
+function update_buffer_status(status) {
+	document.getElementById("bufferStatus").textContent = status;
+}
+
+function collection_failed() {
+	tether("stop");
+}
+
 function update_folder_path(folder) {
 	document.getElementById("inputFolderPath").value = folder.toString();
 }
@@ -129,6 +137,10 @@ document.getElementById("btnStop").onclick = () => {
 	tether("stop");
 }
 
+document.getElementById("btnCheckHardware").onclick = () => {
+	tether("check_hardware");
+}
+
 document.getElementById("btnClearLog").onclick = () => {
 	tether("clear_log");
 }